@@ -0,0 +1,70 @@
+// times every script in benches/scripts with the built binary, under both backends
+// usage: cargo bench [-- name-filter]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+fn main() {
+    // cargo passes `--bench`; anything else is a filter on script names
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/scripts");
+    let mut scripts: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("failed to read benches/scripts")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rtn"))
+        .collect();
+    scripts.sort();
+
+    println!(
+        "{:<12} {:>10} {:>10} {:>10} {:>10}",
+        "script", "tree best", "median", "vm best", "median"
+    );
+    for script in scripts {
+        let name = script.file_stem().unwrap().to_string_lossy().to_string();
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+
+        let tree = time_runs(&script, &[]);
+        let vm = time_runs(&script, &["--vm"]);
+        println!(
+            "{:<12} {:>8.1}ms {:>8.1}ms {:>8.1}ms {:>8.1}ms",
+            name,
+            millis(tree[0]),
+            millis(tree[RUNS / 2]),
+            millis(vm[0]),
+            millis(vm[RUNS / 2])
+        );
+    }
+}
+
+// sorted durations of `RUNS` runs
+fn time_runs(script: &Path, flags: &[&str]) -> Vec<Duration> {
+    let mut times: Vec<Duration> = (0..RUNS).map(|_| time_run(script, flags)).collect();
+    times.sort();
+    times
+}
+
+fn time_run(script: &Path, flags: &[&str]) -> Duration {
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_ruten"))
+        .args(flags)
+        .arg(script)
+        .output()
+        .expect("failed to execute ruten");
+    let elapsed = start.elapsed();
+
+    if !output.status.success() {
+        eprintln!("{} failed:\n{}", script.display(), String::from_utf8_lossy(&output.stderr));
+        process::exit(1);
+    }
+    elapsed
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,276 @@
+// functions and error types every script can use without importing anything
+
+use crate::dict::key_set;
+use crate::error::{ErrorKind, RutenError};
+use crate::int::Int;
+use crate::interpreter::{iterate, type_name, Value};
+use crate::methods;
+use std::collections::HashMap;
+
+pub fn create_builtins() -> HashMap<String, Value> {
+    let mut builtins = HashMap::new();
+
+    // catchable error types, e.g. `except TypeError as e:` or `raise RuntimeError("...")`
+    for kind in ErrorKind::ALL {
+        builtins.insert(kind.name().to_string(), Value::ErrorType(kind));
+    }
+
+    // print function
+    builtins.insert(
+        "print".to_string(),
+        Value::native(|_, args| {
+            let output = args
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!("{}", output);
+            Ok(Value::None)
+        }),
+    );
+
+    // len function
+    builtins.insert(
+        "len".to_string(),
+        Value::native(|_, args| {
+            if args.len() != 1 {
+                return Err(RutenError::runtime_error(
+                    "len() takes exactly 1 argument".to_string(),
+                ));
+            }
+            match &args[0] {
+                Value::String(s) => Ok(Value::Int(Int::from(s.chars().count()))),
+                Value::List(l) => Ok(Value::Int(Int::from(l.borrow().len()))),
+                Value::Tuple(items) => Ok(Value::Int(Int::from(items.len()))),
+                Value::Set(items) => Ok(Value::Int(Int::from(items.borrow().len()))),
+                Value::Dict(d) => Ok(Value::Int(Int::from(d.borrow().len()))),
+                _ => Err(RutenError::type_error(
+                    "len() argument must be a string, list, tuple, dict, or set".to_string(),
+                )),
+            }
+        }),
+    );
+
+    // range function
+    builtins.insert(
+        "range".to_string(),
+        Value::native(|_, args| {
+            if args.is_empty() || args.len() > 3 {
+                return Err(RutenError::runtime_error(
+                    "range() takes 1 to 3 arguments".to_string(),
+                ));
+            }
+            
+            let mut bounds = Vec::with_capacity(3);
+            for arg in args {
+                match arg {
+                    Value::Int(n) => bounds.push(n.clone()),
+                    _ => {
+                        return Err(RutenError::type_error(
+                            "range() arguments must be integers".to_string(),
+                        ))
+                    }
+                }
+            }
+            let (start, end, step) = match bounds.as_slice() {
+                [end] => (Int::Small(0), end.clone(), Int::Small(1)),
+                [start, end] => (start.clone(), end.clone(), Int::Small(1)),
+                [start, end, step] => (start.clone(), end.clone(), step.clone()),
+                _ => unreachable!(),
+            };
+            if step.is_zero() {
+                return Err(RutenError::runtime_error(
+                    "range() step must not be zero".to_string(),
+                ));
+            }
+
+            let mut result = Vec::new();
+            let mut current = start;
+            while (!step.is_negative() && current < end) || (step.is_negative() && current > end) {
+                let next = current.add(&step);
+                result.push(Value::Int(current));
+                current = next;
+            }
+            Ok(Value::list(result))
+        }),
+    );
+
+    // str function
+    builtins.insert(
+        "str".to_string(),
+        Value::native(|_, args| {
+            if args.len() != 1 {
+                return Err(RutenError::runtime_error(
+                    "str() takes exactly 1 argument".to_string(),
+                ));
+            }
+            Ok(Value::String(args[0].to_string()))
+        }),
+    );
+
+    // int function
+    builtins.insert(
+        "int".to_string(),
+        Value::native(|_, args| {
+            if args.len() != 1 {
+                return Err(RutenError::runtime_error(
+                    "int() takes exactly 1 argument".to_string(),
+                ));
+            }
+            match &args[0] {
+                Value::Int(n) => Ok(Value::Int(n.clone())),
+                Value::Number(n) => Int::from_f64(*n).map(Value::Int).ok_or_else(|| {
+                    RutenError::runtime_error(format!("cannot convert {} to int", n))
+                }),
+                // digits convert exactly; other numbers are truncated
+                Value::String(s) => {
+                    let s = s.trim();
+                    Int::parse(s)
+                        .or_else(|| s.parse::<f64>().ok().and_then(Int::from_f64))
+                        .map(Value::Int)
+                        .ok_or_else(|| RutenError::runtime_error("invalid literal for int()".to_string()))
+                }
+                _ => Err(RutenError::type_error(
+                    "int() argument must be a number or string".to_string(),
+                )),
+            }
+        }),
+    );
+
+    // float function
+    builtins.insert(
+        "float".to_string(),
+        Value::native(|_, args| {
+            if args.len() != 1 {
+                return Err(RutenError::runtime_error(
+                    "float() takes exactly 1 argument".to_string(),
+                ));
+            }
+            match &args[0] {
+                Value::Number(_) | Value::Int(_) => Ok(Value::Number(args[0].as_f64().unwrap_or_default())),
+                Value::String(s) => s
+                    .parse::<f64>()
+                    .map(Value::Number)
+                    .map_err(|_| RutenError::runtime_error("invalid literal for float()".to_string())),
+                _ => Err(RutenError::type_error(
+                    "float() argument must be a number or string".to_string(),
+                )),
+            }
+        }),
+    );
+
+    // set function: set() or set(iterable)
+    builtins.insert(
+        "set".to_string(),
+        Value::native(|_, args| {
+            if args.len() > 1 {
+                return Err(RutenError::runtime_error(
+                    "set() takes at most 1 argument".to_string(),
+                ));
+            }
+            let items = match args.first() {
+                None => Vec::new(),
+                Some(value) => iterate(value).ok_or_else(|| {
+                    RutenError::type_error(format!("set() requires an iterable, got {}", type_name(value)))
+                })?,
+            };
+            Ok(Value::set(key_set(items)?))
+        }),
+    );
+
+    // map function
+    builtins.insert(
+        "map".to_string(),
+        Value::native(|ctx, args| {
+            let (func, items) = callback_args("map", args)?;
+            let mut result = Vec::with_capacity(items.len());
+            for item in items {
+                result.push(ctx.call(func, vec![item])?);
+            }
+            Ok(Value::list(result))
+        }),
+    );
+
+    // filter function
+    builtins.insert(
+        "filter".to_string(),
+        Value::native(|ctx, args| {
+            let (func, items) = callback_args("filter", args)?;
+            let mut result = Vec::new();
+            for item in items {
+                if ctx.call(func, vec![item.clone()])?.is_truthy() {
+                    result.push(item);
+                }
+            }
+            Ok(Value::list(result))
+        }),
+    );
+
+    // reduce function: reduce(fn, items) or reduce(fn, items, initial)
+    builtins.insert(
+        "reduce".to_string(),
+        Value::native(|ctx, args| {
+            if args.len() != 2 && args.len() != 3 {
+                return Err(RutenError::runtime_error(
+                    "reduce() takes 2 or 3 arguments".to_string(),
+                ));
+            }
+            let (func, items) = callback_args("reduce", &args[..2])?;
+            let mut items = items.into_iter();
+            let mut acc = match args.get(2) {
+                Some(initial) => initial.clone(),
+                None => items.next().ok_or_else(|| {
+                    RutenError::type_error("reduce() of empty sequence with no initial value".to_string())
+                })?,
+            };
+            for item in items {
+                acc = ctx.call(func, vec![acc, item])?;
+            }
+            Ok(acc)
+        }),
+    );
+
+    // sorted function: sorted(items, key=None, reverse=False)
+    builtins.insert(
+        "sorted".to_string(),
+        Value::native_with_keywords(&["key", "reverse"], |ctx, args| {
+            if args.is_empty() || args.len() > 2 {
+                return Err(RutenError::runtime_error(
+                    "sorted() takes 1 or 2 arguments".to_string(),
+                ));
+            }
+            let mut items = iterate(&args[0]).ok_or_else(|| {
+                RutenError::type_error(format!("sorted() requires an iterable, got {}", type_name(&args[0])))
+            })?;
+            let key = ctx.kwarg("key").or_else(|| args.get(1).cloned());
+            match &key {
+                None | Some(Value::None) => methods::sort_values(&mut items)?,
+                Some(key) => {
+                    let mut pairs = Vec::with_capacity(items.len());
+                    for item in items {
+                        pairs.push((ctx.call(key, vec![item.clone()])?, item));
+                    }
+                    methods::sort_keyed(&mut pairs)?;
+                    items = pairs.into_iter().map(|(_, item)| item).collect();
+                }
+            }
+            if ctx.kwarg("reverse").is_some_and(|r| r.is_truthy()) {
+                items.reverse();
+            }
+            Ok(Value::list(items))
+        }),
+    );
+
+    builtins
+}
+
+// splits the `(function, iterable)` arguments shared by map, filter and reduce
+fn callback_args<'a>(name: &str, args: &'a [Value]) -> Result<(&'a Value, Vec<Value>), RutenError> {
+    if args.len() != 2 {
+        return Err(RutenError::runtime_error(format!("{}() takes exactly 2 arguments", name)));
+    }
+    let items = iterate(&args[1]).ok_or_else(|| {
+        RutenError::type_error(format!("{}() requires an iterable, got {}", name, type_name(&args[1])))
+    })?;
+    Ok((&args[0], items))
+}
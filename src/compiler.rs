@@ -0,0 +1,1213 @@
+// compiles a parsed program to bytecode for the vm; variables are resolved here,
+// once, to local slots, closure cells or global slots
+
+use crate::error::{RutenError, Span};
+use crate::interpreter::Value;
+use crate::parser::{
+    Arg, ComprehensionClause, ComprehensionKind, Expr, ExprKind, FStringPart, Param, Program, Stmt, StmtKind,
+};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Constant(usize),
+    LoadLocal(usize),
+    StoreLocal(usize),
+    LoadCell(usize),
+    StoreCell(usize),
+    /// a slot of the module's globals, numbered across every function in the module
+    LoadGlobal(usize),
+    StoreGlobal(usize),
+    Pop,
+    Dup,
+    Binary(crate::parser::BinaryOp),
+    Unary(crate::parser::UnaryOp),
+    Jump(usize),
+    JumpIfFalse(usize),
+    BuildList(usize),
+    BuildTuple(usize),
+    BuildDict(usize),
+    BuildSet(usize),
+    /// pops a value and appends it to the list `n` values below
+    ListAppend(usize),
+    /// pops a key and value and inserts them in the dict `n` values below
+    DictInsert(usize),
+    /// pops a value and adds it to the set `n` values below
+    SetAdd(usize),
+    /// replaces an iterable with its items for a destructuring assignment, the first
+    /// on top; the target at the optional position takes the leftovers as a list
+    Unpack(usize, Option<usize>),
+    /// `[value]` -> `[str]`, following the format spec constant, if any
+    FormatValue(Option<usize>),
+    /// joins the top `n` strings
+    BuildString(usize),
+    /// `[object, index]` -> `[object[index]]`
+    Index,
+    /// `[object, start, stop, step]` -> `[object[start:stop:step]]`
+    Slice,
+    /// `[value, object, index]` -> `[]`
+    StoreIndex,
+    GetMember(usize),
+    /// `[value, object]` -> `[]`
+    StoreMember(usize),
+    /// positional argument count
+    Call(usize),
+    /// positional argument count and the keyword names, whose values follow the positionals
+    CallKw(usize, usize),
+    /// pops one value per parameter with a default
+    MakeFunction(usize),
+    Return,
+    /// replaces an iterable with a snapshot of its items and a position
+    GetIter,
+    /// pushes the next item, or drops the iterator and jumps once it is exhausted
+    ForIter(usize),
+    /// errors raised until the matching `PopExcept` jump to the handler with the error pushed
+    SetupExcept(usize),
+    PopExcept,
+    /// `[error, kind]` -> `[error]`, jumping unless `kind` catches the error
+    MatchError(usize),
+    /// pops the caught error and makes it the one a bare `raise` re-raises
+    EnterHandler,
+    ExitHandler,
+    Raise,
+    /// bare `raise`
+    RaiseCurrent,
+    /// pops a caught error and raises it again unchanged
+    ReRaise,
+    Import(usize),
+    /// the member named by the first operand of the module on top of the stack,
+    /// whose name is the second
+    ImportName(usize, usize),
+}
+
+/// a compiled function body, or a whole module
+#[derive(Debug)]
+pub struct Code {
+    pub name: String,
+    pub file: String,
+    pub params: Rc<[Param]>,
+    pub ops: Vec<Op>,
+    /// source location of each op, for errors
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub keywords: Vec<Vec<String>>,
+    pub functions: Vec<Rc<Code>>,
+    /// parameters come first, one slot each
+    pub local_names: Vec<String>,
+    /// cells created by each call come first, then the ones captured from enclosing functions
+    pub cell_names: Vec<String>,
+    pub own_cells: usize,
+    /// parameters captured by nested functions: (local slot, cell)
+    pub cell_params: Vec<(usize, usize)>,
+    /// the enclosing function's cells this function captures, in order
+    pub captures: Vec<usize>,
+    /// the name of each global slot; only the module's code has them
+    pub globals: Vec<String>,
+}
+
+/// compiles the script `file`
+pub fn compile(program: &Program, file: &str) -> Result<Rc<Code>, RutenError> {
+    let mut compiler = Compiler {
+        file: file.to_string(),
+        scopes: vec![Scope::new("<module>", file, Rc::from(Vec::new()), None)],
+        globals: HashMap::new(),
+    };
+    compiler.block(program)?;
+    let span = program.last().map(|stmt| stmt.span).unwrap_or_default();
+    let none = compiler.constant(Value::None);
+    compiler.emit(Op::Constant(none), span);
+    compiler.emit(Op::Return, span);
+
+    let mut code = compiler.scopes.pop().unwrap().code;
+    code.globals = vec![String::new(); compiler.globals.len()];
+    for (name, slot) in compiler.globals {
+        code.globals[slot] = name;
+    }
+    Ok(Rc::new(code))
+}
+
+enum Var {
+    Local(usize),
+    Cell(usize),
+    Global(usize),
+}
+
+// statements a `return`, `break` or `continue` may have to leave
+enum Block {
+    Loop {
+        start: usize,
+        breaks: Vec<usize>,
+        // a `for` keeps its iterator on the stack
+        is_for: bool,
+    },
+    Try,
+    Handler,
+    Finally(Vec<Stmt>),
+    // running the finally body on the way out of an error, which sits on the stack
+    FinallyError,
+}
+
+struct Scope {
+    code: Code,
+    // None for the module, where every name is global
+    names: Option<Names>,
+    cells: HashMap<String, usize>,
+    locals: HashMap<String, usize>,
+    free: Vec<String>,
+    blocks: Vec<Block>,
+}
+
+impl Scope {
+    fn new(name: &str, file: &str, params: Rc<[Param]>, names: Option<Names>) -> Self {
+        Scope {
+            code: Code {
+                name: name.to_string(),
+                file: file.to_string(),
+                params,
+                ops: Vec::new(),
+                spans: Vec::new(),
+                constants: Vec::new(),
+                names: Vec::new(),
+                keywords: Vec::new(),
+                functions: Vec::new(),
+                local_names: Vec::new(),
+                cell_names: Vec::new(),
+                own_cells: 0,
+                cell_params: Vec::new(),
+                captures: Vec::new(),
+                globals: Vec::new(),
+            },
+            names,
+            cells: HashMap::new(),
+            locals: HashMap::new(),
+            free: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+}
+
+struct Compiler {
+    file: String,
+    scopes: Vec<Scope>,
+    // the slot of each global name used anywhere in the module
+    globals: HashMap<String, usize>,
+}
+
+impl Compiler {
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        let code = &mut self.scope().code;
+        code.ops.push(op);
+        code.spans.push(span);
+        code.ops.len() - 1
+    }
+
+    fn here(&mut self) -> usize {
+        self.scope().code.ops.len()
+    }
+
+    // points the jump at `at` to the next op
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.scope().code.ops[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t) | Op::ForIter(t) | Op::SetupExcept(t) | Op::MatchError(t) => {
+                *t = target
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        let constants = &mut self.scope().code.constants;
+        constants.push(value);
+        constants.len() - 1
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        let names = &mut self.scope().code.names;
+        match names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        }
+    }
+
+    fn global(&mut self, name: &str) -> usize {
+        let next = self.globals.len();
+        *self.globals.entry(name.to_string()).or_insert(next)
+    }
+
+    fn resolve(&mut self, name: &str) -> Var {
+        let depth = self.scopes.len() - 1;
+        let scope = &self.scopes[depth];
+        let names = match &scope.names {
+            None => return Var::Global(self.global(name)),
+            Some(names) => names,
+        };
+        if names.globals.contains(name) {
+            return Var::Global(self.global(name));
+        }
+        if let Some(&cell) = scope.cells.get(name) {
+            return Var::Cell(cell);
+        }
+        if let Some(&slot) = scope.locals.get(name) {
+            return Var::Local(slot);
+        }
+        match self.capture(depth, name) {
+            Some(cell) => Var::Cell(cell),
+            None => Var::Global(self.global(name)),
+        }
+    }
+
+    // the cell of the scope at `depth` holding `name` bound by an enclosing function,
+    // capturing it through every function in between
+    fn capture(&mut self, depth: usize, name: &str) -> Option<usize> {
+        let scope = &self.scopes[depth];
+        if let Some(&cell) = scope.cells.get(name) {
+            return Some(cell);
+        }
+        if let Some(index) = scope.free.iter().position(|n| n == name) {
+            return Some(scope.code.own_cells + index);
+        }
+        if depth == 0 {
+            return None;
+        }
+        let enclosing = &self.scopes[depth - 1];
+        match &enclosing.names {
+            None => return None,
+            Some(names) if names.globals.contains(name) => return None,
+            Some(_) => {}
+        }
+        let outer = self.capture(depth - 1, name)?;
+
+        let scope = &mut self.scopes[depth];
+        scope.free.push(name.to_string());
+        scope.code.cell_names.push(name.to_string());
+        scope.code.captures.push(outer);
+        Some(scope.code.own_cells + scope.free.len() - 1)
+    }
+
+    fn load(&mut self, name: &str, span: Span) {
+        let op = match self.resolve(name) {
+            Var::Local(slot) => Op::LoadLocal(slot),
+            Var::Cell(cell) => Op::LoadCell(cell),
+            Var::Global(index) => Op::LoadGlobal(index),
+        };
+        self.emit(op, span);
+    }
+
+    fn store(&mut self, name: &str, span: Span) {
+        let op = match self.resolve(name) {
+            Var::Local(slot) => Op::StoreLocal(slot),
+            Var::Cell(cell) => Op::StoreCell(cell),
+            Var::Global(index) => Op::StoreGlobal(index),
+        };
+        self.emit(op, span);
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), RutenError> {
+        for stmt in stmts {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), RutenError> {
+        let span = stmt.span;
+        match &stmt.kind {
+            StmtKind::Import { module, alias } => {
+                let index = self.name(module);
+                self.emit(Op::Import(index), span);
+                self.store(alias.as_ref().unwrap_or(module), span);
+            }
+            StmtKind::FromImport { module, names } => {
+                let module_index = self.name(module);
+                self.emit(Op::Import(module_index), span);
+                for (name, alias) in names {
+                    let index = self.name(name);
+                    self.emit(Op::ImportName(index, module_index), span);
+                    self.store(alias.as_ref().unwrap_or(name), span);
+                }
+                self.emit(Op::Pop, span);
+            }
+            StmtKind::Assign { target, value } => {
+                self.expr(value)?;
+                self.store_target(target, span)?;
+            }
+            StmtKind::FunctionDef { name, params, body } => {
+                self.function(name, params, body, span)?;
+                self.store(name, span);
+            }
+            StmtKind::Return(value) => {
+                match value {
+                    Some(expr) => self.expr(expr)?,
+                    None => {
+                        let none = self.constant(Value::None);
+                        self.emit(Op::Constant(none), span);
+                    }
+                }
+                self.exit_blocks(0, span)?;
+                self.emit(Op::Return, span);
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition)?;
+                let to_else = self.emit(Op::JumpIfFalse(0), span);
+                self.block(then_branch)?;
+                match else_branch {
+                    Some(else_branch) => {
+                        let to_end = self.emit(Op::Jump(0), span);
+                        self.patch(to_else);
+                        self.block(else_branch)?;
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let start = self.here();
+                self.expr(condition)?;
+                let to_end = self.emit(Op::JumpIfFalse(0), span);
+                self.loop_body(start, false, body, span)?;
+                self.patch(to_end);
+                self.end_loop();
+            }
+            StmtKind::For { target, iterable, body } => {
+                self.expr(iterable)?;
+                self.emit(Op::GetIter, iterable.span);
+                let start = self.emit(Op::ForIter(0), span);
+                self.store_target(target, span)?;
+                self.loop_body(start, true, body, span)?;
+                self.patch(start);
+                self.end_loop();
+            }
+            StmtKind::Try {
+                body,
+                handlers,
+                finally_body,
+            } => self.try_stmt(body, handlers, finally_body.as_deref(), span)?,
+            StmtKind::Raise(value) => match value {
+                Some(expr) => {
+                    self.expr(expr)?;
+                    self.emit(Op::Raise, span);
+                }
+                None => {
+                    self.emit(Op::RaiseCurrent, span);
+                }
+            },
+            // the parser has checked `nonlocal` names are bound by an enclosing function
+            StmtKind::Global(_) | StmtKind::Nonlocal(_) => {}
+            StmtKind::Break => {
+                let depth = self.loop_depth("break", span)?;
+                self.exit_blocks(depth + 1, span)?;
+                if let Block::Loop { is_for: true, .. } = self.scope().blocks[depth] {
+                    self.emit(Op::Pop, span);
+                    self.emit(Op::Pop, span);
+                }
+                let jump = self.emit(Op::Jump(0), span);
+                if let Block::Loop { breaks, .. } = &mut self.scope().blocks[depth] {
+                    breaks.push(jump);
+                }
+            }
+            StmtKind::Continue => {
+                let depth = self.loop_depth("continue", span)?;
+                self.exit_blocks(depth + 1, span)?;
+                if let Block::Loop { start, .. } = self.scope().blocks[depth] {
+                    self.emit(Op::Jump(start), span);
+                }
+            }
+            StmtKind::Expression(expr) => {
+                self.expr(expr)?;
+                self.emit(Op::Pop, span);
+            }
+        }
+        Ok(())
+    }
+
+    // pops the value on top of the stack into an assignment target
+    fn store_target(&mut self, target: &Expr, span: Span) -> Result<(), RutenError> {
+        match &target.kind {
+            ExprKind::Identifier(name) => self.store(name, span),
+            ExprKind::Index { object, index } => {
+                self.expr(object)?;
+                self.expr(index)?;
+                self.emit(Op::StoreIndex, target.span);
+            }
+            ExprKind::Member { object, member } => {
+                self.expr(object)?;
+                let index = self.name(member);
+                self.emit(Op::StoreMember(index), target.span);
+            }
+            ExprKind::Tuple(targets) | ExprKind::List(targets) => {
+                let star = targets.iter().position(|t| matches!(t.kind, ExprKind::Starred(_)));
+                self.emit(Op::Unpack(targets.len(), star), target.span);
+                for target in targets {
+                    match &target.kind {
+                        ExprKind::Starred(inner) => self.store_target(inner, span)?,
+                        _ => self.store_target(target, span)?,
+                    }
+                }
+            }
+            _ => {
+                return Err(RutenError::syntax_error("cannot assign to this expression")
+                    .with_span(target.span))
+            }
+        }
+        Ok(())
+    }
+
+    fn loop_body(&mut self, start: usize, is_for: bool, body: &[Stmt], span: Span) -> Result<(), RutenError> {
+        self.scope().blocks.push(Block::Loop {
+            start,
+            breaks: Vec::new(),
+            is_for,
+        });
+        self.block(body)?;
+        self.emit(Op::Jump(start), span);
+        Ok(())
+    }
+
+    // `break` jumps past the loop, whose exit has just been emitted
+    fn end_loop(&mut self) {
+        if let Some(Block::Loop { breaks, .. }) = self.scope().blocks.pop() {
+            for jump in breaks {
+                self.patch(jump);
+            }
+        }
+    }
+
+    fn loop_depth(&mut self, keyword: &str, span: Span) -> Result<usize, RutenError> {
+        self.scope()
+            .blocks
+            .iter()
+            .rposition(|block| matches!(block, Block::Loop { .. }))
+            .ok_or_else(|| RutenError::syntax_error(format!("'{}' outside loop", keyword)).with_span(span))
+    }
+
+    // undoes every block from the innermost down to `depth` on the way out of them,
+    // running finally bodies; `depth` 0 means leaving the function
+    fn exit_blocks(&mut self, depth: usize, span: Span) -> Result<(), RutenError> {
+        for index in (depth..self.scope().blocks.len()).rev() {
+            let finally_body = match &self.scope().blocks[index] {
+                Block::Loop { .. } => None,
+                Block::Try => {
+                    self.emit(Op::PopExcept, span);
+                    None
+                }
+                Block::Handler => {
+                    self.emit(Op::ExitHandler, span);
+                    None
+                }
+                Block::FinallyError => {
+                    // the pending error gives way; a return drops the whole stack anyway
+                    if depth > 0 {
+                        self.emit(Op::Pop, span);
+                    }
+                    None
+                }
+                Block::Finally(body) => Some(body.clone()),
+            };
+            if let Some(body) = finally_body {
+                self.emit(Op::PopExcept, span);
+                // the finally body runs outside the statement it guards
+                let inner = self.scope().blocks.split_off(index);
+                let result = self.block(&body);
+                self.scope().blocks.extend(inner);
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn try_stmt(
+        &mut self,
+        body: &[Stmt],
+        handlers: &[crate::parser::ExceptHandler],
+        finally_body: Option<&[Stmt]>,
+        span: Span,
+    ) -> Result<(), RutenError> {
+        let finally_setup = match finally_body {
+            Some(finally_body) => {
+                let setup = self.emit(Op::SetupExcept(0), span);
+                self.scope().blocks.push(Block::Finally(finally_body.to_vec()));
+                Some(setup)
+            }
+            None => None,
+        };
+
+        if handlers.is_empty() {
+            self.block(body)?;
+        } else {
+            let setup = self.emit(Op::SetupExcept(0), span);
+            self.scope().blocks.push(Block::Try);
+            self.block(body)?;
+            self.scope().blocks.pop();
+            self.emit(Op::PopExcept, span);
+            let mut to_end = vec![self.emit(Op::Jump(0), span)];
+
+            // the error is on the stack here
+            self.patch(setup);
+            for handler in handlers {
+                let no_match = match &handler.kind {
+                    Some(kind) => {
+                        self.expr(kind)?;
+                        Some(self.emit(Op::MatchError(0), kind.span))
+                    }
+                    None => None,
+                };
+                if let Some(name) = &handler.name {
+                    self.emit(Op::Dup, span);
+                    self.store(name, span);
+                }
+                self.emit(Op::EnterHandler, span);
+                self.scope().blocks.push(Block::Handler);
+                self.block(&handler.body)?;
+                self.scope().blocks.pop();
+                self.emit(Op::ExitHandler, span);
+                to_end.push(self.emit(Op::Jump(0), span));
+                if let Some(no_match) = no_match {
+                    self.patch(no_match);
+                }
+            }
+            self.emit(Op::ReRaise, span);
+            for jump in to_end {
+                self.patch(jump);
+            }
+        }
+
+        if let (Some(setup), Some(finally_body)) = (finally_setup, finally_body) {
+            self.scope().blocks.pop();
+            self.emit(Op::PopExcept, span);
+            self.block(finally_body)?;
+            let to_end = self.emit(Op::Jump(0), span);
+
+            self.patch(setup);
+            self.scope().blocks.push(Block::FinallyError);
+            self.block(finally_body)?;
+            self.scope().blocks.pop();
+            self.emit(Op::ReRaise, span);
+            self.patch(to_end);
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), RutenError> {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Number(n) => {
+                let index = self.constant(Value::Number(*n));
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::Int(n) => {
+                let index = self.constant(Value::Int(n.clone()));
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::String(s) => {
+                let index = self.constant(Value::String(s.clone()));
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::Bool(b) => {
+                let index = self.constant(Value::Bool(*b));
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::None => {
+                let index = self.constant(Value::None);
+                self.emit(Op::Constant(index), span);
+            }
+            ExprKind::Identifier(name) => self.load(name, span),
+            ExprKind::Binary { left, op, right } => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.emit(Op::Binary(*op), span);
+            }
+            ExprKind::Unary { op, expr } => {
+                self.expr(expr)?;
+                self.emit(Op::Unary(*op), span);
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee)?;
+                let mut positional = 0;
+                let mut keywords = Vec::new();
+                for arg in args {
+                    match arg {
+                        Arg::Positional(value) => {
+                            self.expr(value)?;
+                            positional += 1;
+                        }
+                        Arg::Keyword(name, value) => {
+                            self.expr(value)?;
+                            keywords.push(name.clone());
+                        }
+                    }
+                }
+                if keywords.is_empty() {
+                    self.emit(Op::Call(positional), span);
+                } else {
+                    let code = &mut self.scope().code;
+                    code.keywords.push(keywords);
+                    let index = code.keywords.len() - 1;
+                    self.emit(Op::CallKw(positional, index), span);
+                }
+            }
+            ExprKind::FString(parts) => {
+                for part in parts {
+                    match part {
+                        FStringPart::Literal(text) => {
+                            let index = self.constant(Value::String(text.clone()));
+                            self.emit(Op::Constant(index), span);
+                        }
+                        FStringPart::Field(value, spec) => {
+                            self.expr(value)?;
+                            let spec = spec.as_ref().map(|spec| self.constant(Value::String(spec.clone())));
+                            self.emit(Op::FormatValue(spec), value.span);
+                        }
+                    }
+                }
+                self.emit(Op::BuildString(parts.len()), span);
+            }
+            ExprKind::List(elements) => {
+                for element in elements {
+                    self.expr(element)?;
+                }
+                self.emit(Op::BuildList(elements.len()), span);
+            }
+            ExprKind::Tuple(elements) => {
+                for element in elements {
+                    self.expr(element)?;
+                }
+                self.emit(Op::BuildTuple(elements.len()), span);
+            }
+            ExprKind::Starred(_) => {
+                return Err(RutenError::syntax_error(
+                    "starred expression is only allowed in an assignment target",
+                )
+                .with_span(span))
+            }
+            ExprKind::Dict(pairs) => {
+                for (key, value) in pairs {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                }
+                self.emit(Op::BuildDict(pairs.len()), span);
+            }
+            ExprKind::Set(elements) => {
+                for element in elements {
+                    self.expr(element)?;
+                }
+                self.emit(Op::BuildSet(elements.len()), span);
+            }
+            ExprKind::Index { object, index } => {
+                self.expr(object)?;
+                self.expr(index)?;
+                self.emit(Op::Index, span);
+            }
+            ExprKind::Slice {
+                object,
+                start,
+                stop,
+                step,
+            } => {
+                self.expr(object)?;
+                for bound in [start, stop, step] {
+                    match bound {
+                        Some(bound) => self.expr(bound)?,
+                        None => {
+                            let index = self.constant(Value::None);
+                            self.emit(Op::Constant(index), span);
+                        }
+                    }
+                }
+                self.emit(Op::Slice, span);
+            }
+            ExprKind::Member { object, member } => {
+                self.expr(object)?;
+                let index = self.name(member);
+                self.emit(Op::GetMember(index), span);
+            }
+            ExprKind::Lambda { params, body } => self.function("<lambda>", params, body, span)?,
+            ExprKind::Comprehension { kind, clauses } => {
+                // a function of its own, called right away, so its targets stay local to it
+                let body = [comprehension_body(kind, clauses, span)];
+                self.nested(COMPREHENSION, &Rc::from(Vec::new()), &body, span, |this| {
+                    let op = match kind {
+                        ComprehensionKind::List(_) => Op::BuildList(0),
+                        ComprehensionKind::Dict(..) => Op::BuildDict(0),
+                        ComprehensionKind::Set(_) => Op::BuildSet(0),
+                    };
+                    this.emit(op, span);
+                    this.clauses(kind, clauses, 0, span)?;
+                    this.emit(Op::Return, span);
+                    Ok(())
+                })?;
+                self.emit(Op::Call(0), span);
+            }
+        }
+        Ok(())
+    }
+
+    // the rest of a comprehension's clauses, with `depth` values stacked above the result
+    fn clauses(
+        &mut self,
+        kind: &ComprehensionKind,
+        clauses: &[ComprehensionClause],
+        depth: usize,
+        span: Span,
+    ) -> Result<(), RutenError> {
+        match clauses.split_first() {
+            None => match kind {
+                ComprehensionKind::List(element) => {
+                    self.expr(element)?;
+                    self.emit(Op::ListAppend(depth), span);
+                }
+                ComprehensionKind::Dict(key, value) => {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                    self.emit(Op::DictInsert(depth), span);
+                }
+                ComprehensionKind::Set(element) => {
+                    self.expr(element)?;
+                    self.emit(Op::SetAdd(depth), element.span);
+                }
+            },
+            Some((ComprehensionClause::For { target, iterable }, rest)) => {
+                self.expr(iterable)?;
+                self.emit(Op::GetIter, iterable.span);
+                let start = self.emit(Op::ForIter(0), span);
+                self.store_target(target, span)?;
+                // each `for` keeps an iterator and its position on the stack
+                self.clauses(kind, rest, depth + 2, span)?;
+                self.emit(Op::Jump(start), span);
+                self.patch(start);
+            }
+            Some((ComprehensionClause::If(condition), rest)) => {
+                self.expr(condition)?;
+                let skip = self.emit(Op::JumpIfFalse(0), condition.span);
+                self.clauses(kind, rest, depth, span)?;
+                self.patch(skip);
+            }
+        }
+        Ok(())
+    }
+
+    // leaves the new function on the stack
+    fn function(&mut self, name: &str, params: &Rc<[Param]>, body: &[Stmt], span: Span) -> Result<(), RutenError> {
+        // defaults are evaluated once, in the defining scope
+        for default in params.iter().filter_map(|p| p.default.as_ref()) {
+            self.expr(default)?;
+        }
+        self.nested(name, params, body, span, |this| this.block(body))
+    }
+
+    // compiles a function whose scope is worked out from `body`, with `emit`
+    // generating its code, and leaves it on the stack
+    fn nested(
+        &mut self,
+        name: &str,
+        params: &Rc<[Param]>,
+        body: &[Stmt],
+        span: Span,
+        emit: impl FnOnce(&mut Self) -> Result<(), RutenError>,
+    ) -> Result<(), RutenError> {
+        let names = Names::of(params, body);
+        let captured = captured_names(body);
+        let mut scope = Scope::new(name, &self.file, Rc::clone(params), None);
+        // parameters always get a slot, which call arguments are bound to, and
+        // are copied into their cell if nested functions capture them
+        for (slot, local) in names.locals.iter().enumerate() {
+            let is_param = slot < params.len();
+            if captured.contains(local) {
+                let cell = scope.code.cell_names.len();
+                scope.cells.insert(local.clone(), cell);
+                scope.code.cell_names.push(local.clone());
+                if is_param {
+                    scope.code.cell_params.push((slot, cell));
+                }
+            }
+            if is_param || !captured.contains(local) {
+                scope.locals.insert(local.clone(), scope.code.local_names.len());
+                scope.code.local_names.push(local.clone());
+            }
+        }
+        scope.code.own_cells = scope.code.cell_names.len();
+        scope.names = Some(names);
+
+        self.scopes.push(scope);
+        let result = emit(self);
+        let mut scope = self.scopes.pop().unwrap();
+        result?;
+        let none_index = scope.code.constants.len();
+        scope.code.constants.push(Value::None);
+        let end = body.last().map(|stmt| stmt.span).unwrap_or(span);
+        scope.code.ops.extend([Op::Constant(none_index), Op::Return]);
+        scope.code.spans.extend([end, end]);
+
+        let functions = &mut self.scope().code.functions;
+        functions.push(Rc::new(scope.code));
+        let index = functions.len() - 1;
+        self.emit(Op::MakeFunction(index), span);
+        Ok(())
+    }
+}
+
+/// the names a function binds itself, and the ones it declares global
+pub struct Names {
+    /// parameters first, in order
+    pub locals: Vec<String>,
+    pub globals: HashSet<String>,
+}
+
+impl Names {
+    pub fn of(params: &[Param], body: &[Stmt]) -> Self {
+        let mut assigned: Vec<String> = params.iter().map(|p| p.name.clone()).collect();
+        let mut globals = HashSet::new();
+        let mut nonlocals = HashSet::new();
+        visit_stmts(body, &mut |stmt| match &stmt.kind {
+            StmtKind::Global(names) => globals.extend(names.iter().cloned()),
+            StmtKind::Nonlocal(names) => nonlocals.extend(names.iter().cloned()),
+            kind => assigned.extend(bound_names(kind)),
+        });
+
+        let mut locals: Vec<String> = Vec::new();
+        for name in assigned {
+            if !globals.contains(&name) && !nonlocals.contains(&name) && !locals.contains(&name) {
+                locals.push(name);
+            }
+        }
+        Names { locals, globals }
+    }
+}
+
+/// how a function shares variables with the functions around it, which is all the
+/// tree-walker needs of the analysis above
+#[derive(Debug)]
+pub struct Captures {
+    /// its own variables that functions nested in it capture, so they live in cells
+    pub cells: Vec<String>,
+    /// the variables it uses without binding them, which may be cells of enclosing functions
+    pub free: Vec<String>,
+}
+
+impl Captures {
+    pub fn of(params: &[Param], body: &[Stmt]) -> Self {
+        let captured = captured_names(body);
+        let mut cells = Names::of(params, body).locals;
+        cells.retain(|name| captured.contains(name));
+        Captures {
+            cells,
+            free: free_names(params, body).into_iter().collect(),
+        }
+    }
+}
+
+/// the targets of a comprehension that functions nested in it capture
+pub fn comprehension_cells(kind: &ComprehensionKind, clauses: &[ComprehensionClause]) -> Vec<String> {
+    let mut captured = HashSet::new();
+    let mut targets = Vec::new();
+    for clause in clauses {
+        match clause {
+            ComprehensionClause::For { target, iterable } => {
+                target_names(target, &mut targets);
+                visit_functions(iterable, &mut captured);
+            }
+            ComprehensionClause::If(condition) => visit_functions(condition, &mut captured),
+        }
+    }
+    match kind {
+        ComprehensionKind::List(element) | ComprehensionKind::Set(element) => visit_functions(element, &mut captured),
+        ComprehensionKind::Dict(key, value) => {
+            visit_functions(key, &mut captured);
+            visit_functions(value, &mut captured);
+        }
+    }
+    targets.retain(|name| captured.contains(name));
+    targets
+}
+
+/// whether `stmts` bind or declare any names, which they do even if they never run
+pub fn binds_names(stmts: &[Stmt]) -> bool {
+    let mut binds = false;
+    visit_stmts(stmts, &mut |stmt| {
+        binds |= matches!(stmt.kind, StmtKind::Global(_) | StmtKind::Nonlocal(_))
+            || !bound_names(&stmt.kind).is_empty();
+    });
+    binds
+}
+
+// names a statement binds in the scope it runs in
+fn bound_names(kind: &StmtKind) -> Vec<String> {
+    match kind {
+        StmtKind::Import { module, alias } => vec![alias.clone().unwrap_or_else(|| module.clone())],
+        StmtKind::FromImport { names, .. } => names
+            .iter()
+            .map(|(name, alias)| alias.clone().unwrap_or_else(|| name.clone()))
+            .collect(),
+        StmtKind::Assign { target, .. } | StmtKind::For { target, .. } => {
+            let mut names = Vec::new();
+            target_names(target, &mut names);
+            names
+        }
+        StmtKind::FunctionDef { name, .. } => vec![name.clone()],
+        StmtKind::Try { handlers, .. } => handlers.iter().filter_map(|h| h.name.clone()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// the name of the functions comprehensions compile to, which tracebacks leave out
+pub const COMPREHENSION: &str = "<comprehension>";
+
+// the statements a comprehension amounts to, for working out the names it binds
+// and uses; the innermost ones stand for what it collects
+fn comprehension_body(kind: &ComprehensionKind, clauses: &[ComprehensionClause], span: Span) -> Stmt {
+    let mut stmts: Vec<Stmt> = match kind {
+        ComprehensionKind::List(element) | ComprehensionKind::Set(element) => {
+            vec![Stmt::new(StmtKind::Expression((**element).clone()), span)]
+        }
+        ComprehensionKind::Dict(key, value) => vec![
+            Stmt::new(StmtKind::Expression((**key).clone()), span),
+            Stmt::new(StmtKind::Expression((**value).clone()), span),
+        ],
+    };
+    for clause in clauses.iter().rev() {
+        let kind = match clause {
+            ComprehensionClause::For { target, iterable } => StmtKind::For {
+                target: target.clone(),
+                iterable: iterable.clone(),
+                body: stmts,
+            },
+            ComprehensionClause::If(condition) => StmtKind::If {
+                condition: condition.clone(),
+                then_branch: stmts,
+                else_branch: None,
+            },
+        };
+        stmts = vec![Stmt::new(kind, span)];
+    }
+    stmts.pop().unwrap()
+}
+
+// the variables an assignment target binds, however deeply it destructures
+fn target_names(target: &Expr, names: &mut Vec<String>) {
+    match &target.kind {
+        ExprKind::Identifier(name) => names.push(name.clone()),
+        ExprKind::Starred(inner) => target_names(inner, names),
+        ExprKind::Tuple(targets) | ExprKind::List(targets) => {
+            for target in targets {
+                target_names(target, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+// every statement of a function body, nested blocks included but not nested functions
+fn visit_stmts(stmts: &[Stmt], f: &mut dyn FnMut(&Stmt)) {
+    for stmt in stmts {
+        f(stmt);
+        match &stmt.kind {
+            StmtKind::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                visit_stmts(then_branch, f);
+                if let Some(else_branch) = else_branch {
+                    visit_stmts(else_branch, f);
+                }
+            }
+            StmtKind::While { body, .. } | StmtKind::For { body, .. } => visit_stmts(body, f),
+            StmtKind::Try {
+                body,
+                handlers,
+                finally_body,
+            } => {
+                visit_stmts(body, f);
+                for handler in handlers {
+                    visit_stmts(&handler.body, f);
+                }
+                if let Some(finally_body) = finally_body {
+                    visit_stmts(finally_body, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// names that functions nested in `body` use without binding them, so they may
+// refer to a binding of the function owning `body`
+fn captured_names(body: &[Stmt]) -> HashSet<String> {
+    let mut captured = HashSet::new();
+    visit_stmts(body, &mut |stmt| {
+        for_each_expr(stmt, &mut |expr| visit_functions(expr, &mut captured));
+        if let StmtKind::FunctionDef { params, body, .. } = &stmt.kind {
+            captured.extend(free_names(params, body));
+        }
+    });
+    captured
+}
+
+// lambdas and comprehensions anywhere in `expr`
+fn visit_functions(expr: &Expr, captured: &mut HashSet<String>) {
+    walk_expr(expr, &mut |expr| {
+        match &expr.kind {
+            ExprKind::Lambda { params, body } => {
+                captured.extend(free_names(params, body));
+                false
+            }
+            ExprKind::Comprehension { kind, clauses } => {
+                captured.extend(free_names(&[], &[comprehension_body(kind, clauses, expr.span)]));
+                false
+            }
+            _ => true,
+        }
+    });
+}
+
+// names a function uses that are neither its own nor declared global
+fn free_names(params: &[Param], body: &[Stmt]) -> HashSet<String> {
+    let names = Names::of(params, body);
+    let mut used = captured_names(body);
+    visit_stmts(body, &mut |stmt| {
+        used.extend(bound_names(&stmt.kind));
+        if let StmtKind::Nonlocal(names) = &stmt.kind {
+            used.extend(names.iter().cloned());
+        }
+        for_each_expr(stmt, &mut |expr| {
+            walk_expr(expr, &mut |expr| match &expr.kind {
+                ExprKind::Identifier(name) => {
+                    used.insert(name.clone());
+                    true
+                }
+                ExprKind::Lambda { .. } | ExprKind::Comprehension { .. } => false,
+                _ => true,
+            })
+        });
+    });
+    used.retain(|name| !names.locals.contains(name) && !names.globals.contains(name));
+    used
+}
+
+// the expressions a statement evaluates itself, including parameter defaults
+fn for_each_expr(stmt: &Stmt, f: &mut dyn FnMut(&Expr)) {
+    match &stmt.kind {
+        StmtKind::Assign { target, value } => {
+            f(target);
+            f(value);
+        }
+        StmtKind::FunctionDef { params, .. } => {
+            for default in params.iter().filter_map(|p| p.default.as_ref()) {
+                f(default);
+            }
+        }
+        StmtKind::Return(Some(expr)) | StmtKind::Raise(Some(expr)) | StmtKind::Expression(expr) => f(expr),
+        StmtKind::If { condition, .. } | StmtKind::While { condition, .. } => f(condition),
+        StmtKind::For { target, iterable, .. } => {
+            f(target);
+            f(iterable);
+        }
+        StmtKind::Try { handlers, .. } => {
+            for kind in handlers.iter().filter_map(|h| h.kind.as_ref()) {
+                f(kind);
+            }
+        }
+        _ => {}
+    }
+}
+
+// visits `expr` and its subexpressions, skipping the children of any for which `f` returns false;
+// lambda defaults belong to the enclosing scope, so they are visited too
+fn walk_expr(expr: &Expr, f: &mut dyn FnMut(&Expr) -> bool) {
+    if !f(expr) {
+        if let ExprKind::Lambda { params, .. } = &expr.kind {
+            for default in params.iter().filter_map(|p| p.default.as_ref()) {
+                walk_expr(default, f);
+            }
+        }
+        return;
+    }
+    match &expr.kind {
+        ExprKind::Binary { left, right, .. } => {
+            walk_expr(left, f);
+            walk_expr(right, f);
+        }
+        ExprKind::Unary { expr, .. } => walk_expr(expr, f),
+        ExprKind::Call { callee, args } => {
+            walk_expr(callee, f);
+            for arg in args {
+                match arg {
+                    Arg::Positional(value) | Arg::Keyword(_, value) => walk_expr(value, f),
+                }
+            }
+        }
+        ExprKind::FString(parts) => {
+            for part in parts {
+                if let FStringPart::Field(value, _) = part {
+                    walk_expr(value, f);
+                }
+            }
+        }
+        ExprKind::List(elements) | ExprKind::Tuple(elements) | ExprKind::Set(elements) => {
+            for element in elements {
+                walk_expr(element, f);
+            }
+        }
+        ExprKind::Starred(expr) => walk_expr(expr, f),
+        ExprKind::Comprehension { kind, clauses } => {
+            match kind {
+                ComprehensionKind::List(element) | ComprehensionKind::Set(element) => walk_expr(element, f),
+                ComprehensionKind::Dict(key, value) => {
+                    walk_expr(key, f);
+                    walk_expr(value, f);
+                }
+            }
+            for clause in clauses {
+                match clause {
+                    ComprehensionClause::For { target, iterable } => {
+                        walk_expr(target, f);
+                        walk_expr(iterable, f);
+                    }
+                    ComprehensionClause::If(condition) => walk_expr(condition, f),
+                }
+            }
+        }
+        ExprKind::Dict(pairs) => {
+            for (key, value) in pairs {
+                walk_expr(key, f);
+                walk_expr(value, f);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            walk_expr(object, f);
+            walk_expr(index, f);
+        }
+        ExprKind::Slice {
+            object,
+            start,
+            stop,
+            step,
+        } => {
+            walk_expr(object, f);
+            for bound in [start, stop, step].into_iter().flatten() {
+                walk_expr(bound, f);
+            }
+        }
+        ExprKind::Member { object, .. } => walk_expr(object, f),
+        ExprKind::Number(_)
+        | ExprKind::Int(_)
+        | ExprKind::String(_)
+        | ExprKind::Bool(_)
+        | ExprKind::None
+        | ExprKind::Identifier(_)
+        | ExprKind::Lambda { .. } => {}
+    }
+}
@@ -0,0 +1,114 @@
+// dict keys and set items: any hashable value, matched the way `==` matches them, so `1` and
+// `1.0` are the same key while `1` and `"1"` are not
+
+use crate::error::RutenError;
+use crate::int::Int;
+use crate::interpreter::{type_name, Value};
+use crate::ops;
+use indexmap::{IndexMap, IndexSet};
+use std::hash::{Hash, Hasher};
+
+/// the entries of a dict, in insertion order
+pub type DictMap = IndexMap<Key, Value>;
+
+/// the items of a set, in insertion order
+pub type KeySet = IndexSet<Key>;
+
+/// a value that can key a dict or be in a set: a number, string, bool, None, or a tuple of those
+#[derive(Debug, Clone)]
+pub struct Key(Value);
+
+impl Key {
+    pub fn new(value: Value) -> Result<Key, RutenError> {
+        if let Some(unhashable) = unhashable(&value) {
+            return Err(RutenError::type_error(format!("unhashable type: {}", type_name(unhashable))));
+        }
+        Ok(Key(value))
+    }
+
+    /// the key as it was first inserted
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+}
+
+/// a set of `items`, all of which must be hashable
+pub fn key_set(items: Vec<Value>) -> Result<KeySet, RutenError> {
+    items.into_iter().map(Key::new).collect()
+}
+
+impl From<&str> for Key {
+    fn from(key: &str) -> Key {
+        Key(Value::String(key.to_string()))
+    }
+}
+
+impl From<String> for Key {
+    fn from(key: String) -> Key {
+        Key(Value::String(key))
+    }
+}
+
+// the value, or the item nested in a tuple, that keeps `value` from being a key
+fn unhashable(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Int(_) | Value::Number(_) | Value::String(_) | Value::Bool(_) | Value::None => None,
+        Value::Tuple(items) => items.iter().find_map(unhashable),
+        other => Some(other),
+    }
+}
+
+// a float with no fractional part keys the same entry as the int it equals
+fn integral(n: f64) -> Option<Int> {
+    if n.fract() == 0.0 {
+        Int::from_f64(n)
+    } else {
+        None
+    }
+}
+
+fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Int(n) => {
+            0u8.hash(state);
+            n.hash(state);
+        }
+        Value::Number(n) => match integral(*n) {
+            Some(n) => hash_value(&Value::Int(n), state),
+            None => {
+                1u8.hash(state);
+                n.to_bits().hash(state);
+            }
+        },
+        Value::String(s) => {
+            2u8.hash(state);
+            s.hash(state);
+        }
+        Value::Bool(b) => {
+            3u8.hash(state);
+            b.hash(state);
+        }
+        Value::Tuple(items) => {
+            4u8.hash(state);
+            items.len().hash(state);
+            for item in items.iter() {
+                hash_value(item, state);
+            }
+        }
+        _ => 5u8.hash(state),
+    }
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(&self.0, state);
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        ops::keys_equal(&self.0, &other.0)
+    }
+}
+
+impl Eq for Key {}
@@ -1,24 +1,219 @@
 use std::fmt;
+use std::fs;
+
+/// a region of source text; `line` and `column` are 1-based and point at `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span { start, end, line, column }
+    }
+
+    /// the smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start { (self, other) } else { (other, self) };
+        Span {
+            start: first.start,
+            end: first.end.max(last.end),
+            line: first.line,
+            column: first.column,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Syntax,
+    Runtime,
+    Type,
+    Name,
+    Import,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 5] = [
+        ErrorKind::Syntax,
+        ErrorKind::Runtime,
+        ErrorKind::Type,
+        ErrorKind::Name,
+        ErrorKind::Import,
+    ];
+
+    /// the name scripts use for this kind, e.g. in `except TypeError as e:`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "SyntaxError",
+            ErrorKind::Runtime => "RuntimeError",
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Import => "ImportError",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "syntax error",
+            ErrorKind::Runtime => "runtime error",
+            ErrorKind::Type => "type error",
+            ErrorKind::Name => "name error",
+            ErrorKind::Import => "import error",
+        }
+    }
+}
+
+/// one entry of a traceback: the function that was running and where it was
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub function: String,
+    pub file: String,
+    pub span: Option<Span>,
+}
 
 #[derive(Debug, Clone)]
-pub enum RutenError {
-    SyntaxError(String),
-    RuntimeError(String),
-    TypeError(String),
-    NameError(String),
-    ImportError(String),
+pub struct RutenError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Span>,
+    /// the file `span` points into, when it is not the script being run
+    pub file: Option<String>,
+    /// innermost frame first; empty for errors raised at the top level
+    pub traceback: Vec<TraceFrame>,
+}
+
+impl RutenError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RutenError {
+            kind,
+            message: message.into(),
+            span: None,
+            file: None,
+            traceback: Vec::new(),
+        }
+    }
+
+    pub fn syntax_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Syntax, message)
+    }
+
+    pub fn runtime_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Runtime, message)
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Type, message)
+    }
+
+    pub fn name_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Name, message)
+    }
+
+    pub fn import_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Import, message)
+    }
+
+    /// attaches a location unless a more precise one was recorded already
+    pub fn with_span(mut self, span: Span) -> Self {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        self
+    }
+
+    /// records which file the span belongs to, unless an inner module did already
+    pub fn with_file(mut self, file: &str) -> Self {
+        if self.file.is_none() {
+            self.file = Some(file.to_string());
+        }
+        self
+    }
+
+    /// formats the error with a caret-underlined snippet of the offending line
+    /// followed by the traceback, if any
+    pub fn render(&self, filename: &str, source: &str) -> String {
+        let mut out = self.to_string();
+        match &self.file {
+            // raised inside an imported module: quote that file instead
+            Some(file) if file != filename => {
+                let source = fs::read_to_string(file).unwrap_or_default();
+                out.push_str(&self.render_snippet(file, &source));
+            }
+            _ => out.push_str(&self.render_snippet(filename, source)),
+        }
+
+        if !self.traceback.is_empty() {
+            out.push_str("\ntraceback (most recent call first):");
+            // runaway recursion repeats the same line hundreds of times; show it once
+            let mut previous = String::new();
+            let mut repeats = 0;
+            for (i, frame) in self.traceback.iter().enumerate() {
+                let prefix = if i == 0 { "in" } else { "called from" };
+                let line = match frame.span {
+                    Some(span) => format!("\n  {} {} ({}:{})", prefix, frame.function, frame.file, span.line),
+                    None => format!("\n  {} {} ({})", prefix, frame.function, frame.file),
+                };
+                if line == previous {
+                    repeats += 1;
+                    continue;
+                }
+                out.push_str(&repeated(repeats));
+                out.push_str(&line);
+                previous = line;
+                repeats = 0;
+            }
+            out.push_str(&repeated(repeats));
+        }
+        out
+    }
+
+    fn render_snippet(&self, filename: &str, source: &str) -> String {
+        let mut out = String::new();
+
+        let span = match self.span {
+            Some(span) => span,
+            None => return out,
+        };
+        let line = match source.lines().nth(span.line.saturating_sub(1)) {
+            Some(line) => line.trim_end_matches('\r'),
+            None => return out,
+        };
+
+        let number = span.line.to_string();
+        let pad = " ".repeat(number.len());
+        let start = span.column.saturating_sub(1);
+        let available = line.chars().count().saturating_sub(start).max(1);
+        let width = (span.end - span.start).clamp(1, available);
+        // keep tabs so the carets line up with the echoed source
+        let indent: String = line
+            .chars()
+            .take(start)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        out.push_str(&format!("\n{}--> {}:{}:{}", pad, filename, span.line, span.column));
+        out.push_str(&format!("\n{} |", pad));
+        out.push_str(&format!("\n{} | {}", number, line));
+        out.push_str(&format!("\n{} | {}{}", pad, indent, "^".repeat(width)));
+        out
+    }
+}
+
+fn repeated(times: usize) -> String {
+    match times {
+        0 => String::new(),
+        _ => format!("\n  [previous line repeated {} more times]", times),
+    }
 }
 
 impl fmt::Display for RutenError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            RutenError::SyntaxError(msg) => write!(f, "syntax error: {}", msg),
-            RutenError::RuntimeError(msg) => write!(f, "runtime error: {}", msg),
-            RutenError::TypeError(msg) => write!(f, "type error: {}", msg),
-            RutenError::NameError(msg) => write!(f, "name error: {}", msg),
-            RutenError::ImportError(msg) => write!(f, "import error: {}", msg),
-        }
+        write!(f, "{}: {}", self.kind.label(), self.message)
     }
 }
 
-impl std::error::Error for RutenError {}
\ No newline at end of file
+impl std::error::Error for RutenError {}
@@ -0,0 +1,258 @@
+// format specs for f-string fields, following python's mini-language:
+// [[fill]align][sign][#][0][width][,|_][.precision][type]
+
+use crate::error::RutenError;
+use crate::interpreter::{type_name, Value};
+
+struct Spec {
+    fill: char,
+    align: Option<char>,
+    sign: char,
+    alternate: bool,
+    width: usize,
+    grouping: Option<char>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+/// renders `value` as an f-string field with the spec after its ':'
+pub fn format_value(value: &Value, spec: Option<&str>) -> Result<String, RutenError> {
+    let text = match spec {
+        None | Some("") => return Ok(value.to_string()),
+        Some(text) => text,
+    };
+    let spec = parse(text)
+        .ok_or_else(|| RutenError::runtime_error(format!("invalid format spec '{}'", text)))?;
+    let wrong_type = |kind: char| {
+        RutenError::type_error(format!(
+            "format code '{}' cannot be used with {}",
+            kind,
+            type_name(value)
+        ))
+    };
+
+    // numbers split into sign, radix prefix and digits so '=' can pad between them
+    let (negative, prefix, digits) = match (value, spec.kind) {
+        (Value::Int(n), None | Some('d')) => (n.is_negative(), "", group(&n.abs().to_string(), &spec, 3)),
+        (Value::Int(n), Some(kind @ ('x' | 'X' | 'o' | 'b'))) => {
+            let (radix, prefix) = match kind {
+                'x' => (16, "0x"),
+                'X' => (16, "0X"),
+                'o' => (8, "0o"),
+                _ => (2, "0b"),
+            };
+            let mut digits = group(&n.abs().to_str_radix(radix), &spec, 4);
+            if kind == 'X' {
+                digits = digits.to_uppercase();
+            }
+            (n.is_negative(), if spec.alternate { prefix } else { "" }, digits)
+        }
+        (Value::Number(_) | Value::Int(_), Some(kind @ ('f' | 'F' | 'e' | 'E' | 'g' | 'G' | '%'))) => {
+            let n = value.as_f64().unwrap_or_default();
+            (n.is_sign_negative() && n != 0.0, "", float(n.abs(), kind, &spec))
+        }
+        // a float without a type keeps its usual form unless a precision is given
+        (Value::Number(n), None) => match spec.precision {
+            Some(_) => (n.is_sign_negative() && *n != 0.0, "", float(n.abs(), 'g', &spec)),
+            None => {
+                let text = Value::Number(n.abs()).to_string();
+                (n.is_sign_negative() && *n != 0.0, "", group_fraction(&text, &spec))
+            }
+        },
+        (Value::Number(_) | Value::Int(_), Some(kind)) => return Err(wrong_type(kind)),
+        (_, None | Some('s')) => {
+            if spec.sign != '-' || spec.alternate || spec.grouping.is_some() {
+                return Err(RutenError::runtime_error(format!(
+                    "invalid format spec '{}' for {}",
+                    text,
+                    type_name(value)
+                )));
+            }
+            let mut text = value.to_string();
+            if let Some(precision) = spec.precision {
+                text = text.chars().take(precision).collect();
+            }
+            return Ok(pad("", text, spec.fill, spec.align.unwrap_or('<'), spec.width));
+        }
+        (_, Some(kind)) => return Err(wrong_type(kind)),
+    };
+
+    let sign = match (negative, spec.sign) {
+        (true, _) => "-",
+        (false, '+') => "+",
+        (false, ' ') => " ",
+        _ => "",
+    };
+    let lead = format!("{}{}", sign, prefix);
+    Ok(pad(&lead, digits, spec.fill, spec.align.unwrap_or('>'), spec.width))
+}
+
+fn parse(text: &str) -> Option<Spec> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spec = Spec {
+        fill: ' ',
+        align: None,
+        sign: '-',
+        alternate: false,
+        width: 0,
+        grouping: None,
+        precision: None,
+        kind: None,
+    };
+    let mut i = 0;
+    let is_align = |ch: &char| "<>^=".contains(*ch);
+    if chars.get(1).is_some_and(is_align) {
+        spec.fill = chars[0];
+        spec.align = Some(chars[1]);
+        i = 2;
+    } else if chars.first().is_some_and(is_align) {
+        spec.align = Some(chars[0]);
+        i = 1;
+    }
+    if let Some(&sign @ ('+' | '-' | ' ')) = chars.get(i) {
+        spec.sign = sign;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'#') {
+        spec.alternate = true;
+        i += 1;
+    }
+    // a leading zero pads with zeros after the sign, unless an alignment was given
+    if chars.get(i) == Some(&'0') {
+        if spec.align.is_none() {
+            spec.fill = '0';
+            spec.align = Some('=');
+        }
+        i += 1;
+    }
+    let (width, next) = number(&chars, i);
+    spec.width = width.unwrap_or(0);
+    i = next;
+    if let Some(&grouping @ (',' | '_')) = chars.get(i) {
+        spec.grouping = Some(grouping);
+        i += 1;
+    }
+    if chars.get(i) == Some(&'.') {
+        let (precision, next) = number(&chars, i + 1);
+        spec.precision = Some(precision?);
+        i = next;
+    }
+    if let Some(&kind) = chars.get(i) {
+        if !"sdxXobfFeEgG%".contains(kind) {
+            return None;
+        }
+        spec.kind = Some(kind);
+        i += 1;
+    }
+    (i == chars.len()).then_some(spec)
+}
+
+// the decimal number starting at `i`, if any, and the index after it
+fn number(chars: &[char], mut i: usize) -> (Option<usize>, usize) {
+    let start = i;
+    while chars.get(i).is_some_and(|ch| ch.is_ascii_digit()) {
+        i += 1;
+    }
+    let digits: String = chars[start..i].iter().collect();
+    (digits.parse().ok(), i)
+}
+
+// `n` (not negative) in fixed, exponent, general or percent notation
+fn float(n: f64, kind: char, spec: &Spec) -> String {
+    let upper = kind.is_ascii_uppercase();
+    let text = if n.is_infinite() {
+        "inf".to_string()
+    } else if n.is_nan() {
+        "nan".to_string()
+    } else {
+        let precision = spec.precision.unwrap_or(6);
+        match kind {
+            'f' | 'F' => format!("{:.*}", precision, n),
+            'e' | 'E' => exponent(n, precision),
+            '%' => format!("{:.*}", precision, n * 100.0),
+            _ => general(n, precision.max(1), spec.alternate),
+        }
+    };
+    let text = if kind == '%' { text + "%" } else { text };
+    let text = group_fraction(&text, spec);
+    if upper {
+        text.to_uppercase()
+    } else {
+        text
+    }
+}
+
+// scientific notation with a signed, two-digit exponent: 1.500000e+03
+fn exponent(n: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, n);
+    let (mantissa, exp) = text.split_once('e').unwrap_or((&text, "0"));
+    let exp: i32 = exp.parse().unwrap_or(0);
+    format!("{}e{}{:02}", mantissa, if exp < 0 { '-' } else { '+' }, exp.abs())
+}
+
+// fixed or exponent notation, whichever suits the magnitude, without trailing zeros
+fn general(n: f64, precision: usize, alternate: bool) -> String {
+    // the exponent after rounding to `precision` significant digits
+    let exp: i32 = if n == 0.0 {
+        0
+    } else {
+        let text = format!("{:.*e}", precision - 1, n);
+        text.split_once('e').and_then(|(_, exp)| exp.parse().ok()).unwrap_or(0)
+    };
+    let text = if -4 <= exp && exp < precision as i32 {
+        format!("{:.*}", (precision as i32 - 1 - exp) as usize, n)
+    } else {
+        exponent(n, precision - 1)
+    };
+    if alternate {
+        return text;
+    }
+    let (mantissa, exp) = match text.find('e') {
+        Some(at) => text.split_at(at),
+        None => (text.as_str(), ""),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{}{}", mantissa, exp)
+}
+
+// inserts the spec's grouping separator every `size` digits from the right
+fn group(digits: &str, spec: &Spec, size: usize) -> String {
+    let separator = match spec.grouping {
+        Some(separator) => separator,
+        None => return digits.to_string(),
+    };
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / size);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(ch);
+    }
+    grouped
+}
+
+// groups only the whole part of a formatted float
+fn group_fraction(text: &str, spec: &Spec) -> String {
+    let end = text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+    let (whole, rest) = text.split_at(end);
+    group(whole, spec, 3) + rest
+}
+
+fn pad(lead: &str, body: String, fill: char, align: char, width: usize) -> String {
+    let len = lead.chars().count() + body.chars().count();
+    if len >= width {
+        return format!("{}{}", lead, body);
+    }
+    let padding = width - len;
+    let fill_with = |n: usize| fill.to_string().repeat(n);
+    match align {
+        '<' => format!("{}{}{}", lead, body, fill_with(padding)),
+        '^' => format!("{}{}{}{}", fill_with(padding / 2), lead, body, fill_with(padding - padding / 2)),
+        '=' => format!("{}{}{}", lead, fill_with(padding), body),
+        _ => format!("{}{}{}", fill_with(padding), lead, body),
+    }
+}
@@ -0,0 +1,280 @@
+// arbitrary precision integers: an i64 while the value fits, a bignum once it doesn't
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+/// always in its smallest form, so equal values compare and hash alike
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Int {
+    Small(i64),
+    Big(Rc<BigInt>),
+}
+
+impl From<i64> for Int {
+    fn from(n: i64) -> Self {
+        Int::Small(n)
+    }
+}
+
+impl From<u64> for Int {
+    fn from(n: u64) -> Self {
+        match i64::try_from(n) {
+            Ok(n) => Int::Small(n),
+            Err(_) => Int::from(BigInt::from(n)),
+        }
+    }
+}
+
+impl From<usize> for Int {
+    fn from(n: usize) -> Self {
+        match i64::try_from(n) {
+            Ok(n) => Int::Small(n),
+            Err(_) => Int::from(BigInt::from(n)),
+        }
+    }
+}
+
+impl From<BigInt> for Int {
+    fn from(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(small) => Int::Small(small),
+            None => Int::Big(Rc::new(n)),
+        }
+    }
+}
+
+impl Int {
+    /// the integer part of `f`, if it is finite
+    pub fn from_f64(f: f64) -> Option<Int> {
+        if !f.is_finite() {
+            return None;
+        }
+        let f = f.trunc();
+        if f.abs() < 9.0e18 {
+            Some(Int::Small(f as i64))
+        } else {
+            BigInt::from_f64(f).map(Int::from)
+        }
+    }
+
+    /// parses decimal digits with an optional sign
+    pub fn parse(text: &str) -> Option<Int> {
+        match text.parse::<i64>() {
+            Ok(n) => Some(Int::Small(n)),
+            Err(_) => text.parse::<BigInt>().ok().map(Int::from),
+        }
+    }
+
+    /// parses digits in base `radix`, without a sign or prefix
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<Int> {
+        match i64::from_str_radix(digits, radix) {
+            Ok(n) => Some(Int::Small(n)),
+            Err(_) => BigInt::parse_bytes(digits.as_bytes(), radix).map(Int::from),
+        }
+    }
+
+    /// the digits of `self` in base `radix`, with a leading '-' when negative
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        match self {
+            Int::Small(n) => BigInt::from(*n).to_str_radix(radix),
+            Int::Big(n) => n.to_str_radix(radix),
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Int::Small(n) => *n as f64,
+            Int::Big(n) => n.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// `self / other` as the float nearest the exact quotient, however large the ints;
+    /// None when dividing by zero, and infinite when the quotient is too large for a float
+    pub fn true_div(&self, other: &Int) -> Option<f64> {
+        if other.is_zero() {
+            return None;
+        }
+        // both exact as floats, so the one rounding of `/` gives the nearest quotient
+        if let (Int::Small(a), Int::Small(b)) = (self, other) {
+            if a.unsigned_abs() <= 1 << 53 && b.unsigned_abs() <= 1 << 53 {
+                return Some(*a as f64 / *b as f64);
+            }
+        }
+        let (a, b) = (self.to_big(), other.to_big());
+        let negative = a.is_negative() != b.is_negative();
+        let (a, b) = (a.abs(), b.abs());
+        // scaled so the integer quotient has at least two bits more than a float keeps,
+        // the lowest one set when anything was left over, so it rounds like the exact one
+        let shift = a.bits() as i64 - b.bits() as i64 - 55;
+        let (n, d) = if shift >= 0 {
+            (a, b << shift as usize)
+        } else {
+            (a << shift.unsigned_abs() as usize, b)
+        };
+        let (quotient, remainder) = n.div_rem(&d);
+        let quotient = quotient.to_u64().unwrap_or(u64::MAX) | u64::from(!remainder.is_zero());
+        // applied in two halves, so neither power overflows while the result doesn't
+        let shift = shift.clamp(-2200, 2200) as i32;
+        let result = quotient as f64 * 2f64.powi(shift / 2) * 2f64.powi(shift - shift / 2);
+        Some(if negative { -result } else { result })
+    }
+
+    /// how `self` compares with `f`, exactly rather than after rounding to a float;
+    /// None when `f` is NaN
+    pub fn cmp_f64(&self, f: f64) -> Option<Ordering> {
+        if f.is_nan() {
+            return None;
+        }
+        if f.is_infinite() {
+            return Some(if f > 0.0 { Ordering::Less } else { Ordering::Greater });
+        }
+        let floor = f.floor();
+        match self.cmp(&Int::from_f64(floor)?) {
+            Ordering::Equal if floor < f => Some(Ordering::Less),
+            ordering => Some(ordering),
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Int::Small(n) => Some(*n),
+            Int::Big(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Int::Small(0))
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Int::Small(n) => *n < 0,
+            Int::Big(n) => n.is_negative(),
+        }
+    }
+
+    /// how many bits the magnitude takes
+    pub fn bits(&self) -> u64 {
+        match self {
+            Int::Small(n) => u64::from(64 - n.unsigned_abs().leading_zeros()),
+            Int::Big(n) => n.bits(),
+        }
+    }
+
+    fn to_big(&self) -> BigInt {
+        match self {
+            Int::Small(n) => BigInt::from(*n),
+            Int::Big(n) => (**n).clone(),
+        }
+    }
+
+    pub fn add(&self, other: &Int) -> Int {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => match a.checked_add(*b) {
+                Some(n) => Int::Small(n),
+                None => Int::from(BigInt::from(*a) + b),
+            },
+            _ => Int::from(self.to_big() + other.to_big()),
+        }
+    }
+
+    pub fn sub(&self, other: &Int) -> Int {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => match a.checked_sub(*b) {
+                Some(n) => Int::Small(n),
+                None => Int::from(BigInt::from(*a) - b),
+            },
+            _ => Int::from(self.to_big() - other.to_big()),
+        }
+    }
+
+    pub fn mul(&self, other: &Int) -> Int {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => match a.checked_mul(*b) {
+                Some(n) => Int::Small(n),
+                None => Int::from(BigInt::from(*a) * b),
+            },
+            _ => Int::from(self.to_big() * other.to_big()),
+        }
+    }
+
+    /// division rounding toward negative infinity; None when dividing by zero
+    pub fn floor_div(&self, other: &Int) -> Option<Int> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(match (self, other) {
+            // only i64::MIN // -1 overflows
+            (Int::Small(a), Int::Small(b)) if !(*a == i64::MIN && *b == -1) => Int::Small(a.div_floor(b)),
+            _ => Int::from(self.to_big().div_floor(&other.to_big())),
+        })
+    }
+
+    /// the remainder of `floor_div`, which takes the sign of the divisor
+    pub fn modulo(&self, other: &Int) -> Option<Int> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(match (self, other) {
+            (Int::Small(a), Int::Small(b)) if *b != -1 => Int::Small(a.mod_floor(b)),
+            (_, Int::Small(-1)) => Int::Small(0),
+            _ => Int::from(self.to_big().mod_floor(&other.to_big())),
+        })
+    }
+
+    /// `self ** exponent` for a non-negative exponent
+    pub fn pow(&self, exponent: u32) -> Int {
+        if let Int::Small(base) = self {
+            if let Some(n) = base.checked_pow(exponent) {
+                return Int::Small(n);
+            }
+        }
+        Int::from(self.to_big().pow(exponent))
+    }
+
+    pub fn neg(&self) -> Int {
+        match self {
+            Int::Small(n) => match n.checked_neg() {
+                Some(n) => Int::Small(n),
+                None => Int::from(-BigInt::from(*n)),
+            },
+            Int::Big(n) => Int::from(-(**n).clone()),
+        }
+    }
+
+    pub fn abs(&self) -> Int {
+        if self.is_negative() {
+            self.neg()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => a.cmp(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Int::Small(n) => write!(f, "{}", n),
+            Int::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
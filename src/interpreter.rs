@@ -1,28 +1,102 @@
-use crate::error::RutenError;
-use crate::parser::{BinaryOp, Expr, Program, Stmt, UnaryOp};
-use crate::modules;
+use crate::builtins;
+use crate::dict::{DictMap, Key, KeySet};
+use crate::error::{ErrorKind, RutenError, Span, TraceFrame};
+use crate::int::Int;
+use crate::format_spec;
+use crate::parser::{
+    Arg, ComprehensionClause, ComprehensionKind, ExceptHandler, Expr, ExprKind, FStringPart, Param, Program, Stmt,
+    StmtKind,
+};
+use crate::loader::{self, ModuleLoader};
+use crate::optimizer;
+use crate::ops;
+use crate::compiler::{self, Captures};
+use crate::vm::{Cell, Closure, ModuleGlobals};
+use crate::{lexer, parser};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Value {
+    /// a float; integers are `Int`
     Number(f64),
+    Int(Int),
     String(String),
     Bool(bool),
     None,
-    List(Vec<Value>),
-    Dict(HashMap<String, Value>),
-    Function {
-        params: Vec<String>,
-        body: Vec<Stmt>,
-        closure: Environment,
+    List(Rc<RefCell<Vec<Value>>>),
+    /// an immutable sequence; unlike lists, copies can't observe changes
+    Tuple(Rc<[Value]>),
+    Dict(Rc<RefCell<DictMap>>),
+    Set(Rc<RefCell<KeySet>>),
+    Function(Rc<Function>),
+    /// a function compiled for the bytecode vm
+    Closure(Rc<Closure>),
+    NativeFunction(Native),
+    Module(Module),
+    ErrorType(ErrorKind),
+    Error(Box<RutenError>),
+    BoundMethod {
+        receiver: Box<Value>,
+        name: String,
     },
-    NativeFunction(fn(&[Value]) -> Result<Value, RutenError>),
-    Module(HashMap<String, Value>),
 }
 
-impl Value {
-    pub fn to_string(&self) -> String {
+/// an imported module's members; a script's are its live globals, so changes made
+/// through the module and by the script's own functions see each other
+#[derive(Debug, Clone)]
+pub enum Module {
+    /// a native module, or a script run by the tree-walker
+    Members(Rc<RefCell<HashMap<String, Value>>>),
+    /// a script run by the vm, whose globals live in slots
+    Globals(Rc<RefCell<ModuleGlobals>>),
+}
+
+impl Module {
+    pub fn get(&self, name: &str) -> Option<Value> {
         match self {
+            Module::Members(members) => members.borrow().get(name).cloned(),
+            Module::Globals(globals) => globals.borrow().member(name),
+        }
+    }
+
+    pub fn set(&self, name: &str, value: Value) {
+        match self {
+            Module::Members(members) => {
+                members.borrow_mut().insert(name.to_string(), value);
+            }
+            Module::Globals(globals) => globals.borrow_mut().set_member(name, value),
+        }
+    }
+
+    pub fn same(&self, other: &Module) -> bool {
+        match (self, other) {
+            (Module::Members(x), Module::Members(y)) => Rc::ptr_eq(x, y),
+            (Module::Globals(x), Module::Globals(y)) => Rc::ptr_eq(x, y),
+            _ => false,
+        }
+    }
+}
+
+/// a user-defined function; values share it, so passing a function around is cheap
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Rc<[Param]>,
+    /// evaluated defaults, one slot per parameter
+    pub defaults: Vec<Option<Value>>,
+    pub body: Rc<[Stmt]>,
+    pub captures: Rc<Captures>,
+    pub closure: Environment,
+    /// the script the function was defined in
+    pub file: String,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
             Value::Number(n) => {
                 if n.fract() == 0.0 {
                     format!("{:.0}", n)
@@ -30,23 +104,96 @@ impl Value {
                     n.to_string()
                 }
             }
+            Value::Int(n) => n.to_string(),
             Value::String(s) => s.clone(),
             Value::Bool(b) => b.to_string(),
             Value::None => "None".to_string(),
-            Value::List(items) => {
-                let strs: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+            Value::List(items) => print_once(Rc::as_ptr(items) as *const (), "[...]", || {
+                let strs: Vec<String> = items.borrow().iter().map(|v| v.repr()).collect();
                 format!("[{}]", strs.join(", "))
+            }),
+            Value::Tuple(items) => {
+                let strs: Vec<String> = items.iter().map(|v| v.repr()).collect();
+                match strs.len() {
+                    1 => format!("({},)", strs[0]),
+                    _ => format!("({})", strs.join(", ")),
+                }
             }
-            Value::Dict(map) => {
+            Value::Dict(map) => print_once(Rc::as_ptr(map) as *const (), "{...}", || {
                 let pairs: Vec<String> = map
+                    .borrow()
                     .iter()
-                    .map(|(k, v)| format!("{}: {}", k, v.to_string()))
+                    .map(|(k, v)| format!("{}: {}", k.value().repr(), v.repr()))
                     .collect();
                 format!("{{{}}}", pairs.join(", "))
+            }),
+            Value::Set(items) => {
+                let items = items.borrow();
+                if items.is_empty() {
+                    return write!(f, "set()");
+                }
+                let strs: Vec<String> = items.iter().map(|k| k.value().repr()).collect();
+                format!("{{{}}}", strs.join(", "))
             }
-            Value::Function { .. } => "<function>".to_string(),
+            Value::Function(_) | Value::Closure(_) => "<function>".to_string(),
             Value::NativeFunction(_) => "<native function>".to_string(),
             Value::Module(_) => "<module>".to_string(),
+            Value::ErrorType(kind) => format!("<error type {}>", kind.name()),
+            Value::Error(err) => format!("{}: {}", err.kind.name(), err.message),
+            Value::BoundMethod { name, .. } => format!("<method {}>", name),
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl Value {
+    /// how the value shows inside a container: strings are quoted, so `[1, "1"]`
+    /// doesn't print as `[1, 1]`
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => quote(s),
+            other => other.to_string(),
+        }
+    }
+
+    /// a new list; lists are shared, so copies of the value see mutations
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn tuple(items: Vec<Value>) -> Value {
+        Value::Tuple(items.into())
+    }
+
+    /// a new dict, shared like lists
+    pub fn dict(map: DictMap) -> Value {
+        Value::Dict(Rc::new(RefCell::new(map)))
+    }
+
+    /// a new set, shared like lists
+    pub fn set(items: KeySet) -> Value {
+        Value::Set(Rc::new(RefCell::new(items)))
+    }
+
+    /// a native function that takes no keyword arguments
+    pub fn native(call: NativeFn) -> Value {
+        Value::native_with_keywords(&[], call)
+    }
+
+    pub fn native_with_keywords(keywords: &'static [&'static str], call: NativeFn) -> Value {
+        Value::NativeFunction(Native { call, keywords })
+    }
+
+    pub fn module(members: HashMap<String, Value>) -> Value {
+        Value::Module(Module::Members(Rc::new(RefCell::new(members))))
+    }
+
+    /// the value of an int or a float as a float, for natives that take either
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Int(n) => Some(n.to_f64()),
+            _ => None,
         }
     }
 
@@ -55,66 +202,314 @@ impl Value {
             Value::Bool(b) => *b,
             Value::None => false,
             Value::Number(n) => *n != 0.0,
+            Value::Int(n) => !n.is_zero(),
             Value::String(s) => !s.is_empty(),
-            Value::List(l) => !l.is_empty(),
+            Value::List(l) => !l.borrow().is_empty(),
+            Value::Tuple(items) => !items.is_empty(),
+            Value::Set(s) => !s.borrow().is_empty(),
             _ => true,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Environment {
-    scopes: Vec<HashMap<String, Value>>,
+thread_local! {
+    // the lists and dicts being printed, innermost last
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+// prints a list or dict with `print`, or as `placeholder` when it's inside itself,
+// so one that holds itself prints as `[1, [...]]` instead of recursing forever
+fn print_once(container: *const (), placeholder: &str, print: impl FnOnce() -> String) -> String {
+    if PRINTING.with(|printing| printing.borrow().contains(&container)) {
+        return placeholder.to_string();
+    }
+    PRINTING.with(|printing| printing.borrow_mut().push(container));
+    let text = print();
+    PRINTING.with(|printing| printing.borrow_mut().pop());
+    text
+}
+
+// a string in single quotes, or double quotes if that saves escaping, like python's repr
+fn quote(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') { '"' } else { '\'' };
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+    for ch in s.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ if ch == quote => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// a chain of scopes; the root holds the builtins and its child the globals. a closure
+/// doesn't hold the scopes it was defined in, only the cells of the variables it uses
+/// from them, so a call's scope is freed when the call ends
+#[derive(Clone)]
+pub struct Environment(Rc<Scope>);
+
+struct Scope {
+    // shared, so a script's globals can be imported as a module
+    vars: Rc<RefCell<HashMap<String, Value>>>,
+    // variables nested functions capture, which live here instead of in `vars`
+    cells: HashMap<String, Cell>,
+    parent: Option<Environment>,
+    // names declared `global` or `nonlocal` here; assigning them writes outward
+    declared: RefCell<HashMap<String, Declaration>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Declaration {
+    Global,
+    Nonlocal,
+}
+
+// scopes can reach themselves through the closures they hold
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<environment>")
+    }
 }
 
 impl Environment {
     fn new() -> Self {
-        Environment {
-            scopes: vec![HashMap::new()],
-        }
+        Environment::with_parent(None, HashMap::new())
+    }
+
+    fn with_parent(parent: Option<Environment>, cells: HashMap<String, Cell>) -> Self {
+        Environment(Rc::new(Scope {
+            vars: Rc::new(RefCell::new(HashMap::new())),
+            cells,
+            parent,
+            declared: RefCell::new(HashMap::new()),
+        }))
     }
 
-    fn push_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+    /// a fresh scope nested in this one, e.g. for a function call, with empty cells
+    /// for the variables named in `cells`
+    fn child(&self, cells: &[String]) -> Self {
+        let cells = cells.iter().map(|name| (name.clone(), Cell::default())).collect();
+        Environment::with_parent(Some(self.clone()), cells)
     }
 
-    fn pop_scope(&mut self) {
-        self.scopes.pop();
+    /// ends a call's scope: when its cells are only held by functions stored in them,
+    /// such as a nested function that calls itself, they're emptied so that cycle
+    /// doesn't outlive the call
+    fn release(self) {
+        let cells = &self.0.cells;
+        let unreachable = cells.values().all(|cell| {
+            let from_own_functions: usize = cells
+                .values()
+                .map(|holder| match &*holder.borrow() {
+                    Some(Value::Function(function)) if Rc::strong_count(function) == 1 => function
+                        .closure
+                        .0
+                        .cells
+                        .values()
+                        .filter(|captured| Rc::ptr_eq(captured, cell))
+                        .count(),
+                    _ => 0,
+                })
+                .sum();
+            Rc::strong_count(cell) == 1 + from_own_functions
+        });
+        if unreachable {
+            for cell in cells.values() {
+                // dropped outside the borrow, since the function may hold this cell
+                let value = cell.borrow_mut().take();
+                drop(value);
+            }
+        }
     }
 
-    fn define(&mut self, name: String, value: Value) {
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, value);
+    /// binds `name` in this scope, regardless of declarations
+    fn define(&self, name: String, value: Value) {
+        match self.0.cells.get(&name) {
+            Some(cell) => *cell.borrow_mut() = Some(value),
+            None => {
+                self.0.vars.borrow_mut().insert(name, value);
+            }
         }
     }
 
     fn get(&self, name: &str) -> Option<Value> {
-        for scope in self.scopes.iter().rev() {
-            if let Some(value) = scope.get(name) {
+        let mut scope = Some(self);
+        while let Some(env) = scope {
+            if let Some(value) = env.0.vars.borrow().get(name) {
                 return Some(value.clone());
             }
+            // an empty cell hasn't been assigned yet, so the name may still be a global
+            if let Some(value) = env.0.cells.get(name).and_then(|cell| cell.borrow().clone()) {
+                return Some(value);
+            }
+            scope = env.0.parent.as_ref();
         }
         None
     }
 
-    fn set(&mut self, name: &str, value: Value) -> Result<(), RutenError> {
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.contains_key(name) {
-                scope.insert(name.to_string(), value);
-                return Ok(());
+    /// the cell holding `name` in this scope or an enclosing one
+    fn cell(&self, name: &str) -> Option<Cell> {
+        let mut scope = Some(self);
+        while let Some(env) = scope {
+            if let Some(cell) = env.0.cells.get(name) {
+                return Some(Rc::clone(cell));
+            }
+            scope = env.0.parent.as_ref();
+        }
+        None
+    }
+
+    /// binds `name` the way assignment does: in this scope, unless it was declared
+    /// `global` or `nonlocal` here
+    fn assign(&self, name: &str, value: Value) {
+        let declaration = self.0.declared.borrow().get(name).copied();
+        match declaration {
+            Some(Declaration::Global) => self.globals().rebind(name, value),
+            Some(Declaration::Nonlocal) => match self.cell(name) {
+                Some(cell) => *cell.borrow_mut() = Some(value),
+                None => self.rebind(name, value),
+            },
+            None => self.rebind(name, value),
+        }
+    }
+
+    // like `define`, but reuses the existing entry so loops don't allocate the name again
+    fn rebind(&self, name: &str, value: Value) {
+        if let Some(cell) = self.0.cells.get(name) {
+            *cell.borrow_mut() = Some(value);
+            return;
+        }
+        let mut vars = self.0.vars.borrow_mut();
+        match vars.get_mut(name) {
+            Some(slot) => *slot = value,
+            None => {
+                vars.insert(name.to_string(), value);
             }
         }
-        // if not found, define in current scope
-        self.define(name.to_string(), value);
-        Ok(())
+    }
+
+    /// the script's top-level scope, just above the builtins
+    fn globals(&self) -> Environment {
+        let mut env = self;
+        while let Some(parent) = &env.0.parent {
+            if parent.0.parent.is_none() {
+                break;
+            }
+            env = parent;
+        }
+        env.clone()
+    }
+
+    /// the scope a function defined here closes over: the cells of the variables in
+    /// `free` that enclosing functions bind, on top of the globals
+    fn closure(&self, free: &[String]) -> Environment {
+        let cells: HashMap<String, Cell> = free
+            .iter()
+            .filter_map(|name| Some((name.clone(), self.cell(name)?)))
+            .collect();
+        let globals = self.globals();
+        if cells.is_empty() {
+            globals
+        } else {
+            Environment::with_parent(Some(globals), cells)
+        }
+    }
+
+    fn is_global(&self) -> bool {
+        self.0.parent.as_ref().is_none_or(|parent| parent.0.parent.is_none())
+    }
+
+    fn declare_global(&self, name: &str) {
+        if !self.is_global() {
+            self.0.declared.borrow_mut().insert(name.to_string(), Declaration::Global);
+        }
+    }
+
+    // the parser has checked an enclosing function binds `name`, so its cell is reachable
+    fn declare_nonlocal(&self, name: &str) {
+        self.0.declared.borrow_mut().insert(name.to_string(), Declaration::Nonlocal);
     }
 }
 
+/// signature of functions implemented in rust, such as builtins and module members
+pub type NativeFn = fn(&mut CallContext, &[Value]) -> Result<Value, RutenError>;
+
+/// a function implemented in rust, with the keyword arguments it takes; others are
+/// rejected before it runs, so a misspelled keyword can't follow a side effect
+#[derive(Debug, Clone, Copy)]
+pub struct Native {
+    pub call: NativeFn,
+    pub keywords: &'static [&'static str],
+}
+
+/// a backend able to call any callable value; the tree-walker and the vm both are
+pub trait Caller {
+    fn call_value(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+        call_site: Span,
+    ) -> Result<Value, RutenError>;
+}
+
+/// handed to native functions so they can call back into the script
+pub struct CallContext<'a> {
+    caller: &'a mut dyn Caller,
+    call_site: Span,
+    kwargs: Vec<(String, Value)>,
+}
+
+impl<'a> CallContext<'a> {
+    pub fn new(caller: &'a mut dyn Caller, call_site: Span, kwargs: Vec<(String, Value)>) -> Self {
+        CallContext {
+            caller,
+            call_site,
+            kwargs,
+        }
+    }
+
+    /// calls any callable value, e.g. a user-defined function passed as an argument
+    pub fn call(&mut self, func: &Value, args: Vec<Value>) -> Result<Value, RutenError> {
+        self.caller.call_value(func.clone(), args, Vec::new(), self.call_site)
+    }
+
+    /// takes the keyword argument `name`, e.g. `timeout` in `http.get(url, timeout=5)`;
+    /// only names the native declared can be passed
+    pub fn kwarg(&mut self, name: &str) -> Option<Value> {
+        let index = self.kwargs.iter().position(|(key, _)| key == name)?;
+        Some(self.kwargs.remove(index).1)
+    }
+}
+
+// an active call to a user-defined function
+struct Frame {
+    function: Rc<Function>,
+    call_site: Span,
+}
+
 pub struct Interpreter {
     env: Environment,
     return_value: Option<Value>,
     break_flag: bool,
     continue_flag: bool,
+    call_stack: Vec<Frame>,
+    filename: String,
+    // errors being handled by enclosing except blocks, for bare `raise`
+    handling: Vec<RutenError>,
+    loader: Rc<RefCell<ModuleLoader>>,
+    // the captures of each function body, worked out the first time it's defined;
+    // the body is kept so its address isn't reused
+    captures: HashMap<*const Stmt, (Rc<[Stmt]>, Rc<Captures>)>,
 }
 
 impl Interpreter {
@@ -124,147 +519,31 @@ impl Interpreter {
             return_value: None,
             break_flag: false,
             continue_flag: false,
+            call_stack: Vec::new(),
+            filename: "<repl>".to_string(),
+            handling: Vec::new(),
+            loader: Rc::new(RefCell::new(ModuleLoader::default())),
+            captures: HashMap::new(),
         };
-        interpreter.init_builtins();
+        for (name, value) in builtins::create_builtins() {
+            interpreter.env.define(name, value);
+        }
+        // builtins live in a scope of their own, below the script's globals
+        interpreter.env = interpreter.env.child(&[]);
         interpreter
     }
 
-    fn init_builtins(&mut self) {
-        // print function
-        self.env.define(
-            "print".to_string(),
-            Value::NativeFunction(|args| {
-                let output = args
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                println!("{}", output);
-                Ok(Value::None)
-            }),
-        );
-
-        // len function
-        self.env.define(
-            "len".to_string(),
-            Value::NativeFunction(|args| {
-                if args.len() != 1 {
-                    return Err(RutenError::RuntimeError(
-                        "len() takes exactly 1 argument".to_string(),
-                    ));
-                }
-                match &args[0] {
-                    Value::String(s) => Ok(Value::Number(s.len() as f64)),
-                    Value::List(l) => Ok(Value::Number(l.len() as f64)),
-                    Value::Dict(d) => Ok(Value::Number(d.len() as f64)),
-                    _ => Err(RutenError::TypeError(
-                        "len() argument must be a string, list, or dict".to_string(),
-                    )),
-                }
-            }),
-        );
-
-        // range function
-        self.env.define(
-            "range".to_string(),
-            Value::NativeFunction(|args| {
-                if args.is_empty() || args.len() > 3 {
-                    return Err(RutenError::RuntimeError(
-                        "range() takes 1 to 3 arguments".to_string(),
-                    ));
-                }
-                
-                let (start, end, step) = match args.len() {
-                    1 => {
-                        if let Value::Number(n) = args[0] {
-                            (0.0, n, 1.0)
-                        } else {
-                            return Err(RutenError::TypeError("range() arguments must be numbers".to_string()));
-                        }
-                    }
-                    2 => {
-                        if let (Value::Number(s), Value::Number(e)) = (&args[0], &args[1]) {
-                            (*s, *e, 1.0)
-                        } else {
-                            return Err(RutenError::TypeError("range() arguments must be numbers".to_string()));
-                        }
-                    }
-                    3 => {
-                        if let (Value::Number(s), Value::Number(e), Value::Number(st)) = (&args[0], &args[1], &args[2]) {
-                            (*s, *e, *st)
-                        } else {
-                            return Err(RutenError::TypeError("range() arguments must be numbers".to_string()));
-                        }
-                    }
-                    _ => unreachable!(),
-                };
-
-                let mut result = Vec::new();
-                let mut current = start;
-                while (step > 0.0 && current < end) || (step < 0.0 && current > end) {
-                    result.push(Value::Number(current));
-                    current += step;
-                }
-                Ok(Value::List(result))
-            }),
-        );
-
-        // str function
-        self.env.define(
-            "str".to_string(),
-            Value::NativeFunction(|args| {
-                if args.len() != 1 {
-                    return Err(RutenError::RuntimeError(
-                        "str() takes exactly 1 argument".to_string(),
-                    ));
-                }
-                Ok(Value::String(args[0].to_string()))
-            }),
-        );
-
-        // int function
-        self.env.define(
-            "int".to_string(),
-            Value::NativeFunction(|args| {
-                if args.len() != 1 {
-                    return Err(RutenError::RuntimeError(
-                        "int() takes exactly 1 argument".to_string(),
-                    ));
-                }
-                match &args[0] {
-                    Value::Number(n) => Ok(Value::Number(n.trunc())),
-                    Value::String(s) => s
-                        .parse::<f64>()
-                        .map(|n| Value::Number(n.trunc()))
-                        .map_err(|_| RutenError::RuntimeError("invalid literal for int()".to_string())),
-                    _ => Err(RutenError::TypeError(
-                        "int() argument must be a number or string".to_string(),
-                    )),
-                }
-            }),
-        );
+    /// an interpreter running the script `filename`, which tracebacks refer to
+    pub fn with_filename(filename: &str) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.filename = filename.to_string();
+        interpreter.loader = Rc::new(RefCell::new(ModuleLoader::for_script(filename)));
+        interpreter
+    }
 
-        // float function
-        self.env.define(
-            "float".to_string(),
-            Value::NativeFunction(|args| {
-                if args.len() != 1 {
-                    return Err(RutenError::RuntimeError(
-                        "float() takes exactly 1 argument".to_string(),
-                    ));
-                }
-                match &args[0] {
-                    Value::Number(n) => Ok(Value::Number(*n)),
-                    Value::String(s) => s
-                        .parse::<f64>()
-                        .map(Value::Number)
-                        .map_err(|_| RutenError::RuntimeError("invalid literal for float()".to_string())),
-                    _ => Err(RutenError::TypeError(
-                        "float() argument must be a number or string".to_string(),
-                    )),
-                }
-            }),
-        );
+    /// the script's top-level bindings, which is what importing it exposes
+    pub fn exports(&self) -> Module {
+        Module::Members(Rc::clone(&self.env.globals().0.vars))
     }
 
     pub fn eval_program(&mut self, program: Program) -> Result<(), RutenError> {
@@ -278,91 +557,129 @@ impl Interpreter {
     }
 
     pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, RutenError> {
-        match expr {
-            Expr::Number(n) => Ok(Value::Number(*n)),
-            Expr::String(s) => Ok(Value::String(s.clone())),
-            Expr::Bool(b) => Ok(Value::Bool(*b)),
-            Expr::None => Ok(Value::None),
-            Expr::Identifier(name) => self
+        self.eval_expr_kind(expr)
+            .map_err(|err| err.with_span(expr.span))
+    }
+
+    // a slice bound, which is None when left out
+    fn eval_bound(&mut self, bound: &Option<Box<Expr>>) -> Result<Value, RutenError> {
+        match bound {
+            Some(bound) => self.eval_expr(bound),
+            None => Ok(Value::None),
+        }
+    }
+
+    fn eval_expr_kind(&mut self, expr: &Expr) -> Result<Value, RutenError> {
+        match &expr.kind {
+            ExprKind::Number(n) => Ok(Value::Number(*n)),
+            ExprKind::Int(n) => Ok(Value::Int(n.clone())),
+            ExprKind::FString(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        FStringPart::Literal(literal) => text.push_str(literal),
+                        FStringPart::Field(value, spec) => {
+                            let span = value.span;
+                            let value = self.eval_expr(value)?;
+                            // a bad spec points at its field, as the vm reports it
+                            let formatted = format_spec::format_value(&value, spec.as_deref())
+                                .map_err(|err| err.with_span(span))?;
+                            text.push_str(&formatted);
+                        }
+                    }
+                }
+                Ok(Value::String(text))
+            }
+            ExprKind::String(s) => Ok(Value::String(s.clone())),
+            ExprKind::Bool(b) => Ok(Value::Bool(*b)),
+            ExprKind::None => Ok(Value::None),
+            ExprKind::Identifier(name) => self
                 .env
                 .get(name)
-                .ok_or_else(|| RutenError::NameError(format!("undefined variable: {}", name))),
-            Expr::Binary { left, op, right } => {
+                .ok_or_else(|| RutenError::name_error(format!("undefined variable: {}", name))),
+            ExprKind::Binary { left, op, right } => {
                 let left_val = self.eval_expr(left)?;
                 let right_val = self.eval_expr(right)?;
-                self.eval_binary_op(&left_val, op, &right_val)
+                ops::binary_op(&left_val, op, &right_val)
             }
-            Expr::Unary { op, expr } => {
+            ExprKind::Unary { op, expr } => {
                 let val = self.eval_expr(expr)?;
-                self.eval_unary_op(op, &val)
+                ops::unary_op(op, &val)
             }
-            Expr::Call { callee, args } => {
+            ExprKind::Call { callee, args } => {
                 let func = self.eval_expr(callee)?;
-                let arg_vals: Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a)).collect();
-                let arg_vals = arg_vals?;
-                self.call_function(func, arg_vals)
+                let mut positional = Vec::with_capacity(args.len());
+                let mut keywords = Vec::new();
+                for arg in args {
+                    match arg {
+                        Arg::Positional(value) => positional.push(self.eval_expr(value)?),
+                        Arg::Keyword(name, value) => keywords.push((name.clone(), self.eval_expr(value)?)),
+                    }
+                }
+                self.call_function(func, positional, keywords, expr.span)
             }
-            Expr::List(elements) => {
+            ExprKind::List(elements) => {
                 let vals: Result<Vec<_>, _> = elements.iter().map(|e| self.eval_expr(e)).collect();
-                Ok(Value::List(vals?))
+                Ok(Value::list(vals?))
+            }
+            ExprKind::Tuple(elements) => {
+                let vals: Result<Vec<_>, _> = elements.iter().map(|e| self.eval_expr(e)).collect();
+                Ok(Value::tuple(vals?))
+            }
+            ExprKind::Starred(_) => Err(RutenError::syntax_error(
+                "starred expression is only allowed in an assignment target",
+            )),
+            ExprKind::Comprehension { kind, clauses } => {
+                let result = match kind {
+                    ComprehensionKind::List(_) => Value::list(Vec::new()),
+                    ComprehensionKind::Dict(..) => Value::dict(DictMap::new()),
+                    ComprehensionKind::Set(_) => Value::set(KeySet::new()),
+                };
+                // the clauses' targets live in a scope of their own
+                let scope = self.env.child(&compiler::comprehension_cells(kind, clauses));
+                let outer = std::mem::replace(&mut self.env, scope);
+                let outcome = self.comprehend(kind, clauses, &result);
+                self.env = outer;
+                outcome.map(|_| result)
             }
-            Expr::Dict(pairs) => {
-                let mut map = HashMap::new();
+            ExprKind::Dict(pairs) => {
+                let mut map = DictMap::new();
                 for (key_expr, val_expr) in pairs {
-                    let key = match self.eval_expr(key_expr)? {
-                        Value::String(s) => s,
-                        other => other.to_string(),
-                    };
+                    let key = Key::new(self.eval_expr(key_expr)?)?;
                     let val = self.eval_expr(val_expr)?;
                     map.insert(key, val);
                 }
-                Ok(Value::Dict(map))
+                Ok(Value::dict(map))
+            }
+            ExprKind::Set(elements) => {
+                let mut items = KeySet::new();
+                for element in elements {
+                    items.insert(Key::new(self.eval_expr(element)?)?);
+                }
+                Ok(Value::set(items))
             }
-            Expr::Index { object, index } => {
+            ExprKind::Index { object, index } => {
                 let obj = self.eval_expr(object)?;
                 let idx = self.eval_expr(index)?;
-                match (obj, idx) {
-                    (Value::List(list), Value::Number(n)) => {
-                        let index = n as i32;
-                        let actual_index = if index < 0 {
-                            (list.len() as i32 + index) as usize
-                        } else {
-                            index as usize
-                        };
-                        list.get(actual_index)
-                            .cloned()
-                            .ok_or_else(|| RutenError::RuntimeError("list index out of range".to_string()))
-                    }
-                    (Value::Dict(dict), Value::String(key)) => {
-                        dict.get(&key)
-                            .cloned()
-                            .ok_or_else(|| RutenError::RuntimeError(format!("key not found: {}", key)))
-                    }
-                    (Value::String(s), Value::Number(n)) => {
-                        let index = n as i32;
-                        let actual_index = if index < 0 {
-                            (s.len() as i32 + index) as usize
-                        } else {
-                            index as usize
-                        };
-                        s.chars()
-                            .nth(actual_index)
-                            .map(|c| Value::String(c.to_string()))
-                            .ok_or_else(|| RutenError::RuntimeError("string index out of range".to_string()))
-                    }
-                    _ => Err(RutenError::TypeError("invalid index operation".to_string())),
-                }
+                ops::get_index(obj, idx)
             }
-            Expr::Member { object, member } => {
+            ExprKind::Slice {
+                object,
+                start,
+                stop,
+                step,
+            } => {
                 let obj = self.eval_expr(object)?;
-                match obj {
-                    Value::Module(map) => map
-                        .get(member)
-                        .cloned()
-                        .ok_or_else(|| RutenError::NameError(format!("module has no member: {}", member))),
-                    _ => Err(RutenError::TypeError("member access on non-module".to_string())),
-                }
+                let start = self.eval_bound(start)?;
+                let stop = self.eval_bound(stop)?;
+                let step = self.eval_bound(step)?;
+                ops::get_slice(obj, start, stop, step)
+            }
+            ExprKind::Member { object, member } => {
+                let obj = self.eval_expr(object)?;
+                ops::get_member(obj, member)
             }
+            ExprKind::Lambda { params, body } => self.make_function("<lambda>", params, body),
         }
     }
 
@@ -371,53 +688,56 @@ impl Interpreter {
             return Ok(());
         }
 
-        match stmt {
-            Stmt::Import(module) => {
-                let module_value = modules::load_module(module)?;
-                self.env.define(module.clone(), module_value);
+        self.eval_stmt_kind(&stmt.kind, stmt.span)
+            .map_err(|err| err.with_span(stmt.span))
+    }
+
+    fn eval_stmt_kind(&mut self, kind: &StmtKind, span: Span) -> Result<(), RutenError> {
+        match kind {
+            StmtKind::Import { module, alias } => {
+                let module_value = self.import(module, span)?;
+                self.env.assign(alias.as_ref().unwrap_or(module), module_value);
+            }
+            StmtKind::FromImport { module, names } => {
+                let members = match self.import(module, span)? {
+                    Value::Module(members) => members,
+                    _ => unreachable!(),
+                };
+                for (name, alias) in names {
+                    let value = members.get(name).ok_or_else(|| {
+                        RutenError::import_error(format!("cannot import name '{}' from '{}'", name, module))
+                    })?;
+                    self.env.assign(alias.as_ref().unwrap_or(name), value);
+                }
             }
-            Stmt::Assign { name, value } => {
+            StmtKind::Assign { target, value } => {
                 let val = self.eval_expr(value)?;
-                self.env.set(name, val)?;
+                self.assign(target, val)?;
             }
-            Stmt::FunctionDef { name, params, body } => {
-                let func = Value::Function {
-                    params: params.clone(),
-                    body: body.clone(),
-                    closure: self.env.clone(),
-                };
-                self.env.define(name.clone(), func);
+            StmtKind::FunctionDef { name, params, body } => {
+                let func = self.make_function(name, params, body)?;
+                self.env.assign(name, func);
             }
-            Stmt::Return(expr) => {
+            StmtKind::Return(expr) => {
                 self.return_value = Some(if let Some(e) = expr {
                     self.eval_expr(e)?
                 } else {
                     Value::None
                 });
             }
-            Stmt::If {
+            StmtKind::If {
                 condition,
                 then_branch,
                 else_branch,
             } => {
                 let cond_val = self.eval_expr(condition)?;
                 if cond_val.is_truthy() {
-                    for stmt in then_branch {
-                        self.eval_stmt(stmt)?;
-                        if self.return_value.is_some() || self.break_flag || self.continue_flag {
-                            break;
-                        }
-                    }
+                    self.eval_block(then_branch)?;
                 } else if let Some(else_stmts) = else_branch {
-                    for stmt in else_stmts {
-                        self.eval_stmt(stmt)?;
-                        if self.return_value.is_some() || self.break_flag || self.continue_flag {
-                            break;
-                        }
-                    }
+                    self.eval_block(else_stmts)?;
                 }
             }
-            Stmt::While { condition, body } => {
+            StmtKind::While { condition, body } => {
                 while self.eval_expr(condition)?.is_truthy() {
                     for stmt in body {
                         self.eval_stmt(stmt)?;
@@ -435,20 +755,12 @@ impl Interpreter {
                     }
                 }
             }
-            Stmt::For { var, iterable, body } => {
+            StmtKind::For { target, iterable, body } => {
                 let iter_val = self.eval_expr(iterable)?;
-                let items = match iter_val {
-                    Value::List(items) => items,
-                    Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
-                    _ => {
-                        return Err(RutenError::TypeError(
-                            "for loop requires an iterable".to_string(),
-                        ))
-                    }
-                };
+                let items = ops::iter_items(&iter_val).map_err(|err| err.with_span(iterable.span))?;
 
                 for item in items {
-                    self.env.set(var, item)?;
+                    self.assign(target, item)?;
                     for stmt in body {
                         self.eval_stmt(stmt)?;
                         if self.return_value.is_some() {
@@ -465,13 +777,67 @@ impl Interpreter {
                     }
                 }
             }
-            Stmt::Break => {
+            StmtKind::Try {
+                body,
+                handlers,
+                finally_body,
+            } => {
+                let mut outcome = self.eval_block(body);
+
+                if let Err(err) = outcome {
+                    outcome = match self.find_handler(handlers, &err) {
+                        Ok(Some(handler)) => self.eval_handler(handler, err),
+                        Ok(None) => Err(err),
+                        Err(handler_err) => Err(handler_err),
+                    };
+                }
+
+                if let Some(finally_stmts) = finally_body {
+                    // finally runs even while a return, break or continue is pending
+                    let pending = (self.return_value.take(), self.break_flag, self.continue_flag);
+                    self.break_flag = false;
+                    self.continue_flag = false;
+
+                    self.eval_block(finally_stmts)?;
+
+                    // control flow inside finally wins over whatever was pending
+                    if self.return_value.is_none() && !self.break_flag && !self.continue_flag {
+                        self.return_value = pending.0;
+                        self.break_flag = pending.1;
+                        self.continue_flag = pending.2;
+                    } else {
+                        outcome = Ok(());
+                    }
+                }
+
+                outcome?;
+            }
+            StmtKind::Global(names) => {
+                for name in names {
+                    self.env.declare_global(name);
+                }
+            }
+            StmtKind::Nonlocal(names) => {
+                for name in names {
+                    self.env.declare_nonlocal(name);
+                }
+            }
+            StmtKind::Raise(expr) => {
+                let err = match expr {
+                    None => self.handling.last().cloned().ok_or_else(|| {
+                        RutenError::runtime_error("no active error to re-raise".to_string())
+                    })?,
+                    Some(e) => ops::raised_error(self.eval_expr(e)?)?,
+                };
+                return Err(err);
+            }
+            StmtKind::Break => {
                 self.break_flag = true;
             }
-            Stmt::Continue => {
+            StmtKind::Continue => {
                 self.continue_flag = true;
             }
-            Stmt::Expression(expr) => {
+            StmtKind::Expression(expr) => {
                 self.eval_expr(expr)?;
             }
         }
@@ -479,97 +845,292 @@ impl Interpreter {
         Ok(())
     }
 
-    fn eval_binary_op(&self, left: &Value, op: &BinaryOp, right: &Value) -> Result<Value, RutenError> {
-        match (left, op, right) {
-            (Value::Number(l), BinaryOp::Add, Value::Number(r)) => Ok(Value::Number(l + r)),
-            (Value::Number(l), BinaryOp::Sub, Value::Number(r)) => Ok(Value::Number(l - r)),
-            (Value::Number(l), BinaryOp::Mul, Value::Number(r)) => Ok(Value::Number(l * r)),
-            (Value::Number(l), BinaryOp::Div, Value::Number(r)) => {
-                if *r == 0.0 {
-                    Err(RutenError::RuntimeError("division by zero".to_string()))
-                } else {
-                    Ok(Value::Number(l / r))
+    fn assign(&mut self, target: &Expr, value: Value) -> Result<(), RutenError> {
+        match &target.kind {
+            ExprKind::Identifier(name) => {
+                self.env.assign(name, value);
+                Ok(())
+            }
+            ExprKind::Index { object, index } => {
+                let obj = self.eval_expr(object)?;
+                let idx = self.eval_expr(index)?;
+                ops::set_index(obj, idx, value).map_err(|err| err.with_span(target.span))
+            }
+            ExprKind::Member { object, member } => {
+                let obj = self.eval_expr(object)?;
+                ops::set_member(obj, member, value).map_err(|err| err.with_span(target.span))
+            }
+            ExprKind::Tuple(targets) | ExprKind::List(targets) => {
+                let star = targets.iter().position(|t| matches!(t.kind, ExprKind::Starred(_)));
+                let values = ops::unpack(value, targets.len(), star).map_err(|err| err.with_span(target.span))?;
+                for (target, value) in targets.iter().zip(values) {
+                    match &target.kind {
+                        ExprKind::Starred(inner) => self.assign(inner, value)?,
+                        _ => self.assign(target, value)?,
+                    }
                 }
+                Ok(())
             }
-            (Value::Number(l), BinaryOp::Mod, Value::Number(r)) => Ok(Value::Number(l % r)),
-            (Value::String(l), BinaryOp::Add, Value::String(r)) => {
-                Ok(Value::String(format!("{}{}", l, r)))
-            }
-            (Value::Number(l), BinaryOp::Equal, Value::Number(r)) => Ok(Value::Bool(l == r)),
-            (Value::String(l), BinaryOp::Equal, Value::String(r)) => Ok(Value::Bool(l == r)),
-            (Value::Bool(l), BinaryOp::Equal, Value::Bool(r)) => Ok(Value::Bool(l == r)),
-            (Value::Number(l), BinaryOp::NotEqual, Value::Number(r)) => Ok(Value::Bool(l != r)),
-            (Value::String(l), BinaryOp::NotEqual, Value::String(r)) => Ok(Value::Bool(l != r)),
-            (Value::Bool(l), BinaryOp::NotEqual, Value::Bool(r)) => Ok(Value::Bool(l != r)),
-            (Value::Number(l), BinaryOp::Less, Value::Number(r)) => Ok(Value::Bool(l < r)),
-            (Value::Number(l), BinaryOp::LessEqual, Value::Number(r)) => Ok(Value::Bool(l <= r)),
-            (Value::Number(l), BinaryOp::Greater, Value::Number(r)) => Ok(Value::Bool(l > r)),
-            (Value::Number(l), BinaryOp::GreaterEqual, Value::Number(r)) => Ok(Value::Bool(l >= r)),
-            (l, BinaryOp::And, r) => Ok(Value::Bool(l.is_truthy() && r.is_truthy())),
-            (l, BinaryOp::Or, r) => Ok(Value::Bool(l.is_truthy() || r.is_truthy())),
-            _ => Err(RutenError::TypeError(format!(
-                "unsupported operation: {} {:?} {}",
-                left.to_string(),
-                op,
-                right.to_string()
-            ))),
-        }
-    }
-
-    fn eval_unary_op(&self, op: &UnaryOp, val: &Value) -> Result<Value, RutenError> {
-        match (op, val) {
-            (UnaryOp::Neg, Value::Number(n)) => Ok(Value::Number(-n)),
-            (UnaryOp::Not, v) => Ok(Value::Bool(!v.is_truthy())),
-            _ => Err(RutenError::TypeError(format!(
-                "unsupported unary operation: {:?} {}",
-                op,
-                val.to_string()
-            ))),
-        }
-    }
-
-    fn call_function(&mut self, func: Value, args: Vec<Value>) -> Result<Value, RutenError> {
-        match func {
-            Value::NativeFunction(f) => f(&args),
-            Value::Function {
-                params,
-                body,
-                closure,
-            } => {
-                if params.len() != args.len() {
-                    return Err(RutenError::RuntimeError(format!(
-                        "function expects {} arguments, got {}",
-                        params.len(),
-                        args.len()
-                    )));
+            _ => Err(RutenError::syntax_error("cannot assign to this expression".to_string())
+                .with_span(target.span)),
+        }
+    }
+
+    // runs the remaining clauses of a comprehension, adding to `result` once none are left
+    fn comprehend(
+        &mut self,
+        kind: &ComprehensionKind,
+        clauses: &[ComprehensionClause],
+        result: &Value,
+    ) -> Result<(), RutenError> {
+        match clauses.split_first() {
+            None => match (kind, result) {
+                (ComprehensionKind::List(element), Value::List(items)) => {
+                    let value = self.eval_expr(element)?;
+                    items.borrow_mut().push(value);
+                    Ok(())
+                }
+                (ComprehensionKind::Set(element), Value::Set(items)) => {
+                    let value = self.eval_expr(element)?;
+                    items.borrow_mut().insert(Key::new(value).map_err(|err| err.with_span(element.span))?);
+                    Ok(())
+                }
+                (ComprehensionKind::Dict(key, value), _) => {
+                    let key = self.eval_expr(key)?;
+                    let value = self.eval_expr(value)?;
+                    ops::set_index(result.clone(), key, value)
+                }
+                _ => unreachable!(),
+            },
+            Some((ComprehensionClause::For { target, iterable }, rest)) => {
+                let value = self.eval_expr(iterable)?;
+                let items = ops::iter_items(&value).map_err(|err| err.with_span(iterable.span))?;
+                for item in items {
+                    self.assign(target, item)?;
+                    self.comprehend(kind, rest, result)?;
                 }
+                Ok(())
+            }
+            Some((ComprehensionClause::If(condition), rest)) => {
+                if self.eval_expr(condition)?.is_truthy() {
+                    self.comprehend(kind, rest, result)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval_block(&mut self, stmts: &[Stmt]) -> Result<(), RutenError> {
+        for stmt in stmts {
+            self.eval_stmt(stmt)?;
+            if self.return_value.is_some() || self.break_flag || self.continue_flag {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // the first except clause whose kind matches `err`; a bare `except:` matches anything
+    fn find_handler<'a>(
+        &mut self,
+        handlers: &'a [ExceptHandler],
+        err: &RutenError,
+    ) -> Result<Option<&'a ExceptHandler>, RutenError> {
+        for handler in handlers {
+            let kind_expr = match &handler.kind {
+                None => return Ok(Some(handler)),
+                Some(kind_expr) => kind_expr,
+            };
+            let kind = self.eval_expr(kind_expr)?;
+            if ops::error_matches(&kind, err).map_err(|e| e.with_span(kind_expr.span))? {
+                return Ok(Some(handler));
+            }
+        }
+        Ok(None)
+    }
 
-                // save current environment
-                let saved_env = self.env.clone();
-                self.env = closure;
-                self.env.push_scope();
+    fn eval_handler(&mut self, handler: &ExceptHandler, err: RutenError) -> Result<(), RutenError> {
+        if let Some(name) = &handler.name {
+            self.env.assign(name, Value::Error(Box::new(err.clone())));
+        }
+        self.handling.push(err);
+        let outcome = self.eval_block(&handler.body);
+        self.handling.pop();
+        outcome
+    }
+
+    // defaults are evaluated here, once, in the defining scope
+    fn make_function(
+        &mut self,
+        name: &str,
+        params: &Rc<[Param]>,
+        body: &Rc<[Stmt]>,
+    ) -> Result<Value, RutenError> {
+        let mut defaults = Vec::with_capacity(params.len());
+        for param in params.iter() {
+            defaults.push(match &param.default {
+                Some(expr) => Some(self.eval_expr(expr)?),
+                None => None,
+            });
+        }
+        let captures = Rc::clone(
+            &self
+                .captures
+                .entry(Rc::as_ptr(body) as *const Stmt)
+                .or_insert_with(|| (Rc::clone(body), Rc::new(Captures::of(params, body))))
+                .1,
+        );
+        Ok(Value::Function(Rc::new(Function {
+            name: name.to_string(),
+            params: Rc::clone(params),
+            defaults,
+            body: Rc::clone(body),
+            closure: self.env.closure(&captures.free),
+            captures,
+            file: self.filename.clone(),
+        })))
+    }
 
-                // bind parameters
-                for (param, arg) in params.iter().zip(args.iter()) {
-                    self.env.define(param.clone(), arg.clone());
+    fn import(&mut self, name: &str, span: Span) -> Result<Value, RutenError> {
+        let loader = Rc::clone(&self.loader);
+        loader::import(&self.loader, name, &self.filename, span, |file, source| {
+            let mut interpreter = Interpreter::new();
+            interpreter.filename = file.to_string();
+            interpreter.loader = loader;
+            lexer::tokenize(source)
+                .and_then(parser::parse)
+                .map(optimizer::optimize)
+                .and_then(|program| interpreter.eval_program(program))?;
+            Ok(interpreter.exports())
+        })
+    }
+
+    fn call_function(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+        call_site: Span,
+    ) -> Result<Value, RutenError> {
+        match func {
+            Value::Function(function) => {
+                ops::check_call_depth(self.call_stack.len())?;
+                let args = ops::bind_args(&function.name, &function.params, &function.defaults, args, kwargs)?;
+
+                // a call only costs a fresh scope on top of the function's closure
+                let scope = function.closure.child(&function.captures.cells);
+                let saved_env = std::mem::replace(&mut self.env, scope);
+                for (param, arg) in function.params.iter().zip(args) {
+                    self.env.define(param.name.clone(), arg);
                 }
+                self.call_stack.push(Frame {
+                    function: Rc::clone(&function),
+                    call_site,
+                });
 
                 // execute function body
-                for stmt in &body {
-                    self.eval_stmt(stmt)?;
-                    if self.return_value.is_some() {
+                let mut outcome = Ok(());
+                for stmt in function.body.iter() {
+                    outcome = self.eval_stmt(stmt);
+                    if outcome.is_err() || self.return_value.is_some() {
                         break;
                     }
                 }
 
                 let result = self.return_value.take().unwrap_or(Value::None);
-                
+                let outcome = outcome.map_err(|err| self.with_traceback(err.with_file(&function.file)));
+
                 // restore environment
-                self.env = saved_env;
+                self.call_stack.pop();
+                std::mem::replace(&mut self.env, saved_env).release();
 
-                Ok(result)
+                outcome.map(|()| result)
             }
-            _ => Err(RutenError::TypeError("not a callable object".to_string())),
+            Value::Closure(_) => Err(RutenError::type_error(
+                "bytecode functions can only be called by the vm".to_string(),
+            )),
+            func => ops::call_builtin(self, func, args, kwargs, call_site),
+        }
+    }
+
+    // records the current call stack on an error leaving the innermost function
+    fn with_traceback(&self, mut err: RutenError) -> RutenError {
+        if !err.traceback.is_empty() || self.call_stack.is_empty() {
+            return err;
+        }
+
+        let mut location = err.span;
+        for frame in self.call_stack.iter().rev() {
+            err.traceback.push(TraceFrame {
+                function: frame.function.name.clone(),
+                file: frame.function.file.clone(),
+                span: location,
+            });
+            location = Some(frame.call_site);
         }
+        err.traceback.push(TraceFrame {
+            function: "<module>".to_string(),
+            file: self.filename.clone(),
+            span: location,
+        });
+        err
+    }
+}
+
+impl Caller for Interpreter {
+    fn call_value(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+        call_site: Span,
+    ) -> Result<Value, RutenError> {
+        self.call_function(func, args, kwargs, call_site)
+    }
+}
+
+// the items a `for` loop visits; lists are copied so the loop body may mutate them,
+// and dicts yield their keys
+pub fn iterate(value: &Value) -> Option<Vec<Value>> {
+    match value {
+        Value::List(items) => Some(items.borrow().clone()),
+        Value::Tuple(items) => Some(items.to_vec()),
+        Value::Dict(map) => Some(map.borrow().keys().map(|k| k.value().clone()).collect()),
+        Value::Set(items) => Some(items.borrow().iter().map(|k| k.value().clone()).collect()),
+        Value::String(s) => Some(s.chars().map(|c| Value::String(c.to_string())).collect()),
+        _ => None,
     }
-}
\ No newline at end of file
+}
+
+// position for index `n` into a sequence of `len` items; negative indices count from the end
+pub fn resolve_index(n: &Int, len: usize) -> Option<usize> {
+    let n = n.to_i64()?;
+    let index = if n < 0 { n.checked_add(len as i64)? } else { n };
+    if index >= 0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+/// the name of a value's type as shown in error messages
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "float",
+        Value::Int(_) => "int",
+        Value::String(_) => "string",
+        Value::Bool(_) => "bool",
+        Value::None => "None",
+        Value::List(_) => "list",
+        Value::Tuple(_) => "tuple",
+        Value::Dict(_) => "dict",
+        Value::Set(_) => "set",
+        Value::Function(_)
+        | Value::Closure(_)
+        | Value::NativeFunction(_)
+        | Value::BoundMethod { .. } => "function",
+        Value::Module(_) => "module",
+        Value::ErrorType(_) => "error type",
+        Value::Error(_) => "error",
+    }
+}
@@ -1,10 +1,15 @@
-use crate::error::RutenError;
+use crate::error::{RutenError, Span};
+use crate::int::Int;
+use std::iter::Peekable;
+use std::str::Chars;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     //  -- literals --
     Number(f64),
+    Int(Int),
     String(String),
+    FString(Vec<FStringChunk>),
     Identifier(String),
     True,
     False,
@@ -12,22 +17,37 @@ pub enum Token {
 
     // -- keywords --
     Import,
+    From,
     Def,
+    Lambda,
     Return,
     If,
+    Elif,
     Else,
     While,
     For,
     In,
     Break,
     Continue,
+    Try,
+    Except,
+    Finally,
+    Raise,
+    As,
+    Global,
+    Nonlocal,
 
     // -- operators --
     Plus,
     Minus,
     Star,
+    StarStar,
     Slash,
+    SlashSlash,
     Percent,
+    Pipe,
+    Ampersand,
+    Caret,
     Equal,
     EqualEqual,
     NotEqual,
@@ -50,15 +70,150 @@ pub enum Token {
     Dot,
     Colon,
     Newline,
+    Indent,
+    Dedent,
 
     Eof,
 }
 
-pub fn tokenize(source: &str) -> Result<Vec<Token>, RutenError> {
-    let mut tokens = Vec::new();
-    let mut chars = source.chars().peekable();
+/// a piece of an f-string: literal text, or the tokens of a `{expr:spec}` field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FStringChunk {
+    Literal(String),
+    Field(Vec<SpannedToken>, Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+// character stream that keeps track of where in the source it is
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Cursor {
+            chars: source.chars().peekable(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    // the character `n` places past the next one
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        self.pos += 1;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn here(&self) -> Span {
+        Span::new(self.pos, self.pos, self.line, self.column)
+    }
+
+    fn since(&self, start: Span) -> Span {
+        Span::new(start.start, self.pos, start.line, start.column)
+    }
+
+    fn token(&self, token: Token, start: Span) -> SpannedToken {
+        SpannedToken {
+            token,
+            span: self.since(start),
+        }
+    }
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<SpannedToken>, RutenError> {
+    let mut tokens: Vec<SpannedToken> = Vec::new();
+    let mut chars = Cursor::new(source);
+
+    // indentation state: each entry is the exact whitespace prefix of an open block
+    let mut indent_stack: Vec<String> = vec![String::new()];
+    let mut at_line_start = true;
+    // newlines and indentation inside brackets are insignificant
+    let mut depth: usize = 0;
+
+    loop {
+        if at_line_start && depth == 0 {
+            let mut indent = String::new();
+            while let Some(ch) = chars.peek() {
+                if ch == ' ' || ch == '\t' {
+                    indent.push(ch);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            // blank and comment-only lines never open or close a block
+            match chars.peek() {
+                None => break,
+                Some('\n') | Some('\r') | Some('#') => {
+                    while let Some(ch) = chars.peek() {
+                        chars.next();
+                        if ch == '\n' {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let here = chars.here();
+            let current = indent_stack.last().unwrap();
+            if indent != *current {
+                if indent.starts_with(current.as_str()) {
+                    indent_stack.push(indent);
+                    tokens.push(SpannedToken { token: Token::Indent, span: here });
+                } else if current.starts_with(indent.as_str()) {
+                    while indent_stack.last().unwrap().len() > indent.len() {
+                        indent_stack.pop();
+                        tokens.push(SpannedToken { token: Token::Dedent, span: here });
+                    }
+                    if *indent_stack.last().unwrap() != indent {
+                        return Err(RutenError::syntax_error(
+                            "unindent does not match any outer indentation level",
+                        )
+                        .with_span(here));
+                    }
+                } else {
+                    return Err(RutenError::syntax_error(
+                        "inconsistent use of tabs and spaces in indentation",
+                    )
+                    .with_span(here));
+                }
+            }
+            at_line_start = false;
+        }
+
+        let ch = match chars.peek() {
+            Some(ch) => ch,
+            None => break,
+        };
+        let start = chars.here();
 
-    while let Some(&ch) = chars.peek() {
         match ch {
             // -- whitespace -- | note: except newlines
             ' ' | '\t' | '\r' => {
@@ -67,12 +222,17 @@ pub fn tokenize(source: &str) -> Result<Vec<Token>, RutenError> {
             // newlines
             '\n' => {
                 chars.next();
-                tokens.push(Token::Newline);
+                if depth == 0 {
+                    if !matches!(tokens.last().map(|t| &t.token), None | Some(Token::Newline)) {
+                        tokens.push(SpannedToken { token: Token::Newline, span: start });
+                    }
+                    at_line_start = true;
+                }
             }
             // -- comments -- | note: lowercase as per spec
             '#' => {
                 chars.next();
-                while let Some(&ch) = chars.peek() {
+                while let Some(ch) = chars.peek() {
                     if ch == '\n' {
                         break;
                     }
@@ -81,55 +241,34 @@ pub fn tokenize(source: &str) -> Result<Vec<Token>, RutenError> {
             }
             // -- numbers --
             '0'..='9' => {
-                let mut num_str = String::new();
-                while let Some(&ch) = chars.peek() {
-                    if ch.is_ascii_digit() || ch == '.' {
-                        num_str.push(ch);
+                // take the whole literal, including anything that doesn't belong in it,
+                // so a mistake is reported against everything the user wrote
+                let mut text = String::new();
+                while let Some(ch) = chars.peek() {
+                    let exponent_sign =
+                        matches!(ch, '+' | '-') && text.ends_with(['e', 'E']) && radix_prefix(&text).is_none();
+                    if ch.is_ascii_alphanumeric() || ch == '_' || ch == '.' || exponent_sign {
+                        text.push(ch);
                         chars.next();
                     } else {
                         break;
                     }
                 }
-                let num = num_str.parse::<f64>()
-                    .map_err(|_| RutenError::SyntaxError(format!("invalid number: {}", num_str)))?;
-                tokens.push(Token::Number(num));
+                let token = number(&text).map_err(|reason| {
+                    RutenError::syntax_error(format!("invalid number '{}': {}", text, reason))
+                        .with_span(chars.since(start))
+                })?;
+                tokens.push(chars.token(token, start));
             }
             // -- strings --
             '"' | '\'' => {
-                let quote = ch;
-                chars.next();
-                let mut string = String::new();
-                while let Some(&ch) = chars.peek() {
-                    chars.next();
-                    if ch == quote {
-                        break;
-                    }
-                    if ch == '\\' {
-                        if let Some(&next_ch) = chars.peek() {
-                            chars.next();
-                            match next_ch {
-                                'n' => string.push('\n'),
-                                't' => string.push('\t'),
-                                'r' => string.push('\r'),
-                                '\\' => string.push('\\'),
-                                '"' => string.push('"'),
-                                '\'' => string.push('\''),
-                                _ => {
-                                    string.push('\\');
-                                    string.push(next_ch);
-                                }
-                            }
-                        }
-                    } else {
-                        string.push(ch);
-                    }
-                }
-                tokens.push(Token::String(string));
+                let token = string(&mut chars, "", start)?;
+                tokens.push(chars.token(token, start));
             }
             // -- identifiers and keywords --
             'a'..='z' | 'A'..='Z' | '_' => {
                 let mut ident = String::new();
-                while let Some(&ch) = chars.peek() {
+                while let Some(ch) = chars.peek() {
                     if ch.is_alphanumeric() || ch == '_' {
                         ident.push(ch);
                         chars.next();
@@ -139,124 +278,425 @@ pub fn tokenize(source: &str) -> Result<Vec<Token>, RutenError> {
                 }
                 let token = match ident.as_str() {
                     "import" => Token::Import,
+                    "from" => Token::From,
                     "def" => Token::Def,
+                    "lambda" => Token::Lambda,
                     "return" => Token::Return,
                     "if" => Token::If,
+                    "elif" => Token::Elif,
                     "else" => Token::Else,
                     "while" => Token::While,
                     "for" => Token::For,
                     "in" => Token::In,
                     "break" => Token::Break,
                     "continue" => Token::Continue,
+                    "try" => Token::Try,
+                    "except" => Token::Except,
+                    "finally" => Token::Finally,
+                    "raise" => Token::Raise,
+                    "as" => Token::As,
+                    "global" => Token::Global,
+                    "nonlocal" => Token::Nonlocal,
                     "True" => Token::True,
                     "False" => Token::False,
                     "None" => Token::None,
                     "and" => Token::And,
                     "or" => Token::Or,
                     "not" => Token::Not,
+                    // a string prefix, as in f"..." or r'...'
+                    prefix if is_string_prefix(prefix) && matches!(chars.peek(), Some('"' | '\'')) => {
+                        string(&mut chars, prefix, start)?
+                    }
                     _ => Token::Identifier(ident),
                 };
-                tokens.push(token);
+                tokens.push(chars.token(token, start));
             }
             // -- operators and delimiters --
             '+' => {
                 chars.next();
-                tokens.push(Token::Plus);
+                tokens.push(chars.token(Token::Plus, start));
             }
             '-' => {
                 chars.next();
-                tokens.push(Token::Minus);
+                tokens.push(chars.token(Token::Minus, start));
             }
             '*' => {
                 chars.next();
-                tokens.push(Token::Star);
+                if let Some('*') = chars.peek() {
+                    chars.next();
+                    tokens.push(chars.token(Token::StarStar, start));
+                } else {
+                    tokens.push(chars.token(Token::Star, start));
+                }
             }
             '/' => {
                 chars.next();
-                tokens.push(Token::Slash);
+                if let Some('/') = chars.peek() {
+                    chars.next();
+                    tokens.push(chars.token(Token::SlashSlash, start));
+                } else {
+                    tokens.push(chars.token(Token::Slash, start));
+                }
             }
             '%' => {
                 chars.next();
-                tokens.push(Token::Percent);
+                tokens.push(chars.token(Token::Percent, start));
+            }
+            '|' => {
+                chars.next();
+                tokens.push(chars.token(Token::Pipe, start));
+            }
+            '&' => {
+                chars.next();
+                tokens.push(chars.token(Token::Ampersand, start));
+            }
+            '^' => {
+                chars.next();
+                tokens.push(chars.token(Token::Caret, start));
             }
             '=' => {
                 chars.next();
-                if let Some(&'=') = chars.peek() {
+                if let Some('=') = chars.peek() {
                     chars.next();
-                    tokens.push(Token::EqualEqual);
+                    tokens.push(chars.token(Token::EqualEqual, start));
                 } else {
-                    tokens.push(Token::Equal);
+                    tokens.push(chars.token(Token::Equal, start));
                 }
             }
             '!' => {
                 chars.next();
-                if let Some(&'=') = chars.peek() {
+                if let Some('=') = chars.peek() {
                     chars.next();
-                    tokens.push(Token::NotEqual);
+                    tokens.push(chars.token(Token::NotEqual, start));
                 } else {
-                    return Err(RutenError::SyntaxError("unexpected character '!'".to_string()));
+                    return Err(RutenError::syntax_error("unexpected character '!'").with_span(chars.since(start)));
                 }
             }
             '<' => {
                 chars.next();
-                if let Some(&'=') = chars.peek() {
+                if let Some('=') = chars.peek() {
                     chars.next();
-                    tokens.push(Token::LessEqual);
+                    tokens.push(chars.token(Token::LessEqual, start));
                 } else {
-                    tokens.push(Token::Less);
+                    tokens.push(chars.token(Token::Less, start));
                 }
             }
             '>' => {
                 chars.next();
-                if let Some(&'=') = chars.peek() {
+                if let Some('=') = chars.peek() {
                     chars.next();
-                    tokens.push(Token::GreaterEqual);
+                    tokens.push(chars.token(Token::GreaterEqual, start));
                 } else {
-                    tokens.push(Token::Greater);
+                    tokens.push(chars.token(Token::Greater, start));
                 }
             }
             '(' => {
                 chars.next();
-                tokens.push(Token::LeftParen);
+                depth += 1;
+                tokens.push(chars.token(Token::LeftParen, start));
             }
             ')' => {
                 chars.next();
-                tokens.push(Token::RightParen);
+                depth = depth.saturating_sub(1);
+                tokens.push(chars.token(Token::RightParen, start));
             }
             '[' => {
                 chars.next();
-                tokens.push(Token::LeftBracket);
+                depth += 1;
+                tokens.push(chars.token(Token::LeftBracket, start));
             }
             ']' => {
                 chars.next();
-                tokens.push(Token::RightBracket);
+                depth = depth.saturating_sub(1);
+                tokens.push(chars.token(Token::RightBracket, start));
             }
             '{' => {
                 chars.next();
-                tokens.push(Token::LeftBrace);
+                depth += 1;
+                tokens.push(chars.token(Token::LeftBrace, start));
             }
             '}' => {
                 chars.next();
-                tokens.push(Token::RightBrace);
+                depth = depth.saturating_sub(1);
+                tokens.push(chars.token(Token::RightBrace, start));
             }
             ',' => {
                 chars.next();
-                tokens.push(Token::Comma);
+                tokens.push(chars.token(Token::Comma, start));
             }
             '.' => {
                 chars.next();
-                tokens.push(Token::Dot);
+                tokens.push(chars.token(Token::Dot, start));
             }
             ':' => {
                 chars.next();
-                tokens.push(Token::Colon);
+                tokens.push(chars.token(Token::Colon, start));
             }
             _ => {
-                return Err(RutenError::SyntaxError(format!("unexpected character: '{}'", ch)));
+                return Err(RutenError::syntax_error(format!("unexpected character: '{}'", ch))
+                    .with_span(chars.since(start)));
             }
         }
     }
 
-    tokens.push(Token::Eof);
+    // close the last line and any blocks still open at end of input
+    let end = chars.here();
+    if !matches!(tokens.last().map(|t| &t.token), None | Some(Token::Newline)) {
+        tokens.push(SpannedToken { token: Token::Newline, span: end });
+    }
+    for _ in 1..indent_stack.len() {
+        tokens.push(SpannedToken { token: Token::Dedent, span: end });
+    }
+
+    tokens.push(SpannedToken { token: Token::Eof, span: end });
     Ok(tokens)
 }
+
+// the base and name of a 0x, 0o or 0b literal
+fn radix_prefix(text: &str) -> Option<(u32, &'static str)> {
+    let mut chars = text.chars();
+    if chars.next() != Some('0') {
+        return None;
+    }
+    match chars.next() {
+        Some('x' | 'X') => Some((16, "hexadecimal")),
+        Some('o' | 'O') => Some((8, "octal")),
+        Some('b' | 'B') => Some((2, "binary")),
+        _ => None,
+    }
+}
+
+// the token for a numeric literal, or why it is malformed
+fn number(text: &str) -> Result<Token, String> {
+    if let Some((radix, kind)) = radix_prefix(text) {
+        // an underscore may also follow the prefix, as in 0x_ff
+        let rest = &text[2..];
+        let digits = self::digits(rest.strip_prefix('_').unwrap_or(rest), radix, kind)?;
+        return Int::parse_radix(&digits, radix)
+            .map(Token::Int)
+            .ok_or_else(|| format!("missing {} digits", kind));
+    }
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(at) => (&text[..at], Some(&text[at + 1..])),
+        None => (text, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((_, fraction)) if fraction.contains('.') => {
+            return Err("more than one decimal point".to_string())
+        }
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let whole = digits(whole, 10, "decimal")?;
+    if fraction.is_none() && exponent.is_none() {
+        // without a decimal point or exponent the literal is an integer, however large
+        return Int::parse(&whole).map(Token::Int).ok_or_else(|| "missing decimal digits".to_string());
+    }
+
+    let fraction = match fraction {
+        Some("") | None => "0".to_string(),
+        Some(fraction) => digits(fraction, 10, "decimal")?,
+    };
+    let exponent = match exponent {
+        Some(exponent) => {
+            let (sign, exponent) = match exponent.strip_prefix('-') {
+                Some(rest) => ("-", rest),
+                None => ("", exponent.strip_prefix('+').unwrap_or(exponent)),
+            };
+            format!("e{}{}", sign, digits(exponent, 10, "exponent")?)
+        }
+        None => String::new(),
+    };
+    format!("{}.{}{}", whole, fraction, exponent)
+        .parse::<f64>()
+        .map(Token::Number)
+        .map_err(|e| e.to_string())
+}
+
+// the digits of one part of a literal with its `_` separators removed
+fn digits(text: &str, radix: u32, kind: &str) -> Result<String, String> {
+    if text.is_empty() {
+        return Err(format!("missing {} digits", kind));
+    }
+    if text.starts_with('_') || text.ends_with('_') || text.contains("__") {
+        return Err("underscores must separate digits".to_string());
+    }
+    let mut digits = String::with_capacity(text.len());
+    for ch in text.chars().filter(|&ch| ch != '_') {
+        if !ch.is_digit(radix) {
+            return Err(format!("'{}' is not a valid {} digit", ch, kind));
+        }
+        digits.push(ch);
+    }
+    Ok(digits)
+}
+
+fn is_string_prefix(ident: &str) -> bool {
+    matches!(ident.to_ascii_lowercase().as_str(), "f" | "r" | "fr" | "rf")
+}
+
+// lexes a string literal whose opening quote is next; `prefix` is any f/r prefix
+fn string(chars: &mut Cursor, prefix: &str, start: Span) -> Result<Token, RutenError> {
+    let prefix = prefix.to_ascii_lowercase();
+    let raw = prefix.contains('r');
+    let format = prefix.contains('f');
+    let unterminated = |chars: &Cursor| {
+        RutenError::syntax_error("unterminated string literal").with_span(chars.since(start))
+    };
+
+    let quote = chars.next().unwrap_or('"');
+    let triple = chars.peek() == Some(quote) && chars.peek_nth(1) == Some(quote);
+    if triple {
+        chars.next();
+        chars.next();
+    }
+
+    let mut chunks = Vec::new();
+    let mut text = String::new();
+    loop {
+        let ch = match chars.peek() {
+            Some(ch) => ch,
+            None => return Err(unterminated(chars)),
+        };
+        if ch == quote && (!triple || (chars.peek_nth(1) == Some(quote) && chars.peek_nth(2) == Some(quote))) {
+            for _ in 0..if triple { 3 } else { 1 } {
+                chars.next();
+            }
+            break;
+        }
+        // only triple-quoted strings may span lines
+        if ch == '\n' && !triple {
+            return Err(unterminated(chars));
+        }
+        chars.next();
+        match ch {
+            '\\' => {
+                let next_ch = chars.next().ok_or_else(|| unterminated(chars))?;
+                if raw {
+                    // the backslash stays, but still keeps a quote from ending the string
+                    text.push('\\');
+                    text.push(next_ch);
+                    continue;
+                }
+                match next_ch {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'r' => text.push('\r'),
+                    '\\' => text.push('\\'),
+                    '"' => text.push('"'),
+                    '\'' => text.push('\''),
+                    // a backslash at the end of a line continues the string on the next
+                    '\n' => {}
+                    _ => {
+                        text.push('\\');
+                        text.push(next_ch);
+                    }
+                }
+            }
+            '{' if format && chars.peek() == Some('{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if format && chars.peek() == Some('}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' if format => {
+                if !text.is_empty() {
+                    chunks.push(FStringChunk::Literal(std::mem::take(&mut text)));
+                }
+                chunks.push(field(chars, triple)?);
+            }
+            '}' if format => {
+                return Err(RutenError::syntax_error("f-string: single '}' is not allowed")
+                    .with_span(chars.since(start)));
+            }
+            _ => text.push(ch),
+        }
+    }
+
+    if !format {
+        return Ok(Token::String(text));
+    }
+    if !text.is_empty() {
+        chunks.push(FStringChunk::Literal(text));
+    }
+    Ok(Token::FString(chunks))
+}
+
+// lexes an f-string replacement field after its '{', up to and including the '}'
+fn field(chars: &mut Cursor, triple: bool) -> Result<FStringChunk, RutenError> {
+    while matches!(chars.peek(), Some(' ' | '\t')) {
+        chars.next();
+    }
+    let origin = chars.here();
+    let error = |message: &str, chars: &Cursor| RutenError::syntax_error(message).with_span(chars.since(origin));
+
+    // the expression ends at a ':' or '}' outside any brackets or nested strings
+    let mut source = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    loop {
+        let ch = match chars.peek() {
+            Some('\n') if !triple => None,
+            ch => ch,
+        };
+        let ch = match ch {
+            Some(ch) => ch,
+            None => return Err(error("f-string: expecting '}'", chars)),
+        };
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') if depth > 0 => depth -= 1,
+            (None, '}' | ':') if depth == 0 => break,
+            _ => {}
+        }
+        source.push(ch);
+        chars.next();
+    }
+    if source.trim().is_empty() {
+        return Err(error("f-string: empty expression not allowed", chars));
+    }
+
+    let mut spec = None;
+    if chars.peek() == Some(':') {
+        chars.next();
+        let mut text = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some('{') => {
+                    return Err(error("f-string: nested fields in a format spec are not supported", chars))
+                }
+                Some('\n') if !triple => return Err(error("f-string: expecting '}'", chars)),
+                Some(ch) => text.push(ch),
+                None => return Err(error("f-string: expecting '}'", chars)),
+            }
+        }
+        spec = Some(text);
+    } else {
+        chars.next();
+    }
+
+    // the field is lexed on its own, then moved to where it sits in the file
+    let shift = |span: Span| {
+        let column = if span.line == 1 { origin.column + span.column - 1 } else { span.column };
+        Span::new(origin.start + span.start, origin.start + span.end, origin.line + span.line - 1, column)
+    };
+    let tokens = tokenize(&source).map_err(|mut err| {
+        err.span = err.span.map(shift);
+        err
+    })?;
+    let tokens = tokens
+        .into_iter()
+        .map(|token| SpannedToken {
+            span: shift(token.span),
+            ..token
+        })
+        .collect();
+    Ok(FStringChunk::Field(tokens, spec))
+}
@@ -0,0 +1,148 @@
+// finds `.rtn` files imported as modules and remembers the ones already evaluated
+
+use crate::error::{RutenError, Span, TraceFrame};
+use crate::interpreter::{Module, Value};
+use crate::modules;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// shared by a script and every module it imports, directly or not
+#[derive(Default)]
+pub struct ModuleLoader {
+    loaded: HashMap<PathBuf, Value>,
+    // modules being evaluated right now, outermost first
+    loading: Vec<(String, PathBuf)>,
+}
+
+impl ModuleLoader {
+    /// a loader for the script `filename`, which counts as being imported,
+    /// so a module importing it back is a cycle
+    pub fn for_script(filename: &str) -> Self {
+        let mut loader = ModuleLoader::default();
+        let path = Path::new(filename);
+        if let Some(name) = path.file_stem() {
+            let _ = loader.begin(&name.to_string_lossy(), path);
+        }
+        loader
+    }
+
+    /// looks for `name.rtn` next to `importer`, then in each directory of `RUTEN_PATH`
+    pub fn find(name: &str, importer: &str) -> Option<PathBuf> {
+        let file = format!("{}.rtn", name);
+
+        // the repl imports from the working directory
+        let mut dirs = vec![Path::new(importer)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()];
+        if let Some(paths) = env::var_os("RUTEN_PATH") {
+            dirs.extend(env::split_paths(&paths));
+        }
+
+        dirs.into_iter()
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Value> {
+        self.loaded.get(&key(path)).cloned()
+    }
+
+    /// marks `path` as being evaluated; importing it again before it finishes is a cycle
+    pub fn begin(&mut self, name: &str, path: &Path) -> Result<(), RutenError> {
+        let path = key(path);
+        if let Some(start) = self.loading.iter().position(|(_, p)| *p == path) {
+            let chain: Vec<&str> = self.loading[start..]
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .chain([name])
+                .collect();
+            return Err(RutenError::import_error(format!(
+                "circular import: {}",
+                chain.join(" -> ")
+            )));
+        }
+        self.loading.push((name.to_string(), path));
+        Ok(())
+    }
+
+    /// ends the evaluation started by `begin`, caching the module if it succeeded
+    pub fn finish(&mut self, path: &Path, module: Option<Value>) {
+        let path = key(path);
+        self.loading.retain(|(_, p)| *p != path);
+        if let Some(module) = module {
+            self.loaded.insert(path, module);
+        }
+    }
+}
+
+/// resolves `import name` in the script `importer`: built-in modules win over files,
+/// which `eval` runs once, given their path and source, to produce their top-level bindings
+pub fn import<F>(
+    loader: &Rc<RefCell<ModuleLoader>>,
+    name: &str,
+    importer: &str,
+    span: Span,
+    eval: F,
+) -> Result<Value, RutenError>
+where
+    F: FnOnce(&str, &str) -> Result<Module, RutenError>,
+{
+    if let Some(module) = modules::load_module(name) {
+        return Ok(module);
+    }
+
+    let path = ModuleLoader::find(name, importer)
+        .ok_or_else(|| RutenError::import_error(format!("no module named '{}'", name)))?;
+    if let Some(module) = loader.borrow().get(&path) {
+        return Ok(module);
+    }
+
+    loader.borrow_mut().begin(name, &path)?;
+    let result = eval_module(&path, eval);
+    loader
+        .borrow_mut()
+        .finish(&path, result.as_ref().ok().cloned());
+
+    // show where the failing import happened below the module's own frames
+    result.map_err(|mut err| {
+        err.traceback.push(TraceFrame {
+            function: "<module>".to_string(),
+            file: importer.to_string(),
+            span: Some(span),
+        });
+        err
+    })
+}
+
+fn eval_module<F>(path: &Path, eval: F) -> Result<Value, RutenError>
+where
+    F: FnOnce(&str, &str) -> Result<Module, RutenError>,
+{
+    let file = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|e| RutenError::import_error(format!("cannot read module '{}': {}", file, e)))?;
+
+    match eval(&file, &source) {
+        Ok(exports) => Ok(Value::Module(exports)),
+        Err(mut err) => {
+            if err.traceback.is_empty() {
+                err.traceback.push(TraceFrame {
+                    function: "<module>".to_string(),
+                    file: file.clone(),
+                    span: err.span,
+                });
+            }
+            Err(err.with_file(&file))
+        }
+    }
+}
+
+// one entry per file, however it was reached
+fn key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,6 +1,17 @@
 mod lexer;
+mod int;
+mod dict;
 mod parser;
 mod interpreter;
+mod builtins;
+mod ops;
+mod format_spec;
+mod compiler;
+mod vm;
+mod optimizer;
+mod printer;
+mod loader;
+mod methods;
 mod modules;
 mod repl;
 mod error;
@@ -8,24 +19,53 @@ mod error;
 use std::env;
 use std::fs;
 use std::process;
+use std::thread;
+
+// scripts recursing up to the call depth limit recurse just as deep in the tree-walker,
+// which needs more than the main thread's stack to get there
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// how to run a script, from the command line flags
+#[derive(Default)]
+struct Options {
+    use_vm: bool,
+    dump_ast: bool,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(start)
+        .expect("failed to start the interpreter thread");
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn start() {
+    let mut options = Options::default();
+    let mut files = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--vm" => options.use_vm = true,
+            "--dump-ast" => options.dump_ast = true,
+            _ => files.push(arg),
+        }
+    }
 
-    if args.len() == 1 {
+    match files.as_slice() {
         // start interactive repl
-        repl::start();
-    } else if args.len() == 2 {
+        [] if !options.use_vm && !options.dump_ast => repl::start(),
         // run script file
-        let filename = &args[1];
-        run_file(filename);
-    } else {
-        eprintln!("usage: ruten [script.rtn]");
-        process::exit(1);
+        [filename] => run_file(filename, &options),
+        _ => {
+            eprintln!("usage: ruten [--vm] [--dump-ast] [script.rtn]");
+            process::exit(1);
+        }
     }
 }
 
-fn run_file(filename: &str) {
+fn run_file(filename: &str, options: &Options) {
     let source = match fs::read_to_string(filename) {
         Ok(content) => content,
         Err(err) => {
@@ -34,16 +74,25 @@ fn run_file(filename: &str) {
         }
     };
 
-    if let Err(err) = run(&source) {
-        eprintln!("{}", err);
+    if let Err(err) = run(filename, &source, options) {
+        eprintln!("{}", err.render(filename, &source));
         process::exit(1);
     }
 }
 
-fn run(source: &str) -> Result<(), error::RutenError> {
+fn run(filename: &str, source: &str, options: &Options) -> Result<(), error::RutenError> {
     let tokens = lexer::tokenize(source)?;
-    let ast = parser::parse(tokens)?;
-    let mut interpreter = interpreter::Interpreter::new();
-    interpreter.eval_program(ast)?;
+    let ast = optimizer::optimize(parser::parse(tokens)?);
+    if options.dump_ast {
+        // show the program as it would run, after optimization
+        print!("{}", printer::print_program(&ast));
+    } else if options.use_vm {
+        // the bytecode backend; the tree-walker below is the reference it is tested against
+        let mut vm = vm::Vm::with_filename(filename);
+        vm.run_program(&ast)?;
+    } else {
+        let mut interpreter = interpreter::Interpreter::with_filename(filename);
+        interpreter.eval_program(ast)?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
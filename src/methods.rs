@@ -0,0 +1,340 @@
+// built-in methods on strings, lists and dicts, e.g. `"abc".upper()` or `xs.append(x)`
+
+use crate::dict::{key_set, Key};
+use crate::error::RutenError;
+use crate::int::Int;
+use crate::interpreter::{iterate, resolve_index, type_name, Value};
+use crate::ops;
+use std::cmp::Ordering;
+
+const STRING_METHODS: &[&str] = &[
+    "upper", "lower", "strip", "lstrip", "rstrip", "split", "replace", "find", "startswith",
+    "endswith", "join",
+];
+const LIST_METHODS: &[&str] = &["append", "extend", "pop", "insert", "sort", "reverse"];
+const DICT_METHODS: &[&str] = &["keys", "values", "items", "get", "update"];
+const SET_METHODS: &[&str] = &["add", "remove", "issubset", "issuperset"];
+
+/// whether `receiver` has a built-in method called `name`
+pub fn has_method(receiver: &Value, name: &str) -> bool {
+    let methods = match receiver {
+        Value::String(_) => STRING_METHODS,
+        Value::List(_) => LIST_METHODS,
+        Value::Dict(_) => DICT_METHODS,
+        Value::Set(_) => SET_METHODS,
+        _ => return false,
+    };
+    methods.contains(&name)
+}
+
+pub fn call_method(receiver: &Value, name: &str, args: &[Value]) -> Result<Value, RutenError> {
+    match receiver {
+        Value::String(s) => string_method(s, name, args),
+        Value::List(_) => list_method(receiver, name, args),
+        Value::Dict(_) => dict_method(receiver, name, args),
+        Value::Set(_) => set_method(receiver, name, args),
+        _ => Err(RutenError::type_error(format!("{} has no method {}()", type_name(receiver), name))),
+    }
+}
+
+fn expect_args(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), RutenError> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            format!("{}", min)
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(RutenError::runtime_error(format!(
+            "{}() takes {} arguments, got {}",
+            name,
+            expected,
+            args.len()
+        )));
+    }
+    Ok(())
+}
+
+fn string_arg<'a>(name: &str, value: &'a Value) -> Result<&'a str, RutenError> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(RutenError::type_error(format!(
+            "{}() expects a string, got {}",
+            name, other
+        ))),
+    }
+}
+
+fn string_method(s: &str, name: &str, args: &[Value]) -> Result<Value, RutenError> {
+    match name {
+        "upper" => {
+            expect_args(name, args, 0, 0)?;
+            Ok(Value::String(s.to_uppercase()))
+        }
+        "lower" => {
+            expect_args(name, args, 0, 0)?;
+            Ok(Value::String(s.to_lowercase()))
+        }
+        "strip" => {
+            expect_args(name, args, 0, 0)?;
+            Ok(Value::String(s.trim().to_string()))
+        }
+        "lstrip" => {
+            expect_args(name, args, 0, 0)?;
+            Ok(Value::String(s.trim_start().to_string()))
+        }
+        "rstrip" => {
+            expect_args(name, args, 0, 0)?;
+            Ok(Value::String(s.trim_end().to_string()))
+        }
+        "split" => {
+            expect_args(name, args, 0, 1)?;
+            // no separator splits on runs of whitespace
+            let parts: Vec<Value> = match args.first() {
+                None | Some(Value::None) => s
+                    .split_whitespace()
+                    .map(|p| Value::String(p.to_string()))
+                    .collect(),
+                Some(sep) => {
+                    let sep = string_arg(name, sep)?;
+                    if sep.is_empty() {
+                        return Err(RutenError::runtime_error("split() separator is empty".to_string()));
+                    }
+                    s.split(sep).map(|p| Value::String(p.to_string())).collect()
+                }
+            };
+            Ok(Value::list(parts))
+        }
+        "replace" => {
+            expect_args(name, args, 2, 2)?;
+            let old = string_arg(name, &args[0])?;
+            let new = string_arg(name, &args[1])?;
+            Ok(Value::String(s.replace(old, new)))
+        }
+        "find" => {
+            expect_args(name, args, 1, 1)?;
+            let needle = string_arg(name, &args[0])?;
+            // report a character index, matching string indexing
+            let index = match s.find(needle) {
+                Some(byte) => Int::from(s[..byte].chars().count()),
+                None => Int::Small(-1),
+            };
+            Ok(Value::Int(index))
+        }
+        "startswith" => {
+            expect_args(name, args, 1, 1)?;
+            Ok(Value::Bool(s.starts_with(string_arg(name, &args[0])?)))
+        }
+        "endswith" => {
+            expect_args(name, args, 1, 1)?;
+            Ok(Value::Bool(s.ends_with(string_arg(name, &args[0])?)))
+        }
+        "join" => {
+            expect_args(name, args, 1, 1)?;
+            match &args[0] {
+                Value::List(items) => {
+                    let items = items.borrow();
+                    let parts: Result<Vec<&str>, RutenError> =
+                        items.iter().map(|item| string_arg(name, item)).collect();
+                    Ok(Value::String(parts?.join(s)))
+                }
+                other => Err(RutenError::type_error(format!("join() expects a list, got {}", other))),
+            }
+        }
+        _ => Err(RutenError::name_error(format!("string has no method {}()", name))),
+    }
+}
+
+fn list_method(receiver: &Value, name: &str, args: &[Value]) -> Result<Value, RutenError> {
+    let list = match receiver {
+        Value::List(list) => list,
+        _ => unreachable!(),
+    };
+
+    match name {
+        "append" => {
+            expect_args(name, args, 1, 1)?;
+            list.borrow_mut().push(args[0].clone());
+            Ok(Value::None)
+        }
+        "extend" => {
+            expect_args(name, args, 1, 1)?;
+            let extra = match &args[0] {
+                Value::List(other) => other.borrow().clone(),
+                other => return Err(RutenError::type_error(format!("extend() expects a list, got {}", other))),
+            };
+            list.borrow_mut().extend(extra);
+            Ok(Value::None)
+        }
+        "pop" => {
+            expect_args(name, args, 0, 1)?;
+            let mut list = list.borrow_mut();
+            if list.is_empty() {
+                return Err(RutenError::runtime_error("pop from empty list".to_string()));
+            }
+            let index = match args.first() {
+                None => list.len() - 1,
+                Some(Value::Int(n)) => resolve_index(n, list.len())
+                    .ok_or_else(|| RutenError::runtime_error("pop index out of range".to_string()))?,
+                Some(other) => return Err(RutenError::type_error(format!("pop() index must be an integer, got {}", other))),
+            };
+            Ok(list.remove(index))
+        }
+        "insert" => {
+            expect_args(name, args, 2, 2)?;
+            let mut list = list.borrow_mut();
+            let index = match &args[0] {
+                // like python, out-of-range positions clamp to either end
+                Value::Int(n) => {
+                    let len = Int::from(list.len());
+                    let n = if n.is_negative() { n.add(&len).max(Int::Small(0)) } else { n.clone().min(len) };
+                    n.to_i64().unwrap_or_default() as usize
+                }
+                other => return Err(RutenError::type_error(format!("insert() index must be an integer, got {}", other))),
+            };
+            list.insert(index, args[1].clone());
+            Ok(Value::None)
+        }
+        "sort" => {
+            expect_args(name, args, 0, 0)?;
+            let mut items = list.borrow().clone();
+            sort_values(&mut items)?;
+            *list.borrow_mut() = items;
+            Ok(Value::None)
+        }
+        "reverse" => {
+            expect_args(name, args, 0, 0)?;
+            list.borrow_mut().reverse();
+            Ok(Value::None)
+        }
+        _ => Err(RutenError::name_error(format!("list has no method {}()", name))),
+    }
+}
+
+fn dict_method(receiver: &Value, name: &str, args: &[Value]) -> Result<Value, RutenError> {
+    let dict = match receiver {
+        Value::Dict(dict) => dict,
+        _ => unreachable!(),
+    };
+
+    match name {
+        "keys" => {
+            expect_args(name, args, 0, 0)?;
+            let keys = dict.borrow().keys().map(|k| k.value().clone()).collect();
+            Ok(Value::list(keys))
+        }
+        "values" => {
+            expect_args(name, args, 0, 0)?;
+            let values = dict.borrow().values().cloned().collect();
+            Ok(Value::list(values))
+        }
+        "items" => {
+            expect_args(name, args, 0, 0)?;
+            let items = dict
+                .borrow()
+                .iter()
+                .map(|(k, v)| Value::tuple(vec![k.value().clone(), v.clone()]))
+                .collect();
+            Ok(Value::list(items))
+        }
+        "get" => {
+            expect_args(name, args, 1, 2)?;
+            let key = Key::new(args[0].clone())?;
+            let found = dict.borrow().get(&key).cloned();
+            Ok(found.unwrap_or_else(|| args.get(1).cloned().unwrap_or(Value::None)))
+        }
+        "update" => {
+            expect_args(name, args, 1, 1)?;
+            let entries = match &args[0] {
+                Value::Dict(other) => other.borrow().clone(),
+                other => return Err(RutenError::type_error(format!("update() expects a dict, got {}", other))),
+            };
+            dict.borrow_mut().extend(entries);
+            Ok(Value::None)
+        }
+        _ => Err(RutenError::name_error(format!("dict has no method {}()", name))),
+    }
+}
+
+fn set_method(receiver: &Value, name: &str, args: &[Value]) -> Result<Value, RutenError> {
+    let set = match receiver {
+        Value::Set(set) => set,
+        _ => unreachable!(),
+    };
+
+    match name {
+        "add" => {
+            expect_args(name, args, 1, 1)?;
+            set.borrow_mut().insert(Key::new(args[0].clone())?);
+            Ok(Value::None)
+        }
+        "remove" => {
+            expect_args(name, args, 1, 1)?;
+            if !set.borrow_mut().shift_remove(&Key::new(args[0].clone())?) {
+                return Err(RutenError::runtime_error(format!("{} is not in the set", args[0])));
+            }
+            Ok(Value::None)
+        }
+        "issubset" | "issuperset" => {
+            expect_args(name, args, 1, 1)?;
+            let items = iterate(&args[0]).ok_or_else(|| {
+                RutenError::type_error(format!("{}() expects an iterable, got {}", name, type_name(&args[0])))
+            })?;
+            let other = key_set(items)?;
+            let set = set.borrow();
+            Ok(Value::Bool(if name == "issubset" { set.is_subset(&other) } else { set.is_superset(&other) }))
+        }
+        _ => Err(RutenError::name_error(format!("set has no method {}()", name))),
+    }
+}
+
+/// sorts numbers or strings in ascending order; mixed kinds are an error
+pub fn sort_values(items: &mut [Value]) -> Result<(), RutenError> {
+    sort_fallibly(items, compare_values)
+}
+
+/// sorts `(key, value)` pairs by key, keeping equal keys in their original order
+pub fn sort_keyed(pairs: &mut [(Value, Value)]) -> Result<(), RutenError> {
+    sort_fallibly(pairs, |a, b| compare_values(&a.0, &b.0))
+}
+
+// a stable sort that reports the first comparison that failed
+fn sort_fallibly<T>(
+    items: &mut [T],
+    compare: impl Fn(&T, &T) -> Result<Ordering, RutenError>,
+) -> Result<(), RutenError> {
+    let mut failure = None;
+    items.sort_by(|a, b| match compare(a, b) {
+        Ok(ordering) => ordering,
+        Err(err) => {
+            failure.get_or_insert(err);
+            Ordering::Equal
+        }
+    });
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// the order `sorted` puts `a` and `b` in; NaN sorts as equal to everything
+pub fn compare_values(a: &Value, b: &Value) -> Result<Ordering, RutenError> {
+    Ok(partial_compare(a, b)?.unwrap_or(Ordering::Equal))
+}
+
+/// how `a` and `b` are ordered, which `<` and friends test; None when NaN is involved
+pub fn partial_compare(a: &Value, b: &Value) -> Result<Option<Ordering>, RutenError> {
+    match (a, b) {
+        (Value::Number(_) | Value::Int(_), Value::Number(_) | Value::Int(_)) => Ok(ops::compare_numbers(a, b)),
+        (Value::String(x), Value::String(y)) => Ok(Some(x.cmp(y))),
+        (Value::Bool(x), Value::Bool(y)) => Ok(Some(x.cmp(y))),
+        // by the first items that differ, with a shorter tuple first when it is a prefix
+        // of the other
+        (Value::Tuple(x), Value::Tuple(y)) => {
+            match x.iter().zip(y.iter()).find(|(x, y)| !ops::values_equal(x, y)) {
+                Some((x, y)) => partial_compare(x, y),
+                None => Ok(Some(x.len().cmp(&y.len()))),
+            }
+        }
+        _ => Err(RutenError::type_error(format!("cannot compare {} with {}", a, b))),
+    }
+}
@@ -9,32 +9,32 @@ pub fn create_module() -> HashMap<String, Value> {
     // these would require api keys and async runtime
     module.insert(
         "chat".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("chat() takes at least 2 arguments: model, prompt".to_string()));
+                return Err(RutenError::runtime_error("chat() takes at least 2 arguments: model, prompt".to_string()));
             }
             
             match (&args[0], &args[1]) {
                 (Value::String(model), Value::String(prompt)) => {
                     // placeholder implementation
                     // in production, this would call openai/claude/ollama apis
-                    Err(RutenError::RuntimeError(
+                    Err(RutenError::runtime_error(
                         format!("ai.chat() not yet implemented. would call {} with prompt: {}", model, prompt)
                     ))
                 }
-                _ => Err(RutenError::TypeError("chat() requires model and prompt strings".to_string())),
+                _ => Err(RutenError::type_error("chat() requires model and prompt strings".to_string())),
             }
         }),
     );
 
     module.insert(
         "complete".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("complete() takes at least 2 arguments".to_string()));
+                return Err(RutenError::runtime_error("complete() takes at least 2 arguments".to_string()));
             }
             
-            Err(RutenError::RuntimeError("ai.complete() not yet implemented".to_string()))
+            Err(RutenError::runtime_error("ai.complete() not yet implemented".to_string()))
         }),
     );
 
@@ -10,9 +10,9 @@ pub fn create_module() -> HashMap<String, Value> {
 
     module.insert(
         "color".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 2 {
-                return Err(RutenError::RuntimeError("color() takes 2 arguments: text, color".to_string()));
+                return Err(RutenError::runtime_error("color() takes 2 arguments: text, color".to_string()));
             }
             
             match (&args[0], &args[1]) {
@@ -30,35 +30,35 @@ pub fn create_module() -> HashMap<String, Value> {
                     };
                     Ok(Value::String(colored_text))
                 }
-                _ => Err(RutenError::TypeError("color() requires two strings".to_string())),
+                _ => Err(RutenError::type_error("color() requires two strings".to_string())),
             }
         }),
     );
 
     module.insert(
         "bold".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("bold() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("bold() takes 1 argument".to_string()));
             }
             
             match &args[0] {
                 Value::String(text) => Ok(Value::String(text.bold().to_string())),
-                _ => Err(RutenError::TypeError("bold() requires a string".to_string())),
+                _ => Err(RutenError::type_error("bold() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "progress".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("progress() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("progress() takes 1 argument".to_string()));
             }
             
-            match &args[0] {
-                Value::Number(total) => {
-                    let pb = ProgressBar::new(*total as u64);
+            match args[0].as_f64() {
+                Some(total) => {
+                    let pb = ProgressBar::new(total as u64);
                     pb.set_style(
                         ProgressStyle::default_bar()
                             .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
@@ -67,7 +67,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     );
                     
                     // simulate progress
-                    for _ in 0..(*total as u64) {
+                    for _ in 0..(total as u64) {
                         pb.inc(1);
                         std::thread::sleep(Duration::from_millis(50));
                     }
@@ -75,22 +75,22 @@ pub fn create_module() -> HashMap<String, Value> {
                     
                     Ok(Value::None)
                 }
-                _ => Err(RutenError::TypeError("progress() requires a number".to_string())),
+                None => Err(RutenError::type_error("progress() requires a number".to_string())),
             }
         }),
     );
 
     module.insert(
         "clear".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("clear() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("clear() takes no arguments".to_string()));
             }
             
             // clear terminal
             if cfg!(target_os = "windows") {
                 std::process::Command::new("cmd")
-                    .args(&["/C", "cls"])
+                    .args(["/C", "cls"])
                     .status()
                     .ok();
             } else {
@@ -7,7 +7,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get bitcoin price
     module.insert(
         "btc_price".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://api.coinbase.com/v2/prices/BTC-USD/spot";
             let client = reqwest::blocking::Client::new();
             
@@ -16,7 +16,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("blockchain api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("blockchain api error: {}", e))),
             }
         }),
     );
@@ -24,7 +24,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get ethereum price
     module.insert(
         "eth_price".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://api.coinbase.com/v2/prices/ETH-USD/spot";
             let client = reqwest::blocking::Client::new();
             
@@ -33,7 +33,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("blockchain api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("blockchain api error: {}", e))),
             }
         }),
     );
@@ -41,16 +41,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // get any crypto price
     module.insert(
         "price".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "blockchain.price() requires symbol".to_string(),
                 ));
             }
 
             let symbol = match &args[0] {
                 Value::String(s) => s.to_uppercase(),
-                _ => return Err(RutenError::TypeError("symbol must be a string".to_string())),
+                _ => return Err(RutenError::type_error("symbol must be a string".to_string())),
             };
 
             let url = format!("https://api.coinbase.com/v2/prices/{}-USD/spot", symbol);
@@ -61,7 +61,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("blockchain api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("blockchain api error: {}", e))),
             }
         }),
     );
@@ -1,4 +1,5 @@
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 use sha2::{Sha256, Sha512, Digest};
@@ -11,9 +12,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.sha512(data) - sha512 hash
     module.insert(
         "sha512".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sha512() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sha512() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::String(s) => {
@@ -22,7 +23,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let result = hasher.finalize();
                     Ok(Value::String(format!("{:x}", result)))
                 }
-                _ => Err(RutenError::TypeError("sha512() requires a string".to_string())),
+                _ => Err(RutenError::type_error("sha512() requires a string".to_string())),
             }
         }),
     );
@@ -30,9 +31,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.sha256(data) - sha256 hash
     module.insert(
         "sha256".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sha256() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sha256() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::String(s) => {
@@ -41,7 +42,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let result = hasher.finalize();
                     Ok(Value::String(format!("{:x}", result)))
                 }
-                _ => Err(RutenError::TypeError("sha256() requires a string".to_string())),
+                _ => Err(RutenError::type_error("sha256() requires a string".to_string())),
             }
         }),
     );
@@ -49,16 +50,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.md5(data) - md5 hash
     module.insert(
         "md5".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("md5() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("md5() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::String(s) => {
                     let digest = md5::compute(s.as_bytes());
                     Ok(Value::String(format!("{:x}", digest)))
                 }
-                _ => Err(RutenError::TypeError("md5() requires a string".to_string())),
+                _ => Err(RutenError::type_error("md5() requires a string".to_string())),
             }
         }),
     );
@@ -66,18 +67,18 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.random_bytes(length) - secure random byte generation
     module.insert(
         "random_bytes".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("random_bytes() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("random_bytes() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => {
-                    let length = *n as usize;
+            match args[0].as_f64() {
+                Some(n) => {
+                    let length = n as usize;
                     let mut rng = rand::thread_rng();
                     let bytes: Vec<u8> = (0..length).map(|_| rng.gen::<u8>()).collect();
                     Ok(Value::String(hex::encode(bytes)))
                 }
-                _ => Err(RutenError::TypeError("random_bytes() requires a number".to_string())),
+                None => Err(RutenError::type_error("random_bytes() requires a number".to_string())),
             }
         }),
     );
@@ -85,9 +86,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.random() - random float between 0 and 1
     module.insert(
         "random".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("random() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("random() takes no arguments".to_string()));
             }
             let mut rng = rand::thread_rng();
             Ok(Value::Number(rng.gen::<f64>()))
@@ -97,17 +98,20 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.randint(min, max) - random integer in range
     module.insert(
         "randint".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 2 {
-                return Err(RutenError::RuntimeError("randint() takes 2 arguments".to_string()));
+                return Err(RutenError::runtime_error("randint() takes 2 arguments".to_string()));
             }
             match (&args[0], &args[1]) {
-                (Value::Number(min), Value::Number(max)) => {
+                (Value::Int(min), Value::Int(max)) => {
+                    let (min, max) = match (min.to_i64(), max.to_i64()) {
+                        (Some(min), Some(max)) if min <= max => (min, max),
+                        _ => return Err(RutenError::runtime_error("randint() requires min <= max".to_string())),
+                    };
                     let mut rng = rand::thread_rng();
-                    let result = rng.gen_range((*min as i64)..=(*max as i64));
-                    Ok(Value::Number(result as f64))
+                    Ok(Value::Int(Int::from(rng.gen_range(min..=max))))
                 }
-                _ => Err(RutenError::TypeError("randint() requires two numbers".to_string())),
+                _ => Err(RutenError::type_error("randint() requires two integers".to_string())),
             }
         }),
     );
@@ -115,18 +119,18 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.random_hex(length) - random hex string
     module.insert(
         "random_hex".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("random_hex() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("random_hex() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => {
-                    let length = *n as usize;
+            match args[0].as_f64() {
+                Some(n) => {
+                    let length = n as usize;
                     let mut rng = rand::thread_rng();
                     let bytes: Vec<u8> = (0..length).map(|_| rng.gen::<u8>()).collect();
                     Ok(Value::String(hex::encode(bytes)))
                 }
-                _ => Err(RutenError::TypeError("random_hex() requires a number".to_string())),
+                None => Err(RutenError::type_error("random_hex() requires a number".to_string())),
             }
         }),
     );
@@ -134,20 +138,21 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.choice(list) - random element from list
     module.insert(
         "choice".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("choice() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("choice() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("choice() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("choice() requires non-empty list".to_string()));
                     }
                     let mut rng = rand::thread_rng();
                     let index = rng.gen_range(0..items.len());
                     Ok(items[index].clone())
                 }
-                _ => Err(RutenError::TypeError("choice() requires a list".to_string())),
+                _ => Err(RutenError::type_error("choice() requires a list".to_string())),
             }
         }),
     );
@@ -155,9 +160,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // crypto.uuid() - generate uuid v4
     module.insert(
         "uuid".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("uuid() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("uuid() takes no arguments".to_string()));
             }
             let mut rng = rand::thread_rng();
             let uuid = format!(
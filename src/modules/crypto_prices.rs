@@ -8,9 +8,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // get current price for a cryptocurrency
     module.insert(
         "price".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("price() takes 1-2 arguments: symbol, [currency]".to_string()));
+                return Err(RutenError::runtime_error("price() takes 1-2 arguments: symbol, [currency]".to_string()));
             }
             
             match &args[0] {
@@ -28,14 +28,14 @@ pub fn create_module() -> HashMap<String, Value> {
                                     symbol.to_uppercase(), currency);
                     
                     let response = blocking::get(&url)
-                        .map_err(|e| RutenError::RuntimeError(format!("crypto api error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("crypto api error: {}", e)))?;
                     
                     let text = response.text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
                     
                     Ok(Value::String(text))
                 }
-                _ => Err(RutenError::TypeError("price() requires a string symbol".to_string())),
+                _ => Err(RutenError::type_error("price() requires a string symbol".to_string())),
             }
         }),
     );
@@ -43,16 +43,17 @@ pub fn create_module() -> HashMap<String, Value> {
     // get multiple crypto prices at once
     module.insert(
         "prices".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("prices() takes 1 argument: list of symbols".to_string()));
+                return Err(RutenError::runtime_error("prices() takes 1 argument: list of symbols".to_string()));
             }
             
             match &args[0] {
                 Value::List(symbols) => {
+                    let symbols = symbols.borrow();
                     let mut results = Vec::new();
                     
-                    for symbol in symbols {
+                    for symbol in symbols.iter() {
                         if let Value::String(sym) = symbol {
                             let url = format!("https://api.coinbase.com/v2/prices/{}-USD/spot", 
                                             sym.to_uppercase());
@@ -68,9 +69,9 @@ pub fn create_module() -> HashMap<String, Value> {
                         }
                     }
                     
-                    Ok(Value::List(results))
+                    Ok(Value::list(results))
                 }
-                _ => Err(RutenError::TypeError("prices() requires a list of symbols".to_string())),
+                _ => Err(RutenError::type_error("prices() requires a list of symbols".to_string())),
             }
         }),
     );
@@ -78,9 +79,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // get exchange rate between two currencies
     module.insert(
         "exchange".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("exchange() takes 2 arguments: from, to".to_string()));
+                return Err(RutenError::runtime_error("exchange() takes 2 arguments: from, to".to_string()));
             }
             
             match (&args[0], &args[1]) {
@@ -89,14 +90,14 @@ pub fn create_module() -> HashMap<String, Value> {
                                     from.to_uppercase());
                     
                     let response = blocking::get(&url)
-                        .map_err(|e| RutenError::RuntimeError(format!("exchange api error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("exchange api error: {}", e)))?;
                     
                     let text = response.text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
                     
                     Ok(Value::String(text))
                 }
-                _ => Err(RutenError::TypeError("exchange() requires two string currencies".to_string())),
+                _ => Err(RutenError::type_error("exchange() requires two string currencies".to_string())),
             }
         }),
     );
@@ -15,16 +15,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // set key-value pair
     module.insert(
         "set".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "database.set() requires key and value".to_string(),
                 ));
             }
 
             let key = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("key must be a string".to_string())),
+                _ => return Err(RutenError::type_error("key must be a string".to_string())),
             };
 
             let value = args[1].to_string();
@@ -39,16 +39,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // get value by key
     module.insert(
         "get".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "database.get() requires key".to_string(),
                 ));
             }
 
             let key = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("key must be a string".to_string())),
+                _ => return Err(RutenError::type_error("key must be a string".to_string())),
             };
 
             let store = DB_STORE.lock().unwrap();
@@ -62,16 +62,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // delete key
     module.insert(
         "delete".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "database.delete() requires key".to_string(),
                 ));
             }
 
             let key = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("key must be a string".to_string())),
+                _ => return Err(RutenError::type_error("key must be a string".to_string())),
             };
 
             let mut store = DB_STORE.lock().unwrap();
@@ -84,16 +84,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // check if key exists
     module.insert(
         "exists".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "database.exists() requires key".to_string(),
                 ));
             }
 
             let key = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("key must be a string".to_string())),
+                _ => return Err(RutenError::type_error("key must be a string".to_string())),
             };
 
             let store = DB_STORE.lock().unwrap();
@@ -104,20 +104,20 @@ pub fn create_module() -> HashMap<String, Value> {
     // get all keys
     module.insert(
         "keys".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let store = DB_STORE.lock().unwrap();
             let keys: Vec<Value> = store
                 .keys()
                 .map(|k| Value::String(k.clone()))
                 .collect();
-            Ok(Value::List(keys))
+            Ok(Value::list(keys))
         }),
     );
 
     // clear all data
     module.insert(
         "clear".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let mut store = DB_STORE.lock().unwrap();
             store.clear();
             Ok(Value::Bool(true))
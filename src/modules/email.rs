@@ -8,26 +8,26 @@ pub fn create_module() -> HashMap<String, Value> {
     // send email (simplified - would need proper smtp in production)
     module.insert(
         "send".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 3 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "email.send() requires to, subject, body".to_string(),
                 ));
             }
 
             let to = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("to must be a string".to_string())),
+                _ => return Err(RutenError::type_error("to must be a string".to_string())),
             };
 
             let subject = match &args[1] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("subject must be a string".to_string())),
+                _ => return Err(RutenError::type_error("subject must be a string".to_string())),
             };
 
             let body = match &args[2] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("body must be a string".to_string())),
+                _ => return Err(RutenError::type_error("body must be a string".to_string())),
             };
 
             // in production, this would use lettre or similar
@@ -42,16 +42,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // validate email format
     module.insert(
         "validate".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "email.validate() requires email address".to_string(),
                 ));
             }
 
             let email = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("email must be a string".to_string())),
+                _ => return Err(RutenError::type_error("email must be a string".to_string())),
             };
 
             // basic email validation
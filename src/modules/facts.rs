@@ -8,7 +8,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get random fact
     module.insert(
         "random".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://uselessfacts.jsph.pl/random.json?language=en";
             let client = reqwest::blocking::Client::new();
             
@@ -17,7 +17,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("facts api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("facts api error: {}", e))),
             }
         }),
     );
@@ -25,7 +25,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get fact of the day
     module.insert(
         "today".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://uselessfacts.jsph.pl/today.json?language=en";
             let client = reqwest::blocking::Client::new();
             
@@ -34,7 +34,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("facts api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("facts api error: {}", e))),
             }
         }),
     );
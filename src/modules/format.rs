@@ -7,30 +7,30 @@ pub fn create_module() -> HashMap<String, Value> {
 
     module.insert(
         "indent".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 2 {
-                return Err(RutenError::RuntimeError("indent() takes 2 arguments: text, spaces".to_string()));
+                return Err(RutenError::runtime_error("indent() takes 2 arguments: text, spaces".to_string()));
             }
             
-            match (&args[0], &args[1]) {
-                (Value::String(text), Value::Number(spaces)) => {
-                    let indent = " ".repeat(*spaces as usize);
+            match (&args[0], args[1].as_f64()) {
+                (Value::String(text), Some(spaces)) => {
+                    let indent = " ".repeat(spaces as usize);
                     let indented: Vec<String> = text
                         .lines()
                         .map(|line| format!("{}{}", indent, line))
                         .collect();
                     Ok(Value::String(indented.join("\n")))
                 }
-                _ => Err(RutenError::TypeError("indent() requires string and number".to_string())),
+                _ => Err(RutenError::type_error("indent() requires string and number".to_string())),
             }
         }),
     );
 
     module.insert(
         "dedent".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("dedent() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("dedent() takes 1 argument".to_string()));
             }
             
             match &args[0] {
@@ -61,16 +61,16 @@ pub fn create_module() -> HashMap<String, Value> {
                     
                     Ok(Value::String(dedented.join("\n")))
                 }
-                _ => Err(RutenError::TypeError("dedent() requires a string".to_string())),
+                _ => Err(RutenError::type_error("dedent() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "strip_comments".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("strip_comments() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("strip_comments() takes 1 argument".to_string()));
             }
             
             match &args[0] {
@@ -85,16 +85,16 @@ pub fn create_module() -> HashMap<String, Value> {
                         .collect();
                     Ok(Value::String(stripped.join("\n")))
                 }
-                _ => Err(RutenError::TypeError("strip_comments() requires a string".to_string())),
+                _ => Err(RutenError::type_error("strip_comments() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "minify".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("minify() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("minify() takes 1 argument".to_string()));
             }
             
             match &args[0] {
@@ -108,7 +108,7 @@ pub fn create_module() -> HashMap<String, Value> {
                         .join(" ");
                     Ok(Value::String(minified))
                 }
-                _ => Err(RutenError::TypeError("minify() requires a string".to_string())),
+                _ => Err(RutenError::type_error("minify() requires a string".to_string())),
             }
         }),
     );
@@ -8,7 +8,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get current ip location
     module.insert(
         "myip".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://ipapi.co/json/";
             let client = reqwest::blocking::Client::new();
             
@@ -17,7 +17,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("geo api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("geo api error: {}", e))),
             }
         }),
     );
@@ -25,16 +25,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // lookup ip address
     module.insert(
         "lookup".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "geo.lookup() requires ip address".to_string(),
                 ));
             }
 
             let ip = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("ip must be a string".to_string())),
+                _ => return Err(RutenError::type_error("ip must be a string".to_string())),
             };
 
             let url = format!("https://ipapi.co/{}/json/", ip);
@@ -45,7 +45,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("geo api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("geo api error: {}", e))),
             }
         }),
     );
@@ -53,31 +53,31 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate distance between coordinates
     module.insert(
         "distance".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 4 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "geo.distance() requires lat1, lon1, lat2, lon2".to_string(),
                 ));
             }
 
-            let lat1 = match &args[0] {
-                Value::Number(n) => *n,
-                _ => return Err(RutenError::TypeError("latitude must be a number".to_string())),
+            let lat1 = match args[0].as_f64() {
+                Some(n) => n,
+                None => return Err(RutenError::type_error("latitude must be a number".to_string())),
             };
 
-            let lon1 = match &args[1] {
-                Value::Number(n) => *n,
-                _ => return Err(RutenError::TypeError("longitude must be a number".to_string())),
+            let lon1 = match args[1].as_f64() {
+                Some(n) => n,
+                None => return Err(RutenError::type_error("longitude must be a number".to_string())),
             };
 
-            let lat2 = match &args[2] {
-                Value::Number(n) => *n,
-                _ => return Err(RutenError::TypeError("latitude must be a number".to_string())),
+            let lat2 = match args[2].as_f64() {
+                Some(n) => n,
+                None => return Err(RutenError::type_error("latitude must be a number".to_string())),
             };
 
-            let lon2 = match &args[3] {
-                Value::Number(n) => *n,
-                _ => return Err(RutenError::TypeError("longitude must be a number".to_string())),
+            let lon2 = match args[3].as_f64() {
+                Some(n) => n,
+                None => return Err(RutenError::type_error("longitude must be a number".to_string())),
             };
 
             // haversine formula
@@ -8,16 +8,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // get user information
     module.insert(
         "user".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "github.user() requires username".to_string(),
                 ));
             }
 
             let username = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("username must be a string".to_string())),
+                _ => return Err(RutenError::type_error("username must be a string".to_string())),
             };
 
             // make api request
@@ -33,7 +33,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("github api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("github api error: {}", e))),
             }
         }),
     );
@@ -41,21 +41,21 @@ pub fn create_module() -> HashMap<String, Value> {
     // get repository information
     module.insert(
         "repo".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "github.repo() requires owner and repo name".to_string(),
                 ));
             }
 
             let owner = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("owner must be a string".to_string())),
+                _ => return Err(RutenError::type_error("owner must be a string".to_string())),
             };
 
             let repo = match &args[1] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("repo must be a string".to_string())),
+                _ => return Err(RutenError::type_error("repo must be a string".to_string())),
             };
 
             let url = format!("https://api.github.com/repos/{}/{}", owner, repo);
@@ -70,7 +70,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("github api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("github api error: {}", e))),
             }
         }),
     );
@@ -78,16 +78,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // search repositories
     module.insert(
         "search".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "github.search() requires query".to_string(),
                 ));
             }
 
             let query = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("query must be a string".to_string())),
+                _ => return Err(RutenError::type_error("query must be a string".to_string())),
             };
 
             let url = format!("https://api.github.com/search/repositories?q={}", 
@@ -103,7 +103,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("github api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("github api error: {}", e))),
             }
         }),
     );
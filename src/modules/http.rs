@@ -1,101 +1,112 @@
+use crate::dict::{DictMap, Key};
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 use reqwest::blocking;
+use std::time::Duration;
 
 pub fn create_module() -> HashMap<String, Value> {
     let mut module = HashMap::new();
 
-    // http.get(url, headers?) - get request
+    // http.get(url, headers?, timeout=seconds) - get request
     module.insert(
         "get".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native_with_keywords(&["headers", "timeout"], |ctx, args| {
             if args.is_empty() || args.len() > 2 {
-                return Err(RutenError::RuntimeError("get() takes 1 or 2 arguments".to_string()));
+                return Err(RutenError::runtime_error("get() takes 1 or 2 arguments".to_string()));
             }
             match &args[0] {
                 Value::String(url) => {
                     let client = blocking::Client::new();
-                    let mut request = client.get(url);
-                    
-                    // add headers if provided
-                    if args.len() == 2 {
-                        if let Value::Dict(headers) = &args[1] {
-                            for (key, val) in headers {
-                                if let Value::String(v) = val {
-                                    request = request.header(key, v);
-                                }
-                            }
-                        }
-                    }
-                    
-                    let response = request
-                        .send()
-                        .map_err(|e| RutenError::RuntimeError(format!("http error: {}", e)))?;
-                    
-                    let status = response.status().as_u16() as f64;
-                    let text = response
-                        .text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
-                    
-                    let mut result = HashMap::new();
-                    result.insert("status".to_string(), Value::Number(status));
-                    result.insert("body".to_string(), Value::String(text));
-                    Ok(Value::Dict(result))
+                    let request = client.get(url);
+                    let headers = ctx.kwarg("headers").or_else(|| args.get(1).cloned());
+                    let request = with_options(request, headers, ctx.kwarg("timeout"))?;
+                    send(request)
                 }
-                _ => Err(RutenError::TypeError("get() requires a url string".to_string())),
+                _ => Err(RutenError::type_error("get() requires a url string".to_string())),
             }
         }),
     );
 
-    // http.post(url, body?, headers?) - post request
+    // http.post(url, body?, headers?, timeout=seconds) - post request
     module.insert(
         "post".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native_with_keywords(&["body", "headers", "timeout"], |ctx, args| {
             if args.is_empty() || args.len() > 3 {
-                return Err(RutenError::RuntimeError("post() takes 1 to 3 arguments".to_string()));
+                return Err(RutenError::runtime_error("post() takes 1 to 3 arguments".to_string()));
             }
             match &args[0] {
                 Value::String(url) => {
                     let client = blocking::Client::new();
                     let mut request = client.post(url);
-                    
+
                     // add body if provided
-                    if args.len() >= 2 {
-                        if let Value::String(body) = &args[1] {
-                            request = request.body(body.clone());
-                        }
-                    }
-                    
-                    // add headers if provided
-                    if args.len() == 3 {
-                        if let Value::Dict(headers) = &args[2] {
-                            for (key, val) in headers {
-                                if let Value::String(v) = val {
-                                    request = request.header(key, v);
-                                }
-                            }
-                        }
+                    if let Some(Value::String(body)) = ctx.kwarg("body").or_else(|| args.get(1).cloned()) {
+                        request = request.body(body);
                     }
-                    
-                    let response = request
-                        .send()
-                        .map_err(|e| RutenError::RuntimeError(format!("http error: {}", e)))?;
-                    
-                    let status = response.status().as_u16() as f64;
-                    let text = response
-                        .text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
-                    
-                    let mut result = HashMap::new();
-                    result.insert("status".to_string(), Value::Number(status));
-                    result.insert("body".to_string(), Value::String(text));
-                    Ok(Value::Dict(result))
+
+                    let headers = ctx.kwarg("headers").or_else(|| args.get(2).cloned());
+                    let request = with_options(request, headers, ctx.kwarg("timeout"))?;
+                    send(request)
                 }
-                _ => Err(RutenError::TypeError("post() requires a url string".to_string())),
+                _ => Err(RutenError::type_error("post() requires a url string".to_string())),
             }
         }),
     );
 
     module
-}
\ No newline at end of file
+}
+// applies the optional headers dict and timeout (in seconds) shared by every request
+fn with_options(
+    mut request: blocking::RequestBuilder,
+    headers: Option<Value>,
+    timeout: Option<Value>,
+) -> Result<blocking::RequestBuilder, RutenError> {
+    match headers {
+        Some(Value::Dict(headers)) => {
+            for (key, val) in headers.borrow().iter() {
+                if let Value::String(v) = val {
+                    request = request.header(key.value().to_string(), v);
+                }
+            }
+        }
+        None | Some(Value::None) => {}
+        Some(other) => {
+            return Err(RutenError::type_error(format!("headers must be a dict, got {}", other)));
+        }
+    }
+
+    match timeout {
+        None | Some(Value::None) => {}
+        Some(value) => match value.as_f64() {
+            Some(seconds) if seconds > 0.0 => match Duration::try_from_secs_f64(seconds) {
+                Ok(duration) => request = request.timeout(duration),
+                Err(_) => return Err(RutenError::runtime_error("timeout is too large")),
+            },
+            _ => {
+                return Err(RutenError::type_error(format!(
+                    "timeout must be a positive number of seconds, got {}",
+                    value
+                )));
+            }
+        },
+    }
+    Ok(request)
+}
+
+fn send(request: blocking::RequestBuilder) -> Result<Value, RutenError> {
+    let response = request
+        .send()
+        .map_err(|e| RutenError::runtime_error(format!("http error: {}", e)))?;
+
+    let status = response.status().as_u16() as i64;
+    let text = response
+        .text()
+        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
+
+    let mut result = DictMap::new();
+    result.insert(Key::from("status"), Value::Int(Int::from(status)));
+    result.insert(Key::from("body"), Value::String(text));
+    Ok(Value::dict(result))
+}
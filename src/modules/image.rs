@@ -1,4 +1,6 @@
+use crate::dict::{DictMap, Key};
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 
@@ -8,52 +10,52 @@ pub fn create_module() -> HashMap<String, Value> {
     // get image dimensions
     module.insert(
         "dimensions".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "image.dimensions() requires file path".to_string(),
                 ));
             }
 
             let path = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("path must be a string".to_string())),
+                _ => return Err(RutenError::type_error("path must be a string".to_string())),
             };
 
             // placeholder - would use image crate in production
             println!("getting dimensions for: {}", path);
             
-            let mut result = HashMap::new();
-            result.insert("width".to_string(), Value::Number(1920.0));
-            result.insert("height".to_string(), Value::Number(1080.0));
+            let mut result = DictMap::new();
+            result.insert(Key::from("width"), Value::Int(Int::Small(1920)));
+            result.insert(Key::from("height"), Value::Int(Int::Small(1080)));
             
-            Ok(Value::Dict(result))
+            Ok(Value::dict(result))
         }),
     );
 
     // resize image
     module.insert(
         "resize".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 3 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "image.resize() requires path, width, height".to_string(),
                 ));
             }
 
             let path = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("path must be a string".to_string())),
+                _ => return Err(RutenError::type_error("path must be a string".to_string())),
             };
 
-            let width = match &args[1] {
-                Value::Number(n) => *n as u32,
-                _ => return Err(RutenError::TypeError("width must be a number".to_string())),
+            let width = match args[1].as_f64() {
+                Some(n) => n as u32,
+                None => return Err(RutenError::type_error("width must be a number".to_string())),
             };
 
-            let height = match &args[2] {
-                Value::Number(n) => *n as u32,
-                _ => return Err(RutenError::TypeError("height must be a number".to_string())),
+            let height = match args[2].as_f64() {
+                Some(n) => n as u32,
+                None => return Err(RutenError::type_error("height must be a number".to_string())),
             };
 
             println!("resizing {} to {}x{}", path, width, height);
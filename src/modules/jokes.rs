@@ -8,7 +8,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get random joke
     module.insert(
         "random".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://official-joke-api.appspot.com/random_joke";
             let client = reqwest::blocking::Client::new();
             
@@ -17,7 +17,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("jokes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("jokes api error: {}", e))),
             }
         }),
     );
@@ -25,7 +25,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get programming joke
     module.insert(
         "programming".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://official-joke-api.appspot.com/jokes/programming/random";
             let client = reqwest::blocking::Client::new();
             
@@ -34,7 +34,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("jokes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("jokes api error: {}", e))),
             }
         }),
     );
@@ -42,13 +42,13 @@ pub fn create_module() -> HashMap<String, Value> {
     // get multiple jokes
     module.insert(
         "multiple".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let count = if args.is_empty() {
                 5
             } else {
-                match &args[0] {
-                    Value::Number(n) => *n as i32,
-                    _ => return Err(RutenError::TypeError("count must be a number".to_string())),
+                match args[0].as_f64() {
+                    Some(n) => n as i32,
+                    None => return Err(RutenError::type_error("count must be a number".to_string())),
                 }
             };
 
@@ -60,7 +60,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("jokes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("jokes api error: {}", e))),
             }
         }),
     );
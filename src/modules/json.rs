@@ -1,5 +1,7 @@
+use crate::dict::{DictMap, Key};
 use crate::error::RutenError;
-use crate::interpreter::Value;
+use crate::int::Int;
+use crate::interpreter::{type_name, Value};
 use std::collections::HashMap;
 use serde_json;
 
@@ -8,43 +10,43 @@ pub fn create_module() -> HashMap<String, Value> {
 
     module.insert(
         "parse".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("parse() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("parse() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::String(json_str) => {
                     let json_value: serde_json::Value = serde_json::from_str(json_str)
-                        .map_err(|e| RutenError::RuntimeError(format!("json parse error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("json parse error: {}", e)))?;
                     json_to_value(&json_value)
                 }
-                _ => Err(RutenError::TypeError("parse() requires a string".to_string())),
+                _ => Err(RutenError::type_error("parse() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "stringify".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("stringify() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("stringify() takes 1 argument".to_string()));
             }
             let json_value = value_to_json(&args[0])?;
             let json_str = serde_json::to_string(&json_value)
-                .map_err(|e| RutenError::RuntimeError(format!("json stringify error: {}", e)))?;
+                .map_err(|e| RutenError::runtime_error(format!("json stringify error: {}", e)))?;
             Ok(Value::String(json_str))
         }),
     );
 
     module.insert(
         "pretty".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("pretty() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("pretty() takes 1 argument".to_string()));
             }
             let json_value = value_to_json(&args[0])?;
             let json_str = serde_json::to_string_pretty(&json_value)
-                .map_err(|e| RutenError::RuntimeError(format!("json pretty error: {}", e)))?;
+                .map_err(|e| RutenError::runtime_error(format!("json pretty error: {}", e)))?;
             Ok(Value::String(json_str))
         }),
     );
@@ -57,23 +59,28 @@ fn json_to_value(json: &serde_json::Value) -> Result<Value, RutenError> {
         serde_json::Value::Null => Ok(Value::None),
         serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
         serde_json::Value::Number(n) => {
-            if let Some(f) = n.as_f64() {
+            // integers stay exact, however long; only numbers with a fraction or exponent
+            // become floats
+            let text = n.to_string();
+            if let Some(i) = Int::parse(&text) {
+                Ok(Value::Int(i))
+            } else if let Some(f) = n.as_f64() {
                 Ok(Value::Number(f))
             } else {
-                Err(RutenError::RuntimeError("invalid json number".to_string()))
+                Err(RutenError::runtime_error("invalid json number".to_string()))
             }
         }
         serde_json::Value::String(s) => Ok(Value::String(s.clone())),
         serde_json::Value::Array(arr) => {
             let values: Result<Vec<_>, _> = arr.iter().map(json_to_value).collect();
-            Ok(Value::List(values?))
+            Ok(Value::list(values?))
         }
         serde_json::Value::Object(obj) => {
-            let mut map = HashMap::new();
+            let mut map = DictMap::new();
             for (key, val) in obj {
-                map.insert(key.clone(), json_to_value(val)?);
+                map.insert(Key::from(key.as_str()), json_to_value(val)?);
             }
-            Ok(Value::Dict(map))
+            Ok(Value::dict(map))
         }
     }
 }
@@ -82,23 +89,55 @@ fn value_to_json(value: &Value) -> Result<serde_json::Value, RutenError> {
     match value {
         Value::None => Ok(serde_json::Value::Null),
         Value::Bool(b) => Ok(serde_json::Value::Bool(*b)),
+        Value::Int(Int::Small(n)) => Ok(serde_json::Value::Number((*n).into())),
+        // serde_json keeps the digits of a number, so big ints are written exactly
+        Value::Int(n) => serde_json::from_str(&n.to_string())
+            .map_err(|_| RutenError::runtime_error("invalid number for json".to_string())),
         Value::Number(n) => {
             serde_json::Number::from_f64(*n)
                 .map(serde_json::Value::Number)
-                .ok_or_else(|| RutenError::RuntimeError("invalid number for json".to_string()))
+                .ok_or_else(|| RutenError::runtime_error("invalid number for json".to_string()))
         }
         Value::String(s) => Ok(serde_json::Value::String(s.clone())),
         Value::List(items) => {
+            let items = items.borrow();
+            let json_items: Result<Vec<_>, _> = items.iter().map(value_to_json).collect();
+            Ok(serde_json::Value::Array(json_items?))
+        }
+        Value::Tuple(items) => {
             let json_items: Result<Vec<_>, _> = items.iter().map(value_to_json).collect();
             Ok(serde_json::Value::Array(json_items?))
         }
         Value::Dict(map) => {
+            let map = map.borrow();
             let mut json_obj = serde_json::Map::new();
-            for (key, val) in map {
-                json_obj.insert(key.clone(), value_to_json(val)?);
+            for (key, val) in map.iter() {
+                let name = json_key(key.value())?;
+                // e.g. 1 and "1", which would otherwise overwrite each other
+                if json_obj.contains_key(&name) {
+                    return Err(RutenError::runtime_error(format!(
+                        "dict keys collide as the json key \"{}\"",
+                        name
+                    )));
+                }
+                json_obj.insert(name, value_to_json(val)?);
             }
             Ok(serde_json::Value::Object(json_obj))
         }
-        _ => Err(RutenError::TypeError("cannot convert to json".to_string())),
+        _ => Err(RutenError::type_error("cannot convert to json".to_string())),
+    }
+}
+
+// json object keys are strings, so other scalar keys are written out as text
+fn json_key(key: &Value) -> Result<String, RutenError> {
+    match key {
+        Value::String(s) => Ok(s.clone()),
+        Value::Int(_) | Value::Number(_) => Ok(key.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::None => Ok("null".to_string()),
+        other => Err(RutenError::type_error(format!(
+            "json keys must be strings, numbers, bools or None, not {}",
+            type_name(other)
+        ))),
     }
 }
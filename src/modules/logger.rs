@@ -7,7 +7,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // info log
     module.insert(
         "info".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let message = args
                 .iter()
                 .map(|v| v.to_string())
@@ -21,7 +21,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // warning log
     module.insert(
         "warn".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let message = args
                 .iter()
                 .map(|v| v.to_string())
@@ -35,7 +35,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // error log
     module.insert(
         "error".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let message = args
                 .iter()
                 .map(|v| v.to_string())
@@ -49,7 +49,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // debug log
     module.insert(
         "debug".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let message = args
                 .iter()
                 .map(|v| v.to_string())
@@ -63,7 +63,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // success log
     module.insert(
         "success".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let message = args
                 .iter()
                 .map(|v| v.to_string())
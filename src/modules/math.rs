@@ -1,5 +1,8 @@
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
+use crate::ops;
+use crate::parser::BinaryOp;
 use std::collections::HashMap;
 
 pub fn create_module() -> HashMap<String, Value> {
@@ -12,39 +15,40 @@ pub fn create_module() -> HashMap<String, Value> {
     // basic math functions
     module.insert(
         "sqrt".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sqrt() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sqrt() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.sqrt())),
-                _ => Err(RutenError::TypeError("sqrt() requires a number".to_string())),
+            match args[0].as_f64() {
+                Some(n) => Ok(Value::Number(n.sqrt())),
+                None => Err(RutenError::type_error("sqrt() requires a number".to_string())),
             }
         }),
     );
 
     module.insert(
         "pow".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 2 {
-                return Err(RutenError::RuntimeError("pow() takes 2 arguments".to_string()));
+                return Err(RutenError::runtime_error("pow() takes 2 arguments".to_string()));
             }
-            match (&args[0], &args[1]) {
-                (Value::Number(base), Value::Number(exp)) => Ok(Value::Number(base.powf(*exp))),
-                _ => Err(RutenError::TypeError("pow() requires numbers".to_string())),
+            match (args[0].as_f64(), args[1].as_f64()) {
+                (Some(base), Some(exp)) => Ok(Value::Number(base.powf(exp))),
+                _ => Err(RutenError::type_error("pow() requires numbers".to_string())),
             }
         }),
     );
 
     module.insert(
         "abs".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("abs() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("abs() takes 1 argument".to_string()));
             }
             match &args[0] {
+                Value::Int(n) => Ok(Value::Int(n.abs())),
                 Value::Number(n) => Ok(Value::Number(n.abs())),
-                _ => Err(RutenError::TypeError("abs() requires a number".to_string())),
+                _ => Err(RutenError::type_error("abs() requires a number".to_string())),
             }
         }),
     );
@@ -52,39 +56,39 @@ pub fn create_module() -> HashMap<String, Value> {
     // trigonometric functions
     module.insert(
         "sin".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sin() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sin() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.sin())),
-                _ => Err(RutenError::TypeError("sin() requires a number".to_string())),
+            match args[0].as_f64() {
+                Some(n) => Ok(Value::Number(n.sin())),
+                None => Err(RutenError::type_error("sin() requires a number".to_string())),
             }
         }),
     );
 
     module.insert(
         "cos".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("cos() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("cos() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.cos())),
-                _ => Err(RutenError::TypeError("cos() requires a number".to_string())),
+            match args[0].as_f64() {
+                Some(n) => Ok(Value::Number(n.cos())),
+                None => Err(RutenError::type_error("cos() requires a number".to_string())),
             }
         }),
     );
 
     module.insert(
         "tan".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("tan() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("tan() takes 1 argument".to_string()));
             }
-            match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.tan())),
-                _ => Err(RutenError::TypeError("tan() requires a number".to_string())),
+            match args[0].as_f64() {
+                Some(n) => Ok(Value::Number(n.tan())),
+                None => Err(RutenError::type_error("tan() requires a number".to_string())),
             }
         }),
     );
@@ -92,49 +96,51 @@ pub fn create_module() -> HashMap<String, Value> {
     // statistical functions
     module.insert(
         "sum".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sum() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sum() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::List(items) => {
-                    let mut total = 0.0;
-                    for item in items {
-                        if let Value::Number(n) = item {
-                            total += n;
-                        } else {
-                            return Err(RutenError::TypeError("sum() requires a list of numbers".to_string()));
+                    let items = items.borrow();
+                    // integers sum exactly until a float joins in
+                    let mut total = Value::Int(Int::Small(0));
+                    for item in items.iter() {
+                        if item.as_f64().is_none() {
+                            return Err(RutenError::type_error("sum() requires a list of numbers".to_string()));
                         }
+                        total = ops::binary_op(&total, &BinaryOp::Add, item)?;
                     }
-                    Ok(Value::Number(total))
+                    Ok(total)
                 }
-                _ => Err(RutenError::TypeError("sum() requires a list".to_string())),
+                _ => Err(RutenError::type_error("sum() requires a list".to_string())),
             }
         }),
     );
 
     module.insert(
         "mean".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("mean() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("mean() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("mean() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("mean() requires non-empty list".to_string()));
                     }
                     let mut total = 0.0;
-                    for item in items {
-                        if let Value::Number(n) = item {
+                    for item in items.iter() {
+                        if let Some(n) = item.as_f64() {
                             total += n;
                         } else {
-                            return Err(RutenError::TypeError("mean() requires a list of numbers".to_string()));
+                            return Err(RutenError::type_error("mean() requires a list of numbers".to_string()));
                         }
                     }
                     Ok(Value::Number(total / items.len() as f64))
                 }
-                _ => Err(RutenError::TypeError("mean() requires a list".to_string())),
+                _ => Err(RutenError::type_error("mean() requires a list".to_string())),
             }
         }),
     );
@@ -142,46 +148,49 @@ pub fn create_module() -> HashMap<String, Value> {
     // fibonacci function
     module.insert(
         "fibonacci".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("fibonacci() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("fibonacci() takes 1 argument".to_string()));
             }
             match &args[0] {
-                Value::Number(n) => {
-                    let n = *n as i32;
-                    if n < 0 {
-                        return Err(RutenError::RuntimeError("fibonacci() requires non-negative number".to_string()));
+                Value::Int(n) => {
+                    if n.is_negative() {
+                        return Err(RutenError::runtime_error("fibonacci() requires non-negative number".to_string()));
                     }
-                    let result = fibonacci(n);
-                    Ok(Value::Number(result as f64))
+                    let n = n
+                        .to_i64()
+                        .ok_or_else(|| RutenError::runtime_error("fibonacci() argument too large".to_string()))?;
+                    Ok(Value::Int(fibonacci(n)))
                 }
-                _ => Err(RutenError::TypeError("fibonacci() requires a number".to_string())),
+                _ => Err(RutenError::type_error("fibonacci() requires an integer".to_string())),
             }
         }),
     );
 
     module.insert(
         "floor".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("floor() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("floor() takes 1 argument".to_string()));
             }
             match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.floor())),
-                _ => Err(RutenError::TypeError("floor() requires a number".to_string())),
+                Value::Int(n) => Ok(Value::Int(n.clone())),
+                Value::Number(n) => to_int(n.floor()),
+                _ => Err(RutenError::type_error("floor() requires a number".to_string())),
             }
         }),
     );
 
     module.insert(
         "ceil".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("ceil() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("ceil() takes 1 argument".to_string()));
             }
             match &args[0] {
-                Value::Number(n) => Ok(Value::Number(n.ceil())),
-                _ => Err(RutenError::TypeError("ceil() requires a number".to_string())),
+                Value::Int(n) => Ok(Value::Int(n.clone())),
+                Value::Number(n) => to_int(n.ceil()),
+                _ => Err(RutenError::type_error("ceil() requires a number".to_string())),
             }
         }),
     );
@@ -189,16 +198,22 @@ pub fn create_module() -> HashMap<String, Value> {
     module
 }
 
-fn fibonacci(n: i32) -> i64 {
+fn fibonacci(n: i64) -> Int {
     if n <= 1 {
-        return n as i64;
+        return Int::Small(n);
     }
-    let mut a = 0i64;
-    let mut b = 1i64;
+    let mut a = Int::Small(0);
+    let mut b = Int::Small(1);
     for _ in 2..=n {
-        let temp = a + b;
+        let temp = a.add(&b);
         a = b;
         b = temp;
     }
     b
+}
+
+fn to_int(n: f64) -> Result<Value, RutenError> {
+    Int::from_f64(n)
+        .map(Value::Int)
+        .ok_or_else(|| RutenError::runtime_error(format!("cannot convert {} to int", n)))
 }
\ No newline at end of file
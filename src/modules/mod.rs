@@ -30,10 +30,10 @@ pub mod logger;
 pub mod image;
 pub mod blockchain;
 
-use crate::error::RutenError;
 use crate::interpreter::Value;
 
-pub fn load_module(name: &str) -> Result<Value, RutenError> {
+/// the built-in module called `name`, if there is one
+pub fn load_module(name: &str) -> Option<Value> {
     let module_map = match name {
         "math" => math::create_module(),
         "strings" => strings::create_module(),
@@ -66,13 +66,8 @@ pub fn load_module(name: &str) -> Result<Value, RutenError> {
         "logger" => logger::create_module(),
         "image" => image::create_module(),
         "blockchain" => blockchain::create_module(),
-        _ => {
-            return Err(RutenError::ImportError(format!(
-                "no module named '{}'",
-                name
-            )))
-        }
+        _ => return None,
     };
 
-    Ok(Value::Module(module_map))
+    Some(Value::module(module_map))
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 
@@ -8,43 +9,48 @@ pub fn create_module() -> HashMap<String, Value> {
     // get number of cpu cores
     module.insert(
         "cpu_count".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("cpu_count() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("cpu_count() takes no arguments".to_string()));
             }
             let count = num_cpus::get();
-            Ok(Value::Number(count as f64))
+            Ok(Value::Int(Int::from(count)))
         }),
     );
 
     // get number of physical cpu cores
     module.insert(
         "physical_cores".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("physical_cores() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("physical_cores() takes no arguments".to_string()));
             }
             let count = num_cpus::get_physical();
-            Ok(Value::Number(count as f64))
+            Ok(Value::Int(Int::from(count)))
         }),
     );
 
-    // execute function in parallel (placeholder - requires more complex implementation)
+    // multiproc.parallel(fn, data_list) - applies fn to every item and returns the results in order
     module.insert(
         "parallel".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|ctx, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "parallel() takes 2 arguments: function, data_list".to_string()
                 ));
             }
-            
-            // note: full parallel execution would require thread-safe interpreter state
-            // this is a simplified version that returns the input for now
-            match &args[1] {
-                Value::List(items) => Ok(Value::List(items.clone())),
-                _ => Err(RutenError::TypeError("parallel() requires a list as second argument".to_string())),
+
+            // note: script functions share one interpreter, which is not thread-safe,
+            // so the calls run one after another on the current thread
+            let items = match &args[1] {
+                Value::List(items) => items.borrow().clone(),
+                _ => return Err(RutenError::type_error("parallel() requires a list as second argument".to_string())),
+            };
+            let mut results = Vec::with_capacity(items.len());
+            for item in items {
+                results.push(ctx.call(&args[0], vec![item])?);
             }
+            Ok(Value::list(results))
         }),
     );
 
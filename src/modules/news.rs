@@ -8,13 +8,13 @@ pub fn create_module() -> HashMap<String, Value> {
     // get top headlines
     module.insert(
         "headlines".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             let country = if args.is_empty() {
                 "us".to_string()
             } else {
                 match &args[0] {
                     Value::String(s) => s.clone(),
-                    _ => return Err(RutenError::TypeError("country must be a string".to_string())),
+                    _ => return Err(RutenError::type_error("country must be a string".to_string())),
                 }
             };
 
@@ -27,7 +27,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("news api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("news api error: {}", e))),
             }
         }),
     );
@@ -35,16 +35,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // search news articles
     module.insert(
         "search".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "news.search() requires query".to_string(),
                 ));
             }
 
             let query = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("query must be a string".to_string())),
+                _ => return Err(RutenError::type_error("query must be a string".to_string())),
             };
 
             let url = format!("https://newsapi.org/v2/everything?q={}&apiKey=demo", 
@@ -56,7 +56,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("news api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("news api error: {}", e))),
             }
         }),
     );
@@ -1,4 +1,6 @@
+use crate::dict::{DictMap, Key};
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 
@@ -7,9 +9,9 @@ pub fn create_module() -> HashMap<String, Value> {
 
     module.insert(
         "tokenize".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("tokenize() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("tokenize() takes 1 argument".to_string()));
             }
             
             match &args[0] {
@@ -19,35 +21,35 @@ pub fn create_module() -> HashMap<String, Value> {
                         .split_whitespace()
                         .map(|word| Value::String(word.to_string()))
                         .collect();
-                    Ok(Value::List(tokens))
+                    Ok(Value::list(tokens))
                 }
-                _ => Err(RutenError::TypeError("tokenize() requires a string".to_string())),
+                _ => Err(RutenError::type_error("tokenize() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "word_count".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("word_count() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("word_count() takes 1 argument".to_string()));
             }
             
             match &args[0] {
                 Value::String(text) => {
                     let count = text.split_whitespace().count();
-                    Ok(Value::Number(count as f64))
+                    Ok(Value::Int(Int::from(count)))
                 }
-                _ => Err(RutenError::TypeError("word_count() requires a string".to_string())),
+                _ => Err(RutenError::type_error("word_count() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "sentiment".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sentiment() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sentiment() takes 1 argument".to_string()));
             }
             
             match &args[0] {
@@ -79,12 +81,12 @@ pub fn create_module() -> HashMap<String, Value> {
                         "neutral"
                     };
                     
-                    let mut result = HashMap::new();
-                    result.insert("score".to_string(), Value::Number(score));
-                    result.insert("sentiment".to_string(), Value::String(sentiment.to_string()));
-                    Ok(Value::Dict(result))
+                    let mut result = DictMap::new();
+                    result.insert(Key::from("score"), Value::Number(score));
+                    result.insert(Key::from("sentiment"), Value::String(sentiment.to_string()));
+                    Ok(Value::dict(result))
                 }
-                _ => Err(RutenError::TypeError("sentiment() requires a string".to_string())),
+                _ => Err(RutenError::type_error("sentiment() requires a string".to_string())),
             }
         }),
     );
@@ -8,7 +8,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get random quote
     module.insert(
         "random".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://api.quotable.io/random";
             let client = reqwest::blocking::Client::new();
             
@@ -17,7 +17,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("quotes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("quotes api error: {}", e))),
             }
         }),
     );
@@ -25,16 +25,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // get quote by author
     module.insert(
         "author".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "quotes.author() requires author name".to_string(),
                 ));
             }
 
             let author = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("author must be a string".to_string())),
+                _ => return Err(RutenError::type_error("author must be a string".to_string())),
             };
 
             let url = format!("https://api.quotable.io/quotes?author={}", 
@@ -46,7 +46,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("quotes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("quotes api error: {}", e))),
             }
         }),
     );
@@ -54,7 +54,7 @@ pub fn create_module() -> HashMap<String, Value> {
     // get quote of the day
     module.insert(
         "today".to_string(),
-        Value::NativeFunction(|_args| {
+        Value::native(|_, _args| {
             let url = "https://api.quotable.io/quotes/random?limit=1";
             let client = reqwest::blocking::Client::new();
             
@@ -63,7 +63,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let body = response.text().unwrap_or_default();
                     Ok(Value::String(body))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("quotes api error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("quotes api error: {}", e))),
             }
         }),
     );
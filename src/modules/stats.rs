@@ -8,28 +8,29 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate mean (average)
     module.insert(
         "mean".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("mean() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("mean() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("mean() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("mean() requires non-empty list".to_string()));
                     }
                     
                     let mut sum = 0.0;
-                    for item in items {
-                        match item {
-                            Value::Number(n) => sum += n,
-                            _ => return Err(RutenError::TypeError("mean() requires list of numbers".to_string())),
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => sum += n,
+                            None => return Err(RutenError::type_error("mean() requires list of numbers".to_string())),
                         }
                     }
                     
                     Ok(Value::Number(sum / items.len() as f64))
                 }
-                _ => Err(RutenError::TypeError("mean() requires a list".to_string())),
+                _ => Err(RutenError::type_error("mean() requires a list".to_string())),
             }
         }),
     );
@@ -37,27 +38,29 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate median
     module.insert(
         "median".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("median() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("median() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("median() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("median() requires non-empty list".to_string()));
                     }
                     
                     let mut numbers: Vec<f64> = Vec::new();
-                    for item in items {
-                        match item {
-                            Value::Number(n) => numbers.push(*n),
-                            _ => return Err(RutenError::TypeError("median() requires list of numbers".to_string())),
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => numbers.push(n),
+                            None => return Err(RutenError::type_error("median() requires list of numbers".to_string())),
                         }
                     }
                     
                     numbers.sort_by(|a, b| a.partial_cmp(b).unwrap());
                     let len = numbers.len();
+                    #[allow(clippy::manual_is_multiple_of)] // note: is_multiple_of needs rust 1.87
                     let median = if len % 2 == 0 {
                         (numbers[len / 2 - 1] + numbers[len / 2]) / 2.0
                     } else {
@@ -66,7 +69,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     
                     Ok(Value::Number(median))
                 }
-                _ => Err(RutenError::TypeError("median() requires a list".to_string())),
+                _ => Err(RutenError::type_error("median() requires a list".to_string())),
             }
         }),
     );
@@ -74,22 +77,23 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate standard deviation
     module.insert(
         "stdev".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("stdev() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("stdev() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("stdev() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("stdev() requires non-empty list".to_string()));
                     }
                     
                     let mut numbers: Vec<f64> = Vec::new();
-                    for item in items {
-                        match item {
-                            Value::Number(n) => numbers.push(*n),
-                            _ => return Err(RutenError::TypeError("stdev() requires list of numbers".to_string())),
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => numbers.push(n),
+                            None => return Err(RutenError::type_error("stdev() requires list of numbers".to_string())),
                         }
                     }
                     
@@ -100,7 +104,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     
                     Ok(Value::Number(variance.sqrt()))
                 }
-                _ => Err(RutenError::TypeError("stdev() requires a list".to_string())),
+                _ => Err(RutenError::type_error("stdev() requires a list".to_string())),
             }
         }),
     );
@@ -108,32 +112,33 @@ pub fn create_module() -> HashMap<String, Value> {
     // find minimum value
     module.insert(
         "min".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("min() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("min() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("min() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("min() requires non-empty list".to_string()));
                     }
                     
                     let mut min = f64::INFINITY;
-                    for item in items {
-                        match item {
-                            Value::Number(n) => {
-                                if *n < min {
-                                    min = *n;
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => {
+                                if n < min {
+                                    min = n;
                                 }
                             }
-                            _ => return Err(RutenError::TypeError("min() requires list of numbers".to_string())),
+                            None => return Err(RutenError::type_error("min() requires list of numbers".to_string())),
                         }
                     }
                     
                     Ok(Value::Number(min))
                 }
-                _ => Err(RutenError::TypeError("min() requires a list".to_string())),
+                _ => Err(RutenError::type_error("min() requires a list".to_string())),
             }
         }),
     );
@@ -141,32 +146,33 @@ pub fn create_module() -> HashMap<String, Value> {
     // find maximum value
     module.insert(
         "max".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("max() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("max() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("max() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("max() requires non-empty list".to_string()));
                     }
                     
                     let mut max = f64::NEG_INFINITY;
-                    for item in items {
-                        match item {
-                            Value::Number(n) => {
-                                if *n > max {
-                                    max = *n;
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => {
+                                if n > max {
+                                    max = n;
                                 }
                             }
-                            _ => return Err(RutenError::TypeError("max() requires list of numbers".to_string())),
+                            None => return Err(RutenError::type_error("max() requires list of numbers".to_string())),
                         }
                     }
                     
                     Ok(Value::Number(max))
                 }
-                _ => Err(RutenError::TypeError("max() requires a list".to_string())),
+                _ => Err(RutenError::type_error("max() requires a list".to_string())),
             }
         }),
     );
@@ -174,22 +180,23 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate variance
     module.insert(
         "variance".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("variance() takes 1 argument: list of numbers".to_string()));
+                return Err(RutenError::runtime_error("variance() takes 1 argument: list of numbers".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("variance() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("variance() requires non-empty list".to_string()));
                     }
                     
                     let mut numbers: Vec<f64> = Vec::new();
-                    for item in items {
-                        match item {
-                            Value::Number(n) => numbers.push(*n),
-                            _ => return Err(RutenError::TypeError("variance() requires list of numbers".to_string())),
+                    for item in items.iter() {
+                        match item.as_f64() {
+                            Some(n) => numbers.push(n),
+                            None => return Err(RutenError::type_error("variance() requires list of numbers".to_string())),
                         }
                     }
                     
@@ -200,7 +207,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     
                     Ok(Value::Number(variance))
                 }
-                _ => Err(RutenError::TypeError("variance() requires a list".to_string())),
+                _ => Err(RutenError::type_error("variance() requires a list".to_string())),
             }
         }),
     );
@@ -208,35 +215,36 @@ pub fn create_module() -> HashMap<String, Value> {
     // calculate correlation between two datasets
     module.insert(
         "correlation".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("correlation() takes 2 arguments: two lists of numbers".to_string()));
+                return Err(RutenError::runtime_error("correlation() takes 2 arguments: two lists of numbers".to_string()));
             }
             
             match (&args[0], &args[1]) {
                 (Value::List(x_items), Value::List(y_items)) => {
+                    let (x_items, y_items) = (x_items.borrow(), y_items.borrow());
                     if x_items.len() != y_items.len() {
-                        return Err(RutenError::RuntimeError("correlation() requires lists of equal length".to_string()));
+                        return Err(RutenError::runtime_error("correlation() requires lists of equal length".to_string()));
                     }
                     
                     if x_items.is_empty() {
-                        return Err(RutenError::RuntimeError("correlation() requires non-empty lists".to_string()));
+                        return Err(RutenError::runtime_error("correlation() requires non-empty lists".to_string()));
                     }
                     
                     let mut x_vals: Vec<f64> = Vec::new();
                     let mut y_vals: Vec<f64> = Vec::new();
                     
-                    for item in x_items {
-                        match item {
-                            Value::Number(n) => x_vals.push(*n),
-                            _ => return Err(RutenError::TypeError("correlation() requires lists of numbers".to_string())),
+                    for item in x_items.iter() {
+                        match item.as_f64() {
+                            Some(n) => x_vals.push(n),
+                            None => return Err(RutenError::type_error("correlation() requires lists of numbers".to_string())),
                         }
                     }
                     
-                    for item in y_items {
-                        match item {
-                            Value::Number(n) => y_vals.push(*n),
-                            _ => return Err(RutenError::TypeError("correlation() requires lists of numbers".to_string())),
+                    for item in y_items.iter() {
+                        match item.as_f64() {
+                            Some(n) => y_vals.push(n),
+                            None => return Err(RutenError::type_error("correlation() requires lists of numbers".to_string())),
                         }
                     }
                     
@@ -259,7 +267,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let correlation = numerator / (x_sq_sum * y_sq_sum).sqrt();
                     Ok(Value::Number(correlation))
                 }
-                _ => Err(RutenError::TypeError("correlation() requires two lists".to_string())),
+                _ => Err(RutenError::type_error("correlation() requires two lists".to_string())),
             }
         }),
     );
@@ -8,9 +8,9 @@ pub fn create_module() -> HashMap<String, Value> {
 
     module.insert(
         "sh".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 1 {
-                return Err(RutenError::RuntimeError("sh() takes 1 argument".to_string()));
+                return Err(RutenError::runtime_error("sh() takes 1 argument".to_string()));
             }
             match &args[0] {
                 Value::String(command) => {
@@ -33,24 +33,24 @@ pub fn create_module() -> HashMap<String, Value> {
                             let output = String::from_utf8_lossy(&capture.stdout).to_string();
                             Ok(Value::String(output.trim().to_string()))
                         }
-                        Err(e) => Err(RutenError::RuntimeError(format!("command error: {}", e))),
+                        Err(e) => Err(RutenError::runtime_error(format!("command error: {}", e))),
                     }
                 }
-                _ => Err(RutenError::TypeError("sh() requires a string".to_string())),
+                _ => Err(RutenError::type_error("sh() requires a string".to_string())),
             }
         }),
     );
 
     module.insert(
         "exec".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("exec() takes at least 1 argument".to_string()));
+                return Err(RutenError::runtime_error("exec() takes at least 1 argument".to_string()));
             }
             
             let command = match &args[0] {
                 Value::String(s) => s.clone(),
-                _ => return Err(RutenError::TypeError("exec() requires string arguments".to_string())),
+                _ => return Err(RutenError::type_error("exec() requires string arguments".to_string())),
             };
             
             let mut exec = Exec::cmd(&command);
@@ -59,7 +59,7 @@ pub fn create_module() -> HashMap<String, Value> {
                 if let Value::String(s) = arg {
                     exec = exec.arg(s);
                 } else {
-                    return Err(RutenError::TypeError("exec() requires string arguments".to_string()));
+                    return Err(RutenError::type_error("exec() requires string arguments".to_string()));
                 }
             }
             
@@ -73,7 +73,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let output = String::from_utf8_lossy(&capture.stdout).to_string();
                     Ok(Value::String(output.trim().to_string()))
                 }
-                Err(e) => Err(RutenError::RuntimeError(format!("exec error: {}", e))),
+                Err(e) => Err(RutenError::runtime_error(format!("exec error: {}", e))),
             }
         }),
     );
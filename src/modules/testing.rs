@@ -1,5 +1,6 @@
 use crate::error::RutenError;
 use crate::interpreter::Value;
+use crate::ops;
 use std::collections::HashMap;
 
 pub fn create_module() -> HashMap<String, Value> {
@@ -8,9 +9,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // assert equal
     module.insert(
         "assert_equal".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "testing.assert_equal() requires two values".to_string(),
                 ));
             }
@@ -18,18 +19,11 @@ pub fn create_module() -> HashMap<String, Value> {
             let a = &args[0];
             let b = &args[1];
 
-            let equal = match (a, b) {
-                (Value::Number(x), Value::Number(y)) => x == y,
-                (Value::String(x), Value::String(y)) => x == y,
-                (Value::Bool(x), Value::Bool(y)) => x == y,
-                _ => false,
-            };
-
-            if !equal {
-                return Err(RutenError::RuntimeError(format!(
+            if !ops::values_equal(a, b) {
+                return Err(RutenError::runtime_error(format!(
                     "assertion failed: {} != {}",
-                    a.to_string(),
-                    b.to_string()
+                    a,
+                    b
                 )));
             }
 
@@ -40,15 +34,15 @@ pub fn create_module() -> HashMap<String, Value> {
     // assert true
     module.insert(
         "assert_true".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "testing.assert_true() requires value".to_string(),
                 ));
             }
 
             if !args[0].is_truthy() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "assertion failed: value is not true".to_string(),
                 ));
             }
@@ -60,15 +54,15 @@ pub fn create_module() -> HashMap<String, Value> {
     // assert false
     module.insert(
         "assert_false".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "testing.assert_false() requires value".to_string(),
                 ));
             }
 
             if args[0].is_truthy() {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "assertion failed: value is not false".to_string(),
                 ));
             }
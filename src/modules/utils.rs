@@ -1,4 +1,5 @@
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -10,41 +11,41 @@ pub fn create_module() -> HashMap<String, Value> {
     // get current unix timestamp
     module.insert(
         "timestamp".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("timestamp() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("timestamp() takes no arguments".to_string()));
             }
             
             let now = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
-                .map_err(|e| RutenError::RuntimeError(format!("time error: {}", e)))?;
+                .map_err(|e| RutenError::runtime_error(format!("time error: {}", e)))?;
             
-            Ok(Value::Number(now.as_secs() as f64))
+            Ok(Value::Int(Int::from(now.as_secs() as i64)))
         }),
     );
 
     // get current timestamp in milliseconds
     module.insert(
         "timestamp_ms".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("timestamp_ms() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("timestamp_ms() takes no arguments".to_string()));
             }
             
             let now = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
-                .map_err(|e| RutenError::RuntimeError(format!("time error: {}", e)))?;
+                .map_err(|e| RutenError::runtime_error(format!("time error: {}", e)))?;
             
-            Ok(Value::Number(now.as_millis() as f64))
+            Ok(Value::Int(Int::from(now.as_millis() as i64)))
         }),
     );
 
     // generate uuid v4
     module.insert(
         "uuid".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("uuid() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("uuid() takes no arguments".to_string()));
             }
             
             let id = Uuid::new_v4();
@@ -55,22 +56,22 @@ pub fn create_module() -> HashMap<String, Value> {
     // sleep for specified seconds
     module.insert(
         "sleep".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("sleep() takes 1 argument: seconds".to_string()));
+                return Err(RutenError::runtime_error("sleep() takes 1 argument: seconds".to_string()));
             }
             
-            match &args[0] {
-                Value::Number(seconds) => {
-                    if *seconds < 0.0 {
-                        return Err(RutenError::RuntimeError("sleep() requires positive number".to_string()));
+            match args[0].as_f64() {
+                Some(seconds) => {
+                    if seconds < 0.0 {
+                        return Err(RutenError::runtime_error("sleep() requires positive number".to_string()));
                     }
                     
-                    let duration = std::time::Duration::from_secs_f64(*seconds);
+                    let duration = std::time::Duration::from_secs_f64(seconds);
                     std::thread::sleep(duration);
                     Ok(Value::None)
                 }
-                _ => Err(RutenError::TypeError("sleep() requires a number".to_string())),
+                None => Err(RutenError::type_error("sleep() requires a number".to_string())),
             }
         }),
     );
@@ -78,9 +79,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // get environment variable
     module.insert(
         "getenv".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("getenv() takes 1 argument: variable name".to_string()));
+                return Err(RutenError::runtime_error("getenv() takes 1 argument: variable name".to_string()));
             }
             
             match &args[0] {
@@ -90,7 +91,7 @@ pub fn create_module() -> HashMap<String, Value> {
                         Err(_) => Ok(Value::None),
                     }
                 }
-                _ => Err(RutenError::TypeError("getenv() requires a string".to_string())),
+                _ => Err(RutenError::type_error("getenv() requires a string".to_string())),
             }
         }),
     );
@@ -98,9 +99,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // set environment variable
     module.insert(
         "setenv".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("setenv() takes 2 arguments: name, value".to_string()));
+                return Err(RutenError::runtime_error("setenv() takes 2 arguments: name, value".to_string()));
             }
             
             match (&args[0], &args[1]) {
@@ -108,7 +109,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     std::env::set_var(name, value);
                     Ok(Value::None)
                 }
-                _ => Err(RutenError::TypeError("setenv() requires two strings".to_string())),
+                _ => Err(RutenError::type_error("setenv() requires two strings".to_string())),
             }
         }),
     );
@@ -116,9 +117,9 @@ pub fn create_module() -> HashMap<String, Value> {
     // generate random number between 0 and 1
     module.insert(
         "random".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if !args.is_empty() {
-                return Err(RutenError::RuntimeError("random() takes no arguments".to_string()));
+                return Err(RutenError::runtime_error("random() takes no arguments".to_string()));
             }
             
             use rand::Rng;
@@ -131,19 +132,22 @@ pub fn create_module() -> HashMap<String, Value> {
     // generate random integer in range
     module.insert(
         "randint".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() < 2 {
-                return Err(RutenError::RuntimeError("randint() takes 2 arguments: min, max".to_string()));
+                return Err(RutenError::runtime_error("randint() takes 2 arguments: min, max".to_string()));
             }
             
             match (&args[0], &args[1]) {
-                (Value::Number(min), Value::Number(max)) => {
+                (Value::Int(min), Value::Int(max)) => {
+                    let (min, max) = match (min.to_i64(), max.to_i64()) {
+                        (Some(min), Some(max)) if min <= max => (min, max),
+                        _ => return Err(RutenError::runtime_error("randint() requires min <= max".to_string())),
+                    };
                     use rand::Rng;
                     let mut rng = rand::thread_rng();
-                    let num = rng.gen_range((*min as i64)..=(*max as i64));
-                    Ok(Value::Number(num as f64))
+                    Ok(Value::Int(Int::from(rng.gen_range(min..=max))))
                 }
-                _ => Err(RutenError::TypeError("randint() requires two numbers".to_string())),
+                _ => Err(RutenError::type_error("randint() requires two integers".to_string())),
             }
         }),
     );
@@ -151,15 +155,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // choose random element from list
     module.insert(
         "choice".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("choice() takes 1 argument: list".to_string()));
+                return Err(RutenError::runtime_error("choice() takes 1 argument: list".to_string()));
             }
             
             match &args[0] {
                 Value::List(items) => {
+                    let items = items.borrow();
                     if items.is_empty() {
-                        return Err(RutenError::RuntimeError("choice() requires non-empty list".to_string()));
+                        return Err(RutenError::runtime_error("choice() requires non-empty list".to_string()));
                     }
                     
                     use rand::Rng;
@@ -167,7 +172,7 @@ pub fn create_module() -> HashMap<String, Value> {
                     let index = rng.gen_range(0..items.len());
                     Ok(items[index].clone())
                 }
-                _ => Err(RutenError::TypeError("choice() requires a list".to_string())),
+                _ => Err(RutenError::type_error("choice() requires a list".to_string())),
             }
         }),
     );
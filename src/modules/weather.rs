@@ -9,23 +9,23 @@ pub fn create_module() -> HashMap<String, Value> {
     // get current weather for a location
     module.insert(
         "current".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("current() takes 1 argument: location".to_string()));
+                return Err(RutenError::runtime_error("current() takes 1 argument: location".to_string()));
             }
             
             match &args[0] {
                 Value::String(location) => {
                     let url = format!("https://wttr.in/{}?format=j1", location);
                     let response = blocking::get(&url)
-                        .map_err(|e| RutenError::RuntimeError(format!("weather api error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("weather api error: {}", e)))?;
                     
                     let text = response.text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
                     
                     Ok(Value::String(text))
                 }
-                _ => Err(RutenError::TypeError("current() requires a string location".to_string())),
+                _ => Err(RutenError::type_error("current() requires a string location".to_string())),
             }
         }),
     );
@@ -33,23 +33,23 @@ pub fn create_module() -> HashMap<String, Value> {
     // get simple weather description
     module.insert(
         "simple".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("simple() takes 1 argument: location".to_string()));
+                return Err(RutenError::runtime_error("simple() takes 1 argument: location".to_string()));
             }
             
             match &args[0] {
                 Value::String(location) => {
                     let url = format!("https://wttr.in/{}?format=%C+%t", location);
                     let response = blocking::get(&url)
-                        .map_err(|e| RutenError::RuntimeError(format!("weather api error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("weather api error: {}", e)))?;
                     
                     let text = response.text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
                     
                     Ok(Value::String(text.trim().to_string()))
                 }
-                _ => Err(RutenError::TypeError("simple() requires a string location".to_string())),
+                _ => Err(RutenError::type_error("simple() requires a string location".to_string())),
             }
         }),
     );
@@ -57,23 +57,23 @@ pub fn create_module() -> HashMap<String, Value> {
     // get forecast for location
     module.insert(
         "forecast".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.is_empty() {
-                return Err(RutenError::RuntimeError("forecast() takes 1 argument: location".to_string()));
+                return Err(RutenError::runtime_error("forecast() takes 1 argument: location".to_string()));
             }
             
             match &args[0] {
                 Value::String(location) => {
                     let url = format!("https://wttr.in/{}?format=j1", location);
                     let response = blocking::get(&url)
-                        .map_err(|e| RutenError::RuntimeError(format!("weather api error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("weather api error: {}", e)))?;
                     
                     let text = response.text()
-                        .map_err(|e| RutenError::RuntimeError(format!("response error: {}", e)))?;
+                        .map_err(|e| RutenError::runtime_error(format!("response error: {}", e)))?;
                     
                     Ok(Value::String(text))
                 }
-                _ => Err(RutenError::TypeError("forecast() requires a string location".to_string())),
+                _ => Err(RutenError::type_error("forecast() requires a string location".to_string())),
             }
         }),
     );
@@ -1,36 +1,93 @@
+use crate::dict::{DictMap, Key};
 use crate::error::RutenError;
+use crate::int::Int;
 use crate::interpreter::Value;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use tokio::runtime::Runtime;
-use hyper::{Body, Request, Response, Server, StatusCode};
-use hyper::service::{make_service_fn, service_fn};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+// limits on what one client can make the server read or wait for
+const MAX_HEAD_BYTES: u64 = 64 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub fn create_module() -> HashMap<String, Value> {
     let mut module = HashMap::new();
 
-    // webhook.listen(port, handler_function)
+    // webhook.listen(port, handler_function, host="127.0.0.1")
     module.insert(
         "listen".to_string(),
-        Value::NativeFunction(|args| {
-            if args.len() != 2 {
-                return Err(RutenError::RuntimeError(
-                    "listen() takes 2 arguments: port and handler function".to_string()
+        Value::native(|ctx, args| {
+            if args.len() != 2 && args.len() != 3 {
+                return Err(RutenError::runtime_error(
+                    "listen() takes 2 or 3 arguments: port, handler function and optional host".to_string()
                 ));
             }
 
-            let port = match &args[0] {
-                Value::Number(n) => *n as u16,
-                _ => return Err(RutenError::TypeError(
+            let port = match args[0].as_f64() {
+                Some(n) => n as u16,
+                None => return Err(RutenError::type_error(
                     "listen() first argument must be a port number".to_string()
                 )),
             };
 
-            // note: in a real implementation, we would need to handle the function callback
-            // this is a simplified version that shows the structure only
-            println!("webhook server would start on port {}", port);
-            println!("note: full async webhook server requires runtime integration");
-            
+            // only reachable from this machine unless the script asks for more
+            let host = match args.get(2) {
+                Some(Value::String(host)) => host.as_str(),
+                Some(_) => return Err(RutenError::type_error(
+                    "listen() host must be a string".to_string()
+                )),
+                None => "127.0.0.1",
+            };
+
+            let handler = args[1].clone();
+            let listener = TcpListener::bind((host, port)).map_err(|e| {
+                RutenError::runtime_error(format!("listen() failed to bind {}:{}: {}", host, port, e))
+            })?;
+            println!("webhook server listening on {}:{}", host, port);
+
+            // serve one request at a time; the handler runs on the interpreter's thread
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                // a client that stalls gives up its turn instead of blocking the server
+                if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+
+                let (status, body) = match read_request(&mut stream) {
+                    Ok(request) => match ctx.call(&handler, vec![request])? {
+                        Value::Dict(fields) => {
+                            let fields = fields.borrow();
+                            let status = match fields.get(&Key::from("status")) {
+                                Some(status) => status.as_f64().map_or(200, |n| n as u16),
+                                None => 200,
+                            };
+                            let body = fields.get(&Key::from("body")).map(|b| b.to_string()).unwrap_or_default();
+                            (status, body)
+                        }
+                        Value::None => (204, String::new()),
+                        other => (200, other.to_string()),
+                    },
+                    Err(status) => (status, String::new()),
+                };
+
+                let reply = format!(
+                    "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    reason_phrase(status),
+                    body.len(),
+                    body
+                );
+                // a client that hung up early is not the script's problem
+                let _ = stream.write_all(reply.as_bytes());
+            }
+
             Ok(Value::None)
         }),
     );
@@ -38,16 +95,16 @@ pub fn create_module() -> HashMap<String, Value> {
     // webhook.respond(status, body)
     module.insert(
         "respond".to_string(),
-        Value::NativeFunction(|args| {
+        Value::native(|_, args| {
             if args.len() != 2 {
-                return Err(RutenError::RuntimeError(
+                return Err(RutenError::runtime_error(
                     "respond() takes 2 arguments: status code and body".to_string()
                 ));
             }
 
-            let status = match &args[0] {
-                Value::Number(n) => *n as u16,
-                _ => return Err(RutenError::TypeError(
+            let status = match args[0].as_f64() {
+                Some(n) => n as u16,
+                None => return Err(RutenError::type_error(
                     "respond() first argument must be a status code".to_string()
                 )),
             };
@@ -58,13 +115,79 @@ pub fn create_module() -> HashMap<String, Value> {
             };
 
             // create response dict
-            let mut response = HashMap::new();
-            response.insert("status".to_string(), Value::Number(status as f64));
-            response.insert("body".to_string(), Value::String(body));
+            let mut response = DictMap::new();
+            response.insert(Key::from("status"), Value::Int(Int::from(status as i64)));
+            response.insert(Key::from("body"), Value::String(body));
             
-            Ok(Value::Dict(response))
+            Ok(Value::dict(response))
         }),
     );
 
     module
-}
\ No newline at end of file
+}
+// parses an http request into the dict handed to the handler:
+// {"method": ..., "path": ..., "headers": {...}, "body": ...}
+// or gives the status to reply with when the request can't be served
+fn read_request(stream: &mut TcpStream) -> Result<Value, u16> {
+    const BAD_REQUEST: u16 = 400;
+    const TOO_LARGE: u16 = 413;
+
+    let mut reader = BufReader::new(Read::by_ref(stream).take(MAX_HEAD_BYTES));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|_| BAD_REQUEST)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or(BAD_REQUEST)?.to_string();
+    let path = parts.next().ok_or(BAD_REQUEST)?.to_string();
+
+    let mut headers = DictMap::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|_| BAD_REQUEST)? == 0 {
+            // the head ran past its limit, or the client hung up mid-head
+            return Err(if reader.get_ref().limit() == 0 { TOO_LARGE } else { BAD_REQUEST });
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_lowercase();
+            let value = value.trim().to_string();
+            if name == "content-length" {
+                content_length = value.parse().map_err(|_| BAD_REQUEST)?;
+            }
+            headers.insert(Key::from(name), Value::String(value));
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(TOO_LARGE);
+    }
+    reader.get_mut().set_limit(content_length as u64);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|_| BAD_REQUEST)?;
+
+    let mut request = DictMap::new();
+    request.insert(Key::from("method"), Value::String(method));
+    request.insert(Key::from("path"), Value::String(path));
+    request.insert(Key::from("headers"), Value::dict(headers));
+    request.insert(Key::from("body"), Value::String(String::from_utf8_lossy(&body).into_owned()));
+    Ok(Value::dict(request))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
@@ -0,0 +1,647 @@
+// value operations shared by the tree-walking interpreter and the bytecode vm,
+// so both backends agree on semantics and error messages
+
+use crate::dict::{DictMap, Key, KeySet};
+use crate::error::{ErrorKind, RutenError, Span};
+use crate::int::Int;
+use crate::interpreter::{iterate, resolve_index, type_name, CallContext, Caller, Value};
+use crate::methods;
+use crate::parser::{BinaryOp, Param, ParamKind, UnaryOp};
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+pub fn binary_op(left: &Value, op: &BinaryOp, right: &Value) -> Result<Value, RutenError> {
+    match (left, op, right) {
+        (item, BinaryOp::In, container) => return Ok(Value::Bool(contains(container, item)?)),
+        (item, BinaryOp::NotIn, container) => return Ok(Value::Bool(!contains(container, item)?)),
+        (l, BinaryOp::Equal, r) => return Ok(Value::Bool(values_equal(l, r))),
+        (l, BinaryOp::NotEqual, r) => return Ok(Value::Bool(!values_equal(l, r))),
+        (l, BinaryOp::And, r) => return Ok(Value::Bool(l.is_truthy() && r.is_truthy())),
+        (l, BinaryOp::Or, r) => return Ok(Value::Bool(l.is_truthy() || r.is_truthy())),
+        // the order `sorted` uses, so the two agree; NaN is in no order with anything
+        (l, BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual, r) => {
+            let holds = methods::partial_compare(l, r)?.is_some_and(|ordering| match op {
+                BinaryOp::Less => ordering.is_lt(),
+                BinaryOp::LessEqual => ordering.is_le(),
+                BinaryOp::Greater => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            });
+            return Ok(Value::Bool(holds));
+        }
+        (Value::Int(l), _, Value::Int(r)) => {
+            if let Some(result) = int_op(l, op, r)? {
+                return Ok(result);
+            }
+        }
+        // mixing an int with a float promotes the int
+        (Value::Int(_) | Value::Number(_), _, Value::Int(_) | Value::Number(_)) => {
+            if let Some(result) = float_op(to_float(left)?, op, to_float(right)?)? {
+                return Ok(result);
+            }
+        }
+        (Value::String(l), BinaryOp::Add, Value::String(r)) => {
+            return Ok(Value::String(format!("{}{}", l, r)))
+        }
+        (Value::Tuple(l), BinaryOp::Add, Value::Tuple(r)) => {
+            return Ok(Value::Tuple(l.iter().chain(r.iter()).cloned().collect()))
+        }
+        (Value::Set(l), BinaryOp::BitOr | BinaryOp::BitAnd | BinaryOp::BitXor | BinaryOp::Sub, Value::Set(r)) => {
+            let (l, r) = (l.borrow(), r.borrow());
+            let items: KeySet = match op {
+                BinaryOp::BitOr => l.union(&r).cloned().collect(),
+                BinaryOp::BitAnd => l.intersection(&r).cloned().collect(),
+                BinaryOp::BitXor => l.symmetric_difference(&r).cloned().collect(),
+                _ => l.difference(&r).cloned().collect(),
+            };
+            return Ok(Value::set(items));
+        }
+        _ => {}
+    }
+    Err(RutenError::type_error(format!(
+        "unsupported operation: {} {:?} {}",
+        left,
+        op,
+        right
+    )))
+}
+
+/// `a == b`: containers compare item by item, ints and floats by their exact values,
+/// and values of unrelated types are simply unequal
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    Equality::default().equal(a, b)
+}
+
+/// whether two dict keys or set items are the same one: `==`, except that NaN matches
+/// NaN, so a NaN key can be found again
+pub fn keys_equal(a: &Value, b: &Value) -> bool {
+    Equality {
+        nan_matches: true,
+        ..Equality::default()
+    }
+    .equal(a, b)
+}
+
+#[derive(Default)]
+struct Equality {
+    nan_matches: bool,
+    // the pairs of lists and dicts compared further out; meeting one again means both
+    // hold themselves the same way, so that part is taken as equal
+    comparing: Vec<(*const (), *const ())>,
+}
+
+impl Equality {
+    fn equal(&mut self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+                match compare_numbers(a, b) {
+                    Some(ordering) => ordering == Ordering::Equal,
+                    None => {
+                        self.nan_matches
+                            && matches!((a, b), (Value::Number(x), Value::Number(y)) if x.is_nan() && y.is_nan())
+                    }
+                }
+            }
+            (Value::String(x), Value::String(y)) => x == y,
+            (Value::Bool(x), Value::Bool(y)) => x == y,
+            (Value::None, Value::None) => true,
+            (Value::List(x), Value::List(y)) => {
+                Rc::ptr_eq(x, y)
+                    || self.nested(Rc::as_ptr(x) as *const (), Rc::as_ptr(y) as *const (), |this| {
+                        this.items_equal(&x.borrow(), &y.borrow())
+                    })
+            }
+            (Value::Tuple(x), Value::Tuple(y)) => self.items_equal(x, y),
+            (Value::Dict(x), Value::Dict(y)) => {
+                Rc::ptr_eq(x, y)
+                    || self.nested(Rc::as_ptr(x) as *const (), Rc::as_ptr(y) as *const (), |this| {
+                        let (x, y) = (x.borrow(), y.borrow());
+                        x.len() == y.len()
+                            && x.iter().all(|(key, value)| {
+                                y.get(key).is_some_and(|other| this.equal(value, other))
+                            })
+                    })
+            }
+            (Value::Set(x), Value::Set(y)) => {
+                let (x, y) = (x.borrow(), y.borrow());
+                x.len() == y.len() && x.iter().all(|item| y.contains(item))
+            }
+            (Value::Function(x), Value::Function(y)) => Rc::ptr_eq(x, y),
+            (Value::Closure(x), Value::Closure(y)) => Rc::ptr_eq(x, y),
+            (Value::NativeFunction(x), Value::NativeFunction(y)) => std::ptr::fn_addr_eq(x.call, y.call),
+            // the same method of the same object; shared containers are compared by identity
+            (
+                Value::BoundMethod { receiver: x, name: m },
+                Value::BoundMethod { receiver: y, name: n },
+            ) => {
+                m == n
+                    && match (&**x, &**y) {
+                        (Value::List(x), Value::List(y)) => Rc::ptr_eq(x, y),
+                        (Value::Dict(x), Value::Dict(y)) => Rc::ptr_eq(x, y),
+                        (Value::Set(x), Value::Set(y)) => Rc::ptr_eq(x, y),
+                        (x, y) => self.equal(x, y),
+                    }
+            }
+            (Value::Module(x), Value::Module(y)) => x.same(y),
+            (Value::ErrorType(x), Value::ErrorType(y)) => x == y,
+            _ => false,
+        }
+    }
+
+    // compares the insides of two containers with `compare`, unless they're already
+    // being compared further out
+    fn nested(&mut self, x: *const (), y: *const (), compare: impl FnOnce(&mut Self) -> bool) -> bool {
+        if self.comparing.contains(&(x, y)) {
+            return true;
+        }
+        self.comparing.push((x, y));
+        let result = compare(self);
+        self.comparing.pop();
+        result
+    }
+
+    fn items_equal(&mut self, x: &[Value], y: &[Value]) -> bool {
+        x.len() == y.len() && x.iter().zip(y).all(|(x, y)| self.equal(x, y))
+    }
+}
+
+/// how two numbers compare, exactly even between an int and a float; None when
+/// either isn't a number or is NaN
+pub fn compare_numbers(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::Int(x), Value::Number(y)) => x.cmp_f64(*y),
+        (Value::Number(x), Value::Int(y)) => y.cmp_f64(*x).map(Ordering::reverse),
+        (Value::Number(x), Value::Number(y)) => x.partial_cmp(y),
+        _ => None,
+    }
+}
+
+// a number as a float, for arithmetic mixing it with one; an int too large for a
+// float is an error rather than inf
+fn to_float(value: &Value) -> Result<f64, RutenError> {
+    match value {
+        Value::Int(n) => int_to_float(n),
+        other => Ok(other.as_f64().unwrap_or_default()),
+    }
+}
+
+fn int_to_float(n: &Int) -> Result<f64, RutenError> {
+    let f = n.to_f64();
+    if f.is_finite() {
+        Ok(f)
+    } else {
+        Err(RutenError::runtime_error("int too large to convert to float"))
+    }
+}
+
+/// `item in container`: a substring, an equal item, a dict key or a set member
+pub fn contains(container: &Value, item: &Value) -> Result<bool, RutenError> {
+    match container {
+        Value::String(s) => match item {
+            Value::String(sub) => Ok(s.contains(sub.as_str())),
+            other => Err(RutenError::type_error(format!(
+                "'in <string>' requires a string on the left, got {}",
+                type_name(other)
+            ))),
+        },
+        Value::List(items) => Ok(items.borrow().iter().any(|x| values_equal(x, item))),
+        Value::Tuple(items) => Ok(items.iter().any(|x| values_equal(x, item))),
+        Value::Dict(map) => Ok(map.borrow().contains_key(&Key::new(item.clone())?)),
+        Value::Set(items) => Ok(items.borrow().contains(&Key::new(item.clone())?)),
+        other => Err(RutenError::type_error(format!(
+            "'in' is not supported for {}",
+            type_name(other)
+        ))),
+    }
+}
+
+// ints stay ints, except that `/` and negative powers give floats
+fn int_op(l: &Int, op: &BinaryOp, r: &Int) -> Result<Option<Value>, RutenError> {
+    let result = match op {
+        BinaryOp::Add => Value::Int(l.add(r)),
+        BinaryOp::Sub => Value::Int(l.sub(r)),
+        BinaryOp::Mul => Value::Int(l.mul(r)),
+        BinaryOp::Div => {
+            let quotient = l.true_div(r).ok_or_else(division_by_zero)?;
+            if quotient.is_infinite() {
+                return Err(RutenError::runtime_error("int division result too large for a float"));
+            }
+            Value::Number(quotient)
+        }
+        BinaryOp::FloorDiv => Value::Int(l.floor_div(r).ok_or_else(division_by_zero)?),
+        BinaryOp::Mod => Value::Int(l.modulo(r).ok_or_else(modulo_by_zero)?),
+        BinaryOp::Pow if r.is_negative() => return float_op(int_to_float(l)?, op, int_to_float(r)?),
+        BinaryOp::Pow => {
+            let exponent = r
+                .to_i64()
+                .and_then(|e| u32::try_from(e).ok())
+                .ok_or_else(|| RutenError::runtime_error("exponent too large".to_string()))?;
+            Value::Int(l.pow(exponent))
+        }
+        // comparisons, like `and`, `or` and `in`, don't depend on the operand types
+        BinaryOp::Equal | BinaryOp::NotEqual => return Ok(None),
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => return Ok(None),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::In | BinaryOp::NotIn => return Ok(None),
+        BinaryOp::BitOr | BinaryOp::BitAnd | BinaryOp::BitXor => return Ok(None),
+    };
+    Ok(Some(result))
+}
+
+fn float_op(l: f64, op: &BinaryOp, r: f64) -> Result<Option<Value>, RutenError> {
+    let result = match op {
+        BinaryOp::Add => Value::Number(l + r),
+        BinaryOp::Sub => Value::Number(l - r),
+        BinaryOp::Mul => Value::Number(l * r),
+        BinaryOp::Div if r == 0.0 => return Err(division_by_zero()),
+        BinaryOp::Div => Value::Number(l / r),
+        BinaryOp::FloorDiv if r == 0.0 => return Err(division_by_zero()),
+        BinaryOp::FloorDiv => Value::Number((l / r).floor()),
+        BinaryOp::Mod if r == 0.0 => return Err(modulo_by_zero()),
+        BinaryOp::Mod => {
+            // like ints, the remainder takes the sign of the divisor
+            let m = l % r;
+            Value::Number(if m != 0.0 && (m < 0.0) != (r < 0.0) { m + r } else { m })
+        }
+        BinaryOp::Pow if l == 0.0 && r < 0.0 => return Err(division_by_zero()),
+        BinaryOp::Pow => Value::Number(l.powf(r)),
+        BinaryOp::Equal | BinaryOp::NotEqual => return Ok(None),
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => return Ok(None),
+        BinaryOp::And | BinaryOp::Or | BinaryOp::In | BinaryOp::NotIn => return Ok(None),
+        BinaryOp::BitOr | BinaryOp::BitAnd | BinaryOp::BitXor => return Ok(None),
+    };
+    Ok(Some(result))
+}
+
+fn division_by_zero() -> RutenError {
+    RutenError::runtime_error("division by zero".to_string())
+}
+
+fn modulo_by_zero() -> RutenError {
+    RutenError::runtime_error("modulo by zero".to_string())
+}
+
+pub fn unary_op(op: &UnaryOp, val: &Value) -> Result<Value, RutenError> {
+    match (op, val) {
+        (UnaryOp::Neg, Value::Number(n)) => Ok(Value::Number(-n)),
+        (UnaryOp::Neg, Value::Int(n)) => Ok(Value::Int(n.neg())),
+        (UnaryOp::Not, v) => Ok(Value::Bool(!v.is_truthy())),
+        _ => Err(RutenError::type_error(format!(
+            "unsupported unary operation: {:?} {}",
+            op,
+            val
+        ))),
+    }
+}
+
+/// `object[index]`
+pub fn get_index(object: Value, index: Value) -> Result<Value, RutenError> {
+    match (object, index) {
+        (Value::List(list), Value::Int(n)) => {
+            let list = list.borrow();
+            resolve_index(&n, list.len())
+                .map(|i| list[i].clone())
+                .ok_or_else(|| RutenError::runtime_error("list index out of range".to_string()))
+        }
+        (Value::Tuple(items), Value::Int(n)) => resolve_index(&n, items.len())
+            .map(|i| items[i].clone())
+            .ok_or_else(|| RutenError::runtime_error("tuple index out of range".to_string())),
+        (Value::Dict(dict), key) => {
+            let key = Key::new(key)?;
+            dict.borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| RutenError::runtime_error(format!("key not found: {}", key.value())))
+        }
+        (Value::String(s), Value::Int(n)) => {
+            resolve_index(&n, s.chars().count())
+                .and_then(|i| s.chars().nth(i))
+                .map(|c| Value::String(c.to_string()))
+                .ok_or_else(|| RutenError::runtime_error("string index out of range".to_string()))
+        }
+        _ => Err(RutenError::type_error("invalid index operation".to_string())),
+    }
+}
+
+/// `object[start:stop:step]` on a list, tuple or string, clamped like python's slices
+pub fn get_slice(object: Value, start: Value, stop: Value, step: Value) -> Result<Value, RutenError> {
+    let bound = |value: Value| match value {
+        Value::None => Ok(None),
+        // beyond i64 every bound clamps to one end anyway
+        Value::Int(n) => Ok(Some(n.to_i64().unwrap_or(if n.is_negative() { i64::MIN } else { i64::MAX }))),
+        other => Err(RutenError::type_error(format!(
+            "slice indices must be integers or None, got {}",
+            type_name(&other)
+        ))),
+    };
+    let (start, stop, step) = (bound(start)?, bound(stop)?, bound(step)?.unwrap_or(1));
+    if step == 0 {
+        return Err(RutenError::runtime_error("slice step cannot be zero".to_string()));
+    }
+    match object {
+        Value::List(list) => {
+            let list = list.borrow();
+            let items = slice_positions(list.len(), start, stop, step).map(|i| list[i].clone());
+            Ok(Value::list(items.collect()))
+        }
+        Value::Tuple(items) => {
+            let picked = slice_positions(items.len(), start, stop, step).map(|i| items[i].clone());
+            Ok(Value::tuple(picked.collect()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(Value::String(slice_positions(chars.len(), start, stop, step).map(|i| chars[i]).collect()))
+        }
+        other => Err(RutenError::type_error(format!("{} cannot be sliced", type_name(&other)))),
+    }
+}
+
+// the positions a slice visits in a sequence of `len` items; `step` is not zero
+fn slice_positions(len: usize, start: Option<i64>, stop: Option<i64>, step: i64) -> impl Iterator<Item = usize> {
+    let len = len as i64;
+    // a negative bound counts from the end; either way it is clamped to the sequence,
+    // where stepping backwards may stop just before the first item
+    let (low, high) = if step > 0 { (0, len) } else { (-1, len - 1) };
+    let clamp = |bound: i64| {
+        let bound = if bound < 0 { bound.saturating_add(len) } else { bound };
+        bound.clamp(low, high)
+    };
+    let start = start.map_or(if step > 0 { 0 } else { len - 1 }, clamp);
+    let stop = stop.map_or(if step > 0 { len } else { -1 }, clamp);
+    let mut position = start;
+    std::iter::from_fn(move || {
+        let inside = if step > 0 { position < stop } else { position > stop };
+        if !inside {
+            return None;
+        }
+        let current = position as usize;
+        position = position.saturating_add(step);
+        Some(current)
+    })
+}
+
+/// the items a `for` loop or comprehension visits
+pub fn iter_items(value: &Value) -> Result<Vec<Value>, RutenError> {
+    iterate(value).ok_or_else(|| RutenError::type_error(format!("{} is not iterable", type_name(value))))
+}
+
+/// the items of `value` for a destructuring assignment to `count` targets; the
+/// target at `star`, if any, takes a list of whatever the others leave over
+pub fn unpack(value: Value, count: usize, star: Option<usize>) -> Result<Vec<Value>, RutenError> {
+    let mut items = iterate(&value).ok_or_else(|| {
+        RutenError::type_error(format!("cannot unpack non-iterable {}", type_name(&value)))
+    })?;
+    match star {
+        Some(star) => {
+            let required = count - 1;
+            if items.len() < required {
+                return Err(RutenError::runtime_error(format!(
+                    "not enough values to unpack (expected at least {}, got {})",
+                    required,
+                    items.len()
+                )));
+            }
+            let rest: Vec<Value> = items.drain(star..items.len() - (required - star)).collect();
+            items.insert(star, Value::list(rest));
+            Ok(items)
+        }
+        None if items.len() < count => Err(RutenError::runtime_error(format!(
+            "not enough values to unpack (expected {}, got {})",
+            count,
+            items.len()
+        ))),
+        None if items.len() > count => Err(RutenError::runtime_error(format!(
+            "too many values to unpack (expected {})",
+            count
+        ))),
+        None => Ok(items),
+    }
+}
+
+/// `object[index] = value`
+pub fn set_index(object: Value, index: Value, value: Value) -> Result<(), RutenError> {
+    match (object, index) {
+        (Value::List(list), Value::Int(n)) => {
+            let mut list = list.borrow_mut();
+            let i = resolve_index(&n, list.len()).ok_or_else(|| {
+                RutenError::runtime_error("list assignment index out of range".to_string())
+            })?;
+            list[i] = value;
+            Ok(())
+        }
+        (Value::Dict(dict), key) => {
+            dict.borrow_mut().insert(Key::new(key)?, value);
+            Ok(())
+        }
+        (Value::Tuple(_), _) => Err(RutenError::type_error(
+            "tuple does not support item assignment".to_string(),
+        )),
+        (obj, _) => Err(RutenError::type_error(format!(
+            "cannot assign to an index of {}",
+            obj
+        ))),
+    }
+}
+
+/// `object.member`
+pub fn get_member(object: Value, member: &str) -> Result<Value, RutenError> {
+    match object {
+        Value::Module(module) => module
+            .get(member)
+            .ok_or_else(|| RutenError::name_error(format!("module has no member: {}", member))),
+        Value::Error(err) => match member {
+            "kind" => Ok(Value::String(err.kind.name().to_string())),
+            "message" => Ok(Value::String(err.message.clone())),
+            _ => Err(RutenError::name_error(format!("error has no member: {}", member))),
+        },
+        obj if methods::has_method(&obj, member) => Ok(Value::BoundMethod {
+            receiver: Box::new(obj),
+            name: member.to_string(),
+        }),
+        obj => Err(RutenError::name_error(format!("{} has no member: {}", type_name(&obj), member))),
+    }
+}
+
+/// `object.member = value`
+pub fn set_member(object: Value, member: &str, value: Value) -> Result<(), RutenError> {
+    match object {
+        Value::Module(module) => {
+            module.set(member, value);
+            Ok(())
+        }
+        obj => Err(RutenError::type_error(format!(
+            "cannot assign to member '{}' of {}",
+            member, obj
+        ))),
+    }
+}
+
+/// the error thrown by `raise value`
+pub fn raised_error(value: Value) -> Result<RutenError, RutenError> {
+    match value {
+        Value::Error(err) => Ok(*err),
+        Value::ErrorType(kind) => Ok(RutenError::new(kind, String::new())),
+        Value::String(message) => Ok(RutenError::runtime_error(message)),
+        other => Err(RutenError::type_error(format!(
+            "can only raise errors or strings, not {}",
+            other
+        ))),
+    }
+}
+
+/// whether an `except` clause naming `kind` catches `err`
+pub fn error_matches(kind: &Value, err: &RutenError) -> Result<bool, RutenError> {
+    match kind {
+        Value::ErrorType(kind) => Ok(*kind == err.kind),
+        other => Err(RutenError::type_error(format!(
+            "except clause expects an error type, got {}",
+            other
+        ))),
+    }
+}
+
+/// calls anything that is not a script function: natives, bound methods and error types
+pub fn call_builtin(
+    caller: &mut dyn Caller,
+    func: Value,
+    args: Vec<Value>,
+    kwargs: Vec<(String, Value)>,
+    call_site: Span,
+) -> Result<Value, RutenError> {
+    // only script functions and natives understand keyword arguments, and natives
+    // only the ones they declare
+    let keywords = match &func {
+        Value::NativeFunction(native) => native.keywords,
+        _ => &[],
+    };
+    if let Some((name, _)) = kwargs.iter().find(|(name, _)| !keywords.contains(&name.as_str())) {
+        return Err(RutenError::type_error(format!("unexpected keyword argument '{}'", name)));
+    }
+
+    match func {
+        Value::NativeFunction(native) => {
+            let mut ctx = CallContext::new(caller, call_site, kwargs);
+            (native.call)(&mut ctx, &args)
+        }
+        Value::BoundMethod { receiver, name } => methods::call_method(&receiver, &name, &args),
+        Value::ErrorType(kind) => make_error(kind, &args),
+        _ => Err(RutenError::type_error("not a callable object".to_string())),
+    }
+}
+
+// `RuntimeError("message")` creates an error value without raising it
+fn make_error(kind: ErrorKind, args: &[Value]) -> Result<Value, RutenError> {
+    let message = match args {
+        [] => String::new(),
+        [message] => message.to_string(),
+        _ => {
+            return Err(RutenError::runtime_error(format!(
+                "{}() takes at most 1 argument",
+                kind.name()
+            )))
+        }
+    };
+    Ok(Value::Error(Box::new(RutenError::new(kind, message))))
+}
+
+/// how deeply calls to script functions may nest before raising instead of overflowing
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// fails a call that would nest deeper than `MAX_CALL_DEPTH`, given the calls already active
+pub fn check_call_depth(depth: usize) -> Result<(), RutenError> {
+    if depth >= MAX_CALL_DEPTH {
+        return Err(RutenError::runtime_error("maximum recursion depth exceeded"));
+    }
+    Ok(())
+}
+
+/// matches call arguments to parameters and returns one value per parameter:
+/// positional arguments fill regular parameters in order, keywords fill them by name,
+/// and `*args` / `**kwargs` take the rest
+pub fn bind_args(
+    name: &str,
+    params: &[Param],
+    defaults: &[Option<Value>],
+    args: Vec<Value>,
+    kwargs: Vec<(String, Value)>,
+) -> Result<Vec<Value>, RutenError> {
+    // the common case: exactly one positional argument per plain parameter
+    if kwargs.is_empty()
+        && args.len() == params.len()
+        && params.iter().all(|p| p.kind == ParamKind::Regular)
+    {
+        return Ok(args);
+    }
+
+    let mut slots: Vec<Option<Value>> = vec![None; params.len()];
+
+    // parameters after `*args` can only be passed by keyword
+    let positional = params
+        .iter()
+        .take_while(|p| p.kind == ParamKind::Regular)
+        .count();
+    let var_args = params.iter().position(|p| p.kind == ParamKind::VarArgs);
+    let var_kwargs = params.iter().position(|p| p.kind == ParamKind::KwArgs);
+
+    let given = args.len();
+    let mut args = args.into_iter();
+    for slot in slots.iter_mut().take(positional) {
+        match args.next() {
+            Some(arg) => *slot = Some(arg),
+            None => break,
+        }
+    }
+    let extra: Vec<Value> = args.collect();
+    match var_args {
+        Some(index) => slots[index] = Some(Value::tuple(extra)),
+        None if !extra.is_empty() => {
+            return Err(RutenError::runtime_error(format!(
+                "{}() expects {} arguments, got {}",
+                name, positional, given
+            )))
+        }
+        None => {}
+    }
+
+    let mut extra_kwargs = DictMap::new();
+    for (key, value) in kwargs {
+        let target = params
+            .iter()
+            .position(|p| p.kind == ParamKind::Regular && p.name == key);
+        match target {
+            Some(index) if slots[index].is_some() => {
+                return Err(RutenError::type_error(format!(
+                    "{}() got multiple values for argument '{}'",
+                    name, key
+                )))
+            }
+            Some(index) => slots[index] = Some(value),
+            None if var_kwargs.is_some() => {
+                extra_kwargs.insert(Key::from(key), value);
+            }
+            None => {
+                return Err(RutenError::type_error(format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    name, key
+                )))
+            }
+        }
+    }
+    if let Some(index) = var_kwargs {
+        slots[index] = Some(Value::dict(extra_kwargs));
+    }
+
+    params
+        .iter()
+        .zip(defaults)
+        .zip(slots)
+        .map(|((param, default), slot)| {
+            slot.or_else(|| default.clone()).ok_or_else(|| {
+                RutenError::runtime_error(format!(
+                    "{}() missing required argument '{}'",
+                    name, param.name
+                ))
+            })
+        })
+        .collect()
+}
+
@@ -0,0 +1,271 @@
+// simplifies a parsed program before it runs: folds constant expressions and drops
+// code that can never execute; both backends run the result
+
+use crate::compiler;
+use crate::interpreter::Value;
+use crate::ops;
+use crate::parser::{
+    Arg, BinaryOp, ComprehensionClause, ComprehensionKind, Expr, ExprKind, FStringPart, Param, Program, Stmt,
+    StmtKind,
+};
+use std::rc::Rc;
+
+pub fn optimize(program: Program) -> Program {
+    block(program)
+}
+
+// code that never runs is only dropped when it binds no names: a function assigning a
+// name anywhere in its body still has it as a local, and `nonlocal` can refer to it
+fn block(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut out = Vec::with_capacity(stmts.len());
+    let mut stmts = stmts.into_iter();
+    while let Some(stmt) = stmts.next() {
+        let span = stmt.span;
+        match stmt.kind {
+            // a branch decided by a constant is replaced by whichever side runs
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = expr(condition);
+                match constant(&condition).map(|value| value.is_truthy()) {
+                    Some(true) if !else_branch.as_deref().is_some_and(compiler::binds_names) => {
+                        out.extend(block(then_branch))
+                    }
+                    Some(false) if !compiler::binds_names(&then_branch) => {
+                        out.extend(else_branch.map(block).unwrap_or_default())
+                    }
+                    _ => out.push(Stmt::new(
+                        StmtKind::If {
+                            condition,
+                            then_branch: block(then_branch),
+                            else_branch: else_branch.map(block),
+                        },
+                        span,
+                    )),
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let condition = expr(condition);
+                let never_runs = constant(&condition).is_some_and(|value| !value.is_truthy());
+                if never_runs && !compiler::binds_names(&body) {
+                    continue;
+                }
+                out.push(Stmt::new(
+                    StmtKind::While {
+                        condition,
+                        body: block(body),
+                    },
+                    span,
+                ));
+            }
+            kind => {
+                let ends_block = matches!(
+                    kind,
+                    StmtKind::Return(_) | StmtKind::Break | StmtKind::Continue | StmtKind::Raise(_)
+                );
+                out.push(Stmt::new(stmt_kind(kind), span));
+                // nothing after these runs
+                if ends_block && !compiler::binds_names(stmts.as_slice()) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+fn stmt_kind(kind: StmtKind) -> StmtKind {
+    match kind {
+        StmtKind::Assign { target, value } => StmtKind::Assign {
+            target: expr(target),
+            value: expr(value),
+        },
+        StmtKind::FunctionDef { name, params, body } => StmtKind::FunctionDef {
+            name,
+            params: self::params(&params),
+            body: shared_block(&body),
+        },
+        StmtKind::Return(value) => StmtKind::Return(value.map(expr)),
+        StmtKind::For { target, iterable, body } => StmtKind::For {
+            target,
+            iterable: expr(iterable),
+            body: block(body),
+        },
+        StmtKind::Try {
+            body,
+            handlers,
+            finally_body,
+        } => StmtKind::Try {
+            body: block(body),
+            handlers: handlers
+                .into_iter()
+                .map(|mut handler| {
+                    handler.kind = handler.kind.map(expr);
+                    handler.body = block(handler.body);
+                    handler
+                })
+                .collect(),
+            finally_body: finally_body.map(block),
+        },
+        StmtKind::Raise(value) => StmtKind::Raise(value.map(expr)),
+        StmtKind::Expression(value) => StmtKind::Expression(expr(value)),
+        other => other,
+    }
+}
+
+fn params(params: &Rc<[Param]>) -> Rc<[Param]> {
+    params
+        .iter()
+        .cloned()
+        .map(|mut param| {
+            param.default = param.default.map(expr);
+            param
+        })
+        .collect()
+}
+
+fn shared_block(body: &Rc<[Stmt]>) -> Rc<[Stmt]> {
+    Rc::from(block(body.to_vec()))
+}
+
+fn expr(e: Expr) -> Expr {
+    let span = e.span;
+    let kind = match e.kind {
+        ExprKind::Binary { left, op, right } => {
+            let left = expr(*left);
+            let right = expr(*right);
+            // operations that would fail are left for the program to report when it runs
+            let folded = match (constant(&left), constant(&right)) {
+                (Some(l), Some(r)) if !too_large(&l, &op, &r) => ops::binary_op(&l, &op, &r).ok().and_then(literal),
+                _ => None,
+            };
+            folded.unwrap_or(ExprKind::Binary {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            })
+        }
+        ExprKind::Unary { op, expr: operand } => {
+            let operand = expr(*operand);
+            let folded = constant(&operand)
+                .and_then(|value| ops::unary_op(&op, &value).ok())
+                .and_then(literal);
+            folded.unwrap_or(ExprKind::Unary {
+                op,
+                expr: Box::new(operand),
+            })
+        }
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: Box::new(expr(*callee)),
+            args: args
+                .into_iter()
+                .map(|arg| match arg {
+                    Arg::Positional(value) => Arg::Positional(expr(value)),
+                    Arg::Keyword(name, value) => Arg::Keyword(name, expr(value)),
+                })
+                .collect(),
+        },
+        ExprKind::FString(parts) => ExprKind::FString(
+            parts
+                .into_iter()
+                .map(|part| match part {
+                    FStringPart::Field(value, spec) => FStringPart::Field(expr(value), spec),
+                    literal => literal,
+                })
+                .collect(),
+        ),
+        ExprKind::List(elements) => ExprKind::List(elements.into_iter().map(expr).collect()),
+        ExprKind::Tuple(elements) => ExprKind::Tuple(elements.into_iter().map(expr).collect()),
+        ExprKind::Set(elements) => ExprKind::Set(elements.into_iter().map(expr).collect()),
+        ExprKind::Dict(pairs) => {
+            ExprKind::Dict(pairs.into_iter().map(|(k, v)| (expr(k), expr(v))).collect())
+        }
+        ExprKind::Index { object, index } => ExprKind::Index {
+            object: Box::new(expr(*object)),
+            index: Box::new(expr(*index)),
+        },
+        ExprKind::Slice {
+            object,
+            start,
+            stop,
+            step,
+        } => ExprKind::Slice {
+            object: Box::new(expr(*object)),
+            start: start.map(|bound| Box::new(expr(*bound))),
+            stop: stop.map(|bound| Box::new(expr(*bound))),
+            step: step.map(|bound| Box::new(expr(*bound))),
+        },
+        ExprKind::Member { object, member } => ExprKind::Member {
+            object: Box::new(expr(*object)),
+            member,
+        },
+        ExprKind::Lambda { params, body } => ExprKind::Lambda {
+            params: self::params(&params),
+            body: shared_block(&body),
+        },
+        ExprKind::Comprehension { kind, clauses } => ExprKind::Comprehension {
+            kind: match kind {
+                ComprehensionKind::List(element) => ComprehensionKind::List(Box::new(expr(*element))),
+                ComprehensionKind::Dict(key, value) => {
+                    ComprehensionKind::Dict(Box::new(expr(*key)), Box::new(expr(*value)))
+                }
+                ComprehensionKind::Set(element) => ComprehensionKind::Set(Box::new(expr(*element))),
+            },
+            clauses: clauses
+                .into_iter()
+                .map(|clause| match clause {
+                    ComprehensionClause::For { target, iterable } => ComprehensionClause::For {
+                        target,
+                        iterable: expr(iterable),
+                    },
+                    ComprehensionClause::If(condition) => ComprehensionClause::If(expr(condition)),
+                })
+                .collect(),
+        },
+        other => other,
+    };
+    Expr::new(kind, span)
+}
+
+// the most bits a folded int may take; anything bigger is computed if and when the code runs
+const MAX_FOLDED_BITS: u64 = 4096;
+
+// whether `l op r` could make an int too large to be worth computing ahead of time
+fn too_large(l: &Value, op: &BinaryOp, r: &Value) -> bool {
+    match (l, op, r) {
+        (Value::Int(base), BinaryOp::Pow, Value::Int(exponent)) => match exponent.to_i64() {
+            // 0, 1 and -1 stay small, and negative powers are floats
+            _ if base.bits() <= 1 || exponent.is_negative() => false,
+            Some(exponent) => base.bits().saturating_mul(exponent as u64) > MAX_FOLDED_BITS,
+            None => true,
+        },
+        (Value::Int(l), BinaryOp::Mul, Value::Int(r)) => l.bits() + r.bits() > MAX_FOLDED_BITS,
+        _ => false,
+    }
+}
+
+// the value of a literal expression
+fn constant(e: &Expr) -> Option<Value> {
+    match &e.kind {
+        ExprKind::Number(n) => Some(Value::Number(*n)),
+        ExprKind::Int(n) => Some(Value::Int(n.clone())),
+        ExprKind::String(s) => Some(Value::String(s.clone())),
+        ExprKind::Bool(b) => Some(Value::Bool(*b)),
+        ExprKind::None => Some(Value::None),
+        _ => None,
+    }
+}
+
+fn literal(value: Value) -> Option<ExprKind> {
+    match value {
+        // NaN has no literal, so an expression giving it stays as written
+        Value::Number(n) if !n.is_nan() => Some(ExprKind::Number(n)),
+        Value::Int(n) => Some(ExprKind::Int(n)),
+        Value::String(s) => Some(ExprKind::String(s)),
+        Value::Bool(b) => Some(ExprKind::Bool(b)),
+        Value::None => Some(ExprKind::None),
+        _ => None,
+    }
+}
@@ -1,10 +1,22 @@
-use crate::error::RutenError;
-use crate::lexer::Token;
+use crate::compiler::Names;
+use crate::error::{RutenError, Span};
+use crate::int::Int;
+use crate::lexer::{FStringChunk, SpannedToken, Token};
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
     Number(f64),
+    Int(Int),
     String(String),
+    /// `f"total: {x:.2f}"`
+    FString(Vec<FStringPart>),
     Bool(bool),
     None,
     Identifier(String),
@@ -19,54 +31,149 @@ pub enum Expr {
     },
     Call {
         callee: Box<Expr>,
-        args: Vec<Expr>,
+        args: Vec<Arg>,
     },
     List(Vec<Expr>),
+    /// `(a, b)`, or `a, b` where a statement allows it
+    Tuple(Vec<Expr>),
     Dict(Vec<(Expr, Expr)>),
+    /// `{a, b}`; `{}` is an empty dict
+    Set(Vec<Expr>),
+    /// `[x * 2 for x in xs if x]`, `{k: v for k, v in pairs}` or `{x for x in xs}`, run
+    /// in a scope of its own
+    Comprehension {
+        kind: ComprehensionKind,
+        clauses: Vec<ComprehensionClause>,
+    },
     Index {
         object: Box<Expr>,
         index: Box<Expr>,
     },
+    /// `object[start:stop:step]`, where any of the three may be left out
+    Slice {
+        object: Box<Expr>,
+        start: Option<Box<Expr>>,
+        stop: Option<Box<Expr>>,
+        step: Option<Box<Expr>>,
+    },
     Member {
         object: Box<Expr>,
         member: String,
     },
+    /// `*rest` in an assignment target, collecting the items no other target takes
+    Starred(Box<Expr>),
+    /// `lambda x, y: expr`; the body is the single statement `return expr`
+    Lambda {
+        params: Rc<[Param]>,
+        body: Rc<[Stmt]>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum FStringPart {
+    Literal(String),
+    /// a replacement field and its format spec, if any
+    Field(Expr, Option<String>),
+}
+
+/// what a comprehension builds, from the expressions it evaluates per item
+#[derive(Debug, Clone)]
+pub enum ComprehensionKind {
+    List(Box<Expr>),
+    Dict(Box<Expr>, Box<Expr>),
+    Set(Box<Expr>),
+}
+
+/// the `for` and `if` clauses of a comprehension, outermost first; the first is a `for`
+#[derive(Debug, Clone)]
+pub enum ComprehensionClause {
+    For { target: Expr, iterable: Expr },
+    If(Expr),
 }
 
+/// an argument at a call site: `f(x)` or `f(name=x)`
 #[derive(Debug, Clone)]
+pub enum Arg {
+    Positional(Expr),
+    Keyword(String, Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind,
+    /// evaluated once, when the function is defined
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Regular,
+    /// `*args` collects surplus positional arguments into a list
+    VarArgs,
+    /// `**kwargs` collects surplus keyword arguments into a dict
+    KwArgs,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum BinaryOp {
     Add,
     Sub,
     Mul,
     Div,
+    FloorDiv,
     Mod,
+    Pow,
     Equal,
     NotEqual,
     Less,
     LessEqual,
     Greater,
     GreaterEqual,
+    /// `x in container`, with the container on the right
+    In,
+    NotIn,
+    /// `|`, `&` and `^`: union, intersection and symmetric difference of sets
+    BitOr,
+    BitAnd,
+    BitXor,
     And,
     Or,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum UnaryOp {
     Neg,
     Not,
 }
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
-    Import(String),
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    /// `import module` or `import module as alias`
+    Import {
+        module: String,
+        alias: Option<String>,
+    },
+    /// `from module import name, other as alias`
+    FromImport {
+        module: String,
+        names: Vec<(String, Option<String>)>,
+    },
     Assign {
-        name: String,
+        target: Expr,
         value: Expr,
     },
+    // parameters and body are shared with every function value created from them
     FunctionDef {
         name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
+        params: Rc<[Param]>,
+        body: Rc<[Stmt]>,
     },
     Return(Option<Expr>),
     If {
@@ -79,25 +186,65 @@ pub enum Stmt {
         body: Vec<Stmt>,
     },
     For {
-        var: String,
+        target: Expr,
         iterable: Expr,
         body: Vec<Stmt>,
     },
+    Try {
+        body: Vec<Stmt>,
+        handlers: Vec<ExceptHandler>,
+        finally_body: Option<Vec<Stmt>>,
+    },
+    Raise(Option<Expr>),
+    /// `global a, b`: assignments in this function write the module's bindings
+    Global(Vec<String>),
+    /// `nonlocal a, b`: assignments in this function write an enclosing function's bindings
+    Nonlocal(Vec<String>),
     Break,
     Continue,
     Expression(Expr),
 }
 
+/// one `except [Kind] [as name]:` clause of a try statement
+#[derive(Debug, Clone)]
+pub struct ExceptHandler {
+    pub kind: Option<Expr>,
+    pub name: Option<String>,
+    pub body: Vec<Stmt>,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
 pub type Program = Vec<Stmt>;
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     current: usize,
+    // loops around the statement being parsed, within the innermost function
+    loops: usize,
+    // for each function being parsed, the `nonlocal` names in it and the functions
+    // nested in it that have yet to be found bound in an enclosing function
+    nonlocals: Vec<Vec<(String, Span)>>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+    fn new(tokens: Vec<SpannedToken>) -> Self {
+        Parser {
+            tokens,
+            current: 0,
+            loops: 0,
+            nonlocals: Vec::new(),
+        }
     }
 
     fn is_at_end(&self) -> bool {
@@ -105,14 +252,47 @@ impl Parser {
     }
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+        &self.tokens[self.current].token
+    }
+
+    fn peek_span(&self) -> Span {
+        self.tokens[self.current].span
+    }
+
+    fn peek_next(&self) -> &Token {
+        self.tokens.get(self.current + 1).map_or(&Token::Eof, |t| &t.token)
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1].token
+    }
+
+    // span from `start` up to the last consumed token, ignoring layout tokens
+    fn span_from(&self, start: Span) -> Span {
+        let mut end = self.current;
+        while end > 0
+            && matches!(
+                self.tokens[end - 1].token,
+                Token::Newline | Token::Indent | Token::Dedent
+            )
+        {
+            end -= 1;
+        }
+        match end {
+            0 => start,
+            _ => start.to(self.tokens[end - 1].span),
+        }
+    }
+
+    fn error(&self, message: &str) -> RutenError {
+        RutenError::syntax_error(message).with_span(self.peek_span())
     }
 
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
         }
-        &self.tokens[self.current - 1]
+        &self.tokens[self.current - 1].token
     }
 
     fn check(&self, token: &Token) -> bool {
@@ -150,10 +330,23 @@ impl Parser {
 
     fn parse_statement(&mut self) -> Result<Stmt, RutenError> {
         self.skip_newlines();
-        
+
+        if self.check(&Token::Indent) {
+            return Err(self.error("unexpected indent"));
+        }
+
+        let start = self.peek_span();
+        let kind = self.parse_statement_kind()?;
+        Ok(Stmt::new(kind, self.span_from(start)))
+    }
+
+    fn parse_statement_kind(&mut self) -> Result<StmtKind, RutenError> {
         if self.match_token(&[Token::Import]) {
             return self.parse_import();
         }
+        if self.match_token(&[Token::From]) {
+            return self.parse_from_import();
+        }
         if self.match_token(&[Token::Def]) {
             return self.parse_function_def();
         }
@@ -169,181 +362,421 @@ impl Parser {
         if self.match_token(&[Token::For]) {
             return self.parse_for();
         }
-        if self.match_token(&[Token::Break]) {
+        if self.match_token(&[Token::Try]) {
+            return self.parse_try();
+        }
+        if self.match_token(&[Token::Raise]) {
+            return self.parse_raise();
+        }
+        if self.match_token(&[Token::Global]) {
+            return Ok(StmtKind::Global(self.parse_names("global")?));
+        }
+        if self.check(&Token::Nonlocal) {
+            if self.nonlocals.is_empty() {
+                return Err(self.error("nonlocal declaration not allowed at module level"));
+            }
+            let start = self.peek_span();
+            self.advance();
+            let names = self.parse_names("nonlocal")?;
+            let span = self.span_from(start);
+            if let Some(pending) = self.nonlocals.last_mut() {
+                pending.extend(names.iter().map(|name| (name.clone(), span)));
+            }
+            return Ok(StmtKind::Nonlocal(names));
+        }
+        if self.check(&Token::Break) {
+            if self.loops == 0 {
+                return Err(self.error("'break' outside loop"));
+            }
+            self.advance();
             self.skip_newlines();
-            return Ok(Stmt::Break);
+            return Ok(StmtKind::Break);
         }
-        if self.match_token(&[Token::Continue]) {
+        if self.check(&Token::Continue) {
+            if self.loops == 0 {
+                return Err(self.error("'continue' outside loop"));
+            }
+            self.advance();
             self.skip_newlines();
-            return Ok(Stmt::Continue);
+            return Ok(StmtKind::Continue);
         }
 
-        // assignment or expression
-        let expr = self.parse_expression()?;
-        
-        if let Expr::Identifier(name) = &expr {
+        // assignment, when what comes first reads as a target followed by '='
+        let checkpoint = self.current;
+        if let Some(target) = self.parse_target_list() {
             if self.match_token(&[Token::Equal]) {
-                let value = self.parse_expression()?;
+                check_target(&target)?;
+                let value = self.parse_expression_list()?;
                 self.skip_newlines();
-                return Ok(Stmt::Assign {
-                    name: name.clone(),
-                    value,
-                });
+                return Ok(StmtKind::Assign { target, value });
             }
         }
-        
+        self.current = checkpoint;
+
+        let expr = self.parse_expression_list()?;
+        if self.check(&Token::Equal) {
+            return Err(RutenError::syntax_error("cannot assign to this expression").with_span(expr.span));
+        }
         self.skip_newlines();
-        Ok(Stmt::Expression(expr))
+        Ok(StmtKind::Expression(expr))
     }
 
-    fn parse_import(&mut self) -> Result<Stmt, RutenError> {
-        let module = if let Token::Identifier(module) = self.advance().clone() {
-            module
-        } else {
-            return Err(RutenError::SyntaxError("expected module name after 'import'".to_string()));
-        };
-        
+    fn parse_import(&mut self) -> Result<StmtKind, RutenError> {
+        let module = self.expect_identifier("expected module name after 'import'")?;
+        let alias = self.parse_alias()?;
+
         self.skip_newlines();
-        Ok(Stmt::Import(module))
+        Ok(StmtKind::Import { module, alias })
     }
 
-    fn parse_function_def(&mut self) -> Result<Stmt, RutenError> {
-        let name = if let Token::Identifier(n) = self.advance() {
-            n.clone()
+    fn parse_from_import(&mut self) -> Result<StmtKind, RutenError> {
+        let module = self.expect_identifier("expected module name after 'from'")?;
+        if !self.match_token(&[Token::Import]) {
+            return Err(self.error("expected 'import' after module name"));
+        }
+
+        // the list may be wrapped in parentheses to span several lines
+        let parenthesized = self.match_token(&[Token::LeftParen]);
+        let mut names = Vec::new();
+        loop {
+            let name = self.expect_identifier("expected name to import")?;
+            names.push((name, self.parse_alias()?));
+            if !self.match_token(&[Token::Comma]) {
+                break;
+            }
+            if parenthesized && self.check(&Token::RightParen) {
+                break;
+            }
+        }
+        if parenthesized && !self.match_token(&[Token::RightParen]) {
+            return Err(self.error("expected ')' after imported names"));
+        }
+
+        self.skip_newlines();
+        Ok(StmtKind::FromImport { module, names })
+    }
+
+    // an optional `as name`
+    fn parse_alias(&mut self) -> Result<Option<String>, RutenError> {
+        if self.match_token(&[Token::As]) {
+            Ok(Some(self.expect_identifier("expected name after 'as'")?))
         } else {
-            return Err(RutenError::SyntaxError("expected function name".to_string()));
-        };
+            Ok(None)
+        }
+    }
 
-        if !self.match_token(&[Token::LeftParen]) {
-            return Err(RutenError::SyntaxError("expected '(' after function name".to_string()));
+    fn expect_identifier(&mut self, message: &str) -> Result<String, RutenError> {
+        if let Token::Identifier(name) = self.peek().clone() {
+            self.advance();
+            Ok(name)
+        } else {
+            Err(self.error(message))
         }
+    }
 
-        let mut params = Vec::new();
-        if !self.check(&Token::RightParen) {
-            loop {
-                if let Token::Identifier(param) = self.advance() {
-                    params.push(param.clone());
-                } else {
-                    return Err(RutenError::SyntaxError("expected parameter name".to_string()));
-                }
-                if !self.match_token(&[Token::Comma]) {
-                    break;
-                }
-            }
+    fn parse_function_def(&mut self) -> Result<StmtKind, RutenError> {
+        let name = if let Token::Identifier(n) = self.peek().clone() {
+            self.advance();
+            n
+        } else {
+            return Err(self.error("expected function name"));
+        };
+
+        if !self.match_token(&[Token::LeftParen]) {
+            return Err(self.error("expected '(' after function name"));
         }
 
+        let params = self.parse_params(&Token::RightParen)?;
         if !self.match_token(&[Token::RightParen]) {
-            return Err(RutenError::SyntaxError("expected ')' after parameters".to_string()));
+            return Err(self.error("expected ')' after parameters"));
         }
 
         if !self.match_token(&[Token::Colon]) {
-            return Err(RutenError::SyntaxError("expected ':' after function signature".to_string()));
+            return Err(self.error("expected ':' after function signature"));
         }
 
-        self.skip_newlines();
-        let body = self.parse_block()?;
+        // loops around the definition don't reach into its body
+        let loops = std::mem::take(&mut self.loops);
+        self.nonlocals.push(Vec::new());
+        let body = self.parse_block();
+        self.loops = loops;
+        let pending = self.nonlocals.pop().unwrap_or_default();
+        let body = body?;
+        self.resolve_nonlocals(pending, &params, &body)?;
+
+        Ok(StmtKind::FunctionDef {
+            name,
+            params: params.into(),
+            body: body.into(),
+        })
+    }
+
+    // settles the `nonlocal` names of the functions nested in the one just parsed that
+    // it binds itself, and passes the rest, with its own, to the function around it;
+    // one bound by none of them is an error
+    fn resolve_nonlocals(
+        &mut self,
+        pending: Vec<(String, Span)>,
+        params: &[Param],
+        body: &[Stmt],
+    ) -> Result<(), RutenError> {
+        // a function's own `nonlocal` names aren't among its locals, so they pass outward too
+        let names = Names::of(params, body);
+        let mut unresolved = Vec::new();
+        for (name, span) in pending {
+            if names.globals.contains(&name) {
+                return Err(no_binding(&name, span));
+            }
+            if !names.locals.contains(&name) {
+                unresolved.push((name, span));
+            }
+        }
+        match self.nonlocals.last_mut() {
+            Some(outer) => outer.extend(unresolved),
+            None => {
+                if let Some((name, span)) = unresolved.into_iter().next() {
+                    return Err(no_binding(&name, span));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // comma-separated parameters, stopping before `end`:
+    // plain names, then `name=default`, then `*args`, keyword-only names and `**kwargs`
+    fn parse_params(&mut self, end: &Token) -> Result<Vec<Param>, RutenError> {
+        let mut params: Vec<Param> = Vec::new();
+        while !self.check(end) {
+            if params.iter().any(|p| p.kind == ParamKind::KwArgs) {
+                return Err(self.error("no parameters may follow '**' parameter"));
+            }
+
+            let kind = if self.match_token(&[Token::StarStar]) {
+                ParamKind::KwArgs
+            } else if self.match_token(&[Token::Star]) {
+                if params.iter().any(|p| p.kind == ParamKind::VarArgs) {
+                    return Err(self.error("only one '*' parameter is allowed"));
+                }
+                ParamKind::VarArgs
+            } else {
+                ParamKind::Regular
+            };
+
+            let name_span = self.peek_span();
+            let name = if let Token::Identifier(name) = self.peek().clone() {
+                self.advance();
+                name
+            } else {
+                return Err(self.error("expected parameter name"));
+            };
+            if params.iter().any(|p| p.name == name) {
+                return Err(RutenError::syntax_error(format!("duplicate parameter '{}'", name)).with_span(name_span));
+            }
+
+            let default = if kind == ParamKind::Regular && self.match_token(&[Token::Equal]) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
+            // keyword-only parameters after `*args` may go without defaults
+            let keyword_only = params.iter().any(|p| p.kind == ParamKind::VarArgs);
+            if kind == ParamKind::Regular
+                && default.is_none()
+                && !keyword_only
+                && params.iter().any(|p| p.default.is_some())
+            {
+                return Err(RutenError::syntax_error("non-default parameter follows default parameter")
+                    .with_span(name_span));
+            }
 
-        Ok(Stmt::FunctionDef { name, params, body })
+            params.push(Param { name, kind, default });
+            if !self.match_token(&[Token::Comma]) {
+                break;
+            }
+        }
+        Ok(params)
     }
 
-    fn parse_return(&mut self) -> Result<Stmt, RutenError> {
+    fn parse_return(&mut self) -> Result<StmtKind, RutenError> {
         if self.check(&Token::Newline) || self.is_at_end() {
             self.skip_newlines();
-            Ok(Stmt::Return(None))
+            Ok(StmtKind::Return(None))
         } else {
-            let expr = self.parse_expression()?;
+            let expr = self.parse_expression_list()?;
             self.skip_newlines();
-            Ok(Stmt::Return(Some(expr)))
+            Ok(StmtKind::Return(Some(expr)))
         }
     }
 
-    fn parse_if(&mut self) -> Result<Stmt, RutenError> {
+    fn parse_if(&mut self) -> Result<StmtKind, RutenError> {
         let condition = self.parse_expression()?;
         
         if !self.match_token(&[Token::Colon]) {
-            return Err(RutenError::SyntaxError("expected ':' after if condition".to_string()));
+            return Err(self.error("expected ':' after if condition"));
         }
 
-        self.skip_newlines();
         let then_branch = self.parse_block()?;
 
-        let else_branch = if self.match_token(&[Token::Else]) {
-            if !self.match_token(&[Token::Colon]) {
-                return Err(RutenError::SyntaxError("expected ':' after else".to_string()));
+        // `elif` and `else if` both become a nested if in the else branch
+        let start = self.peek_span();
+        let else_branch = if self.match_token(&[Token::Elif]) {
+            let elif = self.parse_if()?;
+            Some(vec![Stmt::new(elif, self.span_from(start))])
+        } else if self.match_token(&[Token::Else]) {
+            if self.match_token(&[Token::If]) {
+                let elif = self.parse_if()?;
+                Some(vec![Stmt::new(elif, self.span_from(start))])
+            } else {
+                if !self.match_token(&[Token::Colon]) {
+                    return Err(self.error("expected ':' after else"));
+                }
+                Some(self.parse_block()?)
             }
-            self.skip_newlines();
-            Some(self.parse_block()?)
         } else {
             None
         };
 
-        Ok(Stmt::If {
+        Ok(StmtKind::If {
             condition,
             then_branch,
             else_branch,
         })
     }
 
-    fn parse_while(&mut self) -> Result<Stmt, RutenError> {
+    fn parse_try(&mut self) -> Result<StmtKind, RutenError> {
+        if !self.match_token(&[Token::Colon]) {
+            return Err(self.error("expected ':' after try"));
+        }
+        let body = self.parse_block()?;
+
+        let mut handlers = Vec::new();
+        while self.match_token(&[Token::Except]) {
+            let kind = if self.check(&Token::Colon) || self.check(&Token::As) {
+                None
+            } else {
+                Some(self.parse_expression()?)
+            };
+
+            let name = if self.match_token(&[Token::As]) {
+                if let Token::Identifier(name) = self.peek().clone() {
+                    self.advance();
+                    Some(name)
+                } else {
+                    return Err(self.error("expected name after 'as'"));
+                }
+            } else {
+                None
+            };
+
+            if !self.match_token(&[Token::Colon]) {
+                return Err(self.error("expected ':' after except clause"));
+            }
+            let body = self.parse_block()?;
+            handlers.push(ExceptHandler { kind, name, body });
+        }
+
+        let finally_body = if self.match_token(&[Token::Finally]) {
+            if !self.match_token(&[Token::Colon]) {
+                return Err(self.error("expected ':' after finally"));
+            }
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+
+        if handlers.is_empty() && finally_body.is_none() {
+            return Err(self.error("expected 'except' or 'finally' after try block"));
+        }
+
+        Ok(StmtKind::Try {
+            body,
+            handlers,
+            finally_body,
+        })
+    }
+
+    fn parse_raise(&mut self) -> Result<StmtKind, RutenError> {
+        if self.check(&Token::Newline) || self.is_at_end() {
+            self.skip_newlines();
+            Ok(StmtKind::Raise(None))
+        } else {
+            let expr = self.parse_expression()?;
+            self.skip_newlines();
+            Ok(StmtKind::Raise(Some(expr)))
+        }
+    }
+
+    // the comma-separated names after `global` or `nonlocal`
+    fn parse_names(&mut self, keyword: &str) -> Result<Vec<String>, RutenError> {
+        let mut names = Vec::new();
+        loop {
+            names.push(self.expect_identifier(&format!("expected name after '{}'", keyword))?);
+            if !self.match_token(&[Token::Comma]) {
+                break;
+            }
+        }
+        self.skip_newlines();
+        Ok(names)
+    }
+
+    fn parse_while(&mut self) -> Result<StmtKind, RutenError> {
         let condition = self.parse_expression()?;
         
         if !self.match_token(&[Token::Colon]) {
-            return Err(RutenError::SyntaxError("expected ':' after while condition".to_string()));
+            return Err(self.error("expected ':' after while condition"));
         }
 
-        self.skip_newlines();
-        let body = self.parse_block()?;
+        let body = self.parse_loop_body()?;
 
-        Ok(Stmt::While { condition, body })
+        Ok(StmtKind::While { condition, body })
     }
 
-    fn parse_for(&mut self) -> Result<Stmt, RutenError> {
-        let var = if let Token::Identifier(v) = self.advance() {
-            v.clone()
-        } else {
-            return Err(RutenError::SyntaxError("expected variable name in for loop".to_string()));
+    fn parse_for(&mut self) -> Result<StmtKind, RutenError> {
+        let target = match self.parse_target_list() {
+            Some(target) => target,
+            None => return Err(self.error("expected variable name in for loop")),
         };
+        check_target(&target)?;
 
         if !self.match_token(&[Token::In]) {
-            return Err(RutenError::SyntaxError("expected 'in' in for loop".to_string()));
+            return Err(self.error("expected 'in' in for loop"));
         }
 
-        let iterable = self.parse_expression()?;
+        let iterable = self.parse_expression_list()?;
 
         if !self.match_token(&[Token::Colon]) {
-            return Err(RutenError::SyntaxError("expected ':' after for clause".to_string()));
+            return Err(self.error("expected ':' after for clause"));
         }
 
-        self.skip_newlines();
-        let body = self.parse_block()?;
+        let body = self.parse_loop_body()?;
 
-        Ok(Stmt::For { var, iterable, body })
+        Ok(StmtKind::For { target, iterable, body })
+    }
+
+    // the body of a loop, where `break` and `continue` are allowed
+    fn parse_loop_body(&mut self) -> Result<Vec<Stmt>, RutenError> {
+        self.loops += 1;
+        let body = self.parse_block();
+        self.loops -= 1;
+        body
     }
 
     fn parse_block(&mut self) -> Result<Vec<Stmt>, RutenError> {
+        // a body on the same line as its header, e.g. `if x: return 1`
+        if !self.match_token(&[Token::Newline]) {
+            return Ok(vec![self.parse_statement()?]);
+        }
+
+        if !self.match_token(&[Token::Indent]) {
+            return Err(self.error("expected an indented block"));
+        }
+
         let mut statements = Vec::new();
-        
-        // simple block parsing - collect statements until we hit a dedent or keyword
-        while !self.is_at_end() 
-            && !self.check(&Token::Else) 
-            && !self.check(&Token::Def)
-            && !self.check(&Token::If)
-            && !self.check(&Token::While)
-            && !self.check(&Token::For) {
-            
-            if self.check(&Token::Newline) {
-                self.advance();
-                continue;
-            }
-            
+        while !self.check(&Token::Dedent) && !self.is_at_end() {
             statements.push(self.parse_statement()?);
-            
-            // break on double newline or specific tokens
-            if statements.len() > 0 && (self.check(&Token::Else) || self.is_at_end()) {
-                break;
-            }
         }
-        
+        self.match_token(&[Token::Dedent]);
+
         Ok(statements)
     }
 
@@ -351,16 +784,102 @@ impl Parser {
         self.parse_or()
     }
 
+    // expressions separated by commas, as a tuple when there is more than one
+    // or a trailing comma
+    fn parse_expression_list(&mut self) -> Result<Expr, RutenError> {
+        let start = self.peek_span();
+        let first = self.parse_expression()?;
+        if !self.check(&Token::Comma) {
+            return Ok(first);
+        }
+        let mut items = vec![first];
+        while self.match_token(&[Token::Comma]) {
+            if self.ends_expression_list() {
+                break;
+            }
+            items.push(self.parse_expression()?);
+        }
+        Ok(Expr::new(ExprKind::Tuple(items), self.span_from(start)))
+    }
+
+    fn ends_expression_list(&self) -> bool {
+        self.is_at_end()
+            || matches!(
+                self.peek(),
+                Token::Newline | Token::Equal | Token::Colon | Token::RightParen | Token::RightBracket
+            )
+    }
+
+    // one or more assignment targets separated by commas; None when the tokens
+    // don't read as a target, so the caller can parse them as an expression instead
+    fn parse_target_list(&mut self) -> Option<Expr> {
+        let start = self.peek_span();
+        let first = self.parse_target()?;
+        if !self.check(&Token::Comma) {
+            return Some(first);
+        }
+        let mut items = vec![first];
+        while self.match_token(&[Token::Comma]) {
+            if self.ends_expression_list() || self.check(&Token::In) {
+                break;
+            }
+            items.push(self.parse_target()?);
+        }
+        Some(Expr::new(ExprKind::Tuple(items), self.span_from(start)))
+    }
+
+    fn parse_target(&mut self) -> Option<Expr> {
+        let start = self.peek_span();
+        if self.match_token(&[Token::Star]) {
+            let target = self.parse_target()?;
+            return Some(Expr::new(ExprKind::Starred(Box::new(target)), self.span_from(start)));
+        }
+        for (open, close) in [(Token::LeftParen, Token::RightParen), (Token::LeftBracket, Token::RightBracket)] {
+            if self.match_token(std::slice::from_ref(&open)) {
+                let items = if self.check(&close) {
+                    Vec::new()
+                } else {
+                    match self.parse_target_list()? {
+                        Expr {
+                            kind: ExprKind::Tuple(items),
+                            ..
+                        } => items,
+                        // `(a)` is just `a`, while `[a]` unpacks one item
+                        target if open == Token::LeftParen => {
+                            return self.match_token(&[close]).then_some(target);
+                        }
+                        target => vec![target],
+                    }
+                };
+                if !self.match_token(&[close]) {
+                    return None;
+                }
+                let kind = if open == Token::LeftParen { ExprKind::Tuple(items) } else { ExprKind::List(items) };
+                return Some(Expr::new(kind, self.span_from(start)));
+            }
+        }
+        let target = self.parse_postfix().ok()?;
+        matches!(
+            target.kind,
+            ExprKind::Identifier(_) | ExprKind::Index { .. } | ExprKind::Member { .. }
+        )
+        .then_some(target)
+    }
+
     fn parse_or(&mut self) -> Result<Expr, RutenError> {
         let mut expr = self.parse_and()?;
 
         while self.match_token(&[Token::Or]) {
             let right = self.parse_and()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op: BinaryOp::Or,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::Or,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
@@ -371,11 +890,15 @@ impl Parser {
 
         while self.match_token(&[Token::And]) {
             let right = self.parse_equality()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op: BinaryOp::And,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::And,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
@@ -385,39 +908,114 @@ impl Parser {
         let mut expr = self.parse_comparison()?;
 
         while self.match_token(&[Token::EqualEqual, Token::NotEqual]) {
-            let op = match self.tokens[self.current - 1] {
+            let op = match *self.previous() {
                 Token::EqualEqual => BinaryOp::Equal,
                 Token::NotEqual => BinaryOp::NotEqual,
                 _ => unreachable!(),
             };
             let right = self.parse_comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
     }
 
     fn parse_comparison(&mut self) -> Result<Expr, RutenError> {
-        let mut expr = self.parse_term()?;
+        let mut expr = self.parse_bit_or()?;
 
-        while self.match_token(&[Token::Less, Token::LessEqual, Token::Greater, Token::GreaterEqual]) {
-            let op = match self.tokens[self.current - 1] {
-                Token::Less => BinaryOp::Less,
-                Token::LessEqual => BinaryOp::LessEqual,
-                Token::Greater => BinaryOp::Greater,
-                Token::GreaterEqual => BinaryOp::GreaterEqual,
-                _ => unreachable!(),
+        loop {
+            let comparisons = [Token::Less, Token::LessEqual, Token::Greater, Token::GreaterEqual, Token::In];
+            let op = if self.match_token(&comparisons) {
+                match *self.previous() {
+                    Token::Less => BinaryOp::Less,
+                    Token::LessEqual => BinaryOp::LessEqual,
+                    Token::Greater => BinaryOp::Greater,
+                    Token::GreaterEqual => BinaryOp::GreaterEqual,
+                    Token::In => BinaryOp::In,
+                    _ => unreachable!(),
+                }
+            } else if self.check(&Token::Not) && self.peek_next() == &Token::In {
+                self.advance();
+                self.advance();
+                BinaryOp::NotIn
+            } else {
+                break;
             };
+            let right = self.parse_bit_or()?;
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op,
+                    right: Box::new(right),
+                },
+                span,
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_bit_or(&mut self) -> Result<Expr, RutenError> {
+        let mut expr = self.parse_bit_xor()?;
+
+        while self.match_token(&[Token::Pipe]) {
+            let right = self.parse_bit_xor()?;
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::BitOr,
+                    right: Box::new(right),
+                },
+                span,
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr, RutenError> {
+        let mut expr = self.parse_bit_and()?;
+
+        while self.match_token(&[Token::Caret]) {
+            let right = self.parse_bit_and()?;
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::BitXor,
+                    right: Box::new(right),
+                },
+                span,
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr, RutenError> {
+        let mut expr = self.parse_term()?;
+
+        while self.match_token(&[Token::Ampersand]) {
             let right = self.parse_term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::BitAnd,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
@@ -427,17 +1025,21 @@ impl Parser {
         let mut expr = self.parse_factor()?;
 
         while self.match_token(&[Token::Plus, Token::Minus]) {
-            let op = match self.tokens[self.current - 1] {
+            let op = match *self.previous() {
                 Token::Plus => BinaryOp::Add,
                 Token::Minus => BinaryOp::Sub,
                 _ => unreachable!(),
             };
             let right = self.parse_factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
@@ -446,39 +1048,68 @@ impl Parser {
     fn parse_factor(&mut self) -> Result<Expr, RutenError> {
         let mut expr = self.parse_unary()?;
 
-        while self.match_token(&[Token::Star, Token::Slash, Token::Percent]) {
-            let op = match self.tokens[self.current - 1] {
+        while self.match_token(&[Token::Star, Token::Slash, Token::SlashSlash, Token::Percent]) {
+            let op = match *self.previous() {
                 Token::Star => BinaryOp::Mul,
                 Token::Slash => BinaryOp::Div,
+                Token::SlashSlash => BinaryOp::FloorDiv,
                 Token::Percent => BinaryOp::Mod,
                 _ => unreachable!(),
             };
             let right = self.parse_unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op,
-                right: Box::new(right),
-            };
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprKind::Binary {
+                    left: Box::new(expr),
+                    op,
+                    right: Box::new(right),
+                },
+                span,
+            );
         }
 
         Ok(expr)
     }
 
     fn parse_unary(&mut self) -> Result<Expr, RutenError> {
+        let start = self.peek_span();
         if self.match_token(&[Token::Minus, Token::Not]) {
-            let op = match self.tokens[self.current - 1] {
+            let op = match *self.previous() {
                 Token::Minus => UnaryOp::Neg,
                 Token::Not => UnaryOp::Not,
                 _ => unreachable!(),
             };
             let expr = self.parse_unary()?;
-            return Ok(Expr::Unary {
-                op,
-                expr: Box::new(expr),
-            });
+            let span = start.to(expr.span);
+            return Ok(Expr::new(
+                ExprKind::Unary {
+                    op,
+                    expr: Box::new(expr),
+                },
+                span,
+            ));
         }
 
-        self.parse_postfix()
+        self.parse_power()
+    }
+
+    // `**` binds tighter than a unary minus on its left and groups to the right,
+    // so `-2 ** 2` is -4 and `2 ** 3 ** 2` is 2 ** 9
+    fn parse_power(&mut self) -> Result<Expr, RutenError> {
+        let base = self.parse_postfix()?;
+        if !self.match_token(&[Token::StarStar]) {
+            return Ok(base);
+        }
+        let exponent = self.parse_unary()?;
+        let span = base.span.to(exponent.span);
+        Ok(Expr::new(
+            ExprKind::Binary {
+                left: Box::new(base),
+                op: BinaryOp::Pow,
+                right: Box::new(exponent),
+            },
+            span,
+        ))
     }
 
     fn parse_postfix(&mut self) -> Result<Expr, RutenError> {
@@ -487,41 +1118,72 @@ impl Parser {
         loop {
             if self.match_token(&[Token::LeftParen]) {
                 // function call
-                let mut args = Vec::new();
-                if !self.check(&Token::RightParen) {
-                    loop {
-                        args.push(self.parse_expression()?);
-                        if !self.match_token(&[Token::Comma]) {
-                            break;
-                        }
-                    }
-                }
+                let args = self.parse_args()?;
                 if !self.match_token(&[Token::RightParen]) {
-                    return Err(RutenError::SyntaxError("expected ')' after arguments".to_string()));
+                    return Err(self.error("expected ')' after arguments"));
                 }
-                expr = Expr::Call {
-                    callee: Box::new(expr),
-                    args,
-                };
+                let span = self.span_from(expr.span);
+                expr = Expr::new(
+                    ExprKind::Call {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                    span,
+                );
             } else if self.match_token(&[Token::LeftBracket]) {
-                // indexing
-                let index = self.parse_expression()?;
-                if !self.match_token(&[Token::RightBracket]) {
-                    return Err(RutenError::SyntaxError("expected ']' after index".to_string()));
+                // indexing, or slicing once a ':' shows up
+                let start = self.parse_slice_bound()?;
+                if self.match_token(&[Token::Colon]) {
+                    let stop = self.parse_slice_bound()?;
+                    let step = if self.match_token(&[Token::Colon]) {
+                        self.parse_slice_bound()?
+                    } else {
+                        None
+                    };
+                    if !self.match_token(&[Token::RightBracket]) {
+                        return Err(self.error("expected ']' after slice"));
+                    }
+                    let span = self.span_from(expr.span);
+                    expr = Expr::new(
+                        ExprKind::Slice {
+                            object: Box::new(expr),
+                            start,
+                            stop,
+                            step,
+                        },
+                        span,
+                    );
+                    continue;
                 }
-                expr = Expr::Index {
-                    object: Box::new(expr),
-                    index: Box::new(index),
+                let index = match start {
+                    Some(index) => index,
+                    None => return Err(self.error("expected an index")),
                 };
+                if !self.match_token(&[Token::RightBracket]) {
+                    return Err(self.error("expected ']' after index"));
+                }
+                let span = self.span_from(expr.span);
+                expr = Expr::new(
+                    ExprKind::Index {
+                        object: Box::new(expr),
+                        index,
+                    },
+                    span,
+                );
             } else if self.match_token(&[Token::Dot]) {
                 // member access
-                if let Token::Identifier(member) = self.advance() {
-                    expr = Expr::Member {
-                        object: Box::new(expr),
-                        member: member.clone(),
-                    };
+                if let Token::Identifier(member) = self.peek().clone() {
+                    self.advance();
+                    let span = self.span_from(expr.span);
+                    expr = Expr::new(
+                        ExprKind::Member {
+                            object: Box::new(expr),
+                            member,
+                        },
+                        span,
+                    );
                 } else {
-                    return Err(RutenError::SyntaxError("expected member name after '.'".to_string()));
+                    return Err(self.error("expected member name after '.'"));
                 }
             } else {
                 break;
@@ -531,43 +1193,130 @@ impl Parser {
         Ok(expr)
     }
 
+    // one part of a slice, absent when the next token ends it
+    fn parse_slice_bound(&mut self) -> Result<Option<Box<Expr>>, RutenError> {
+        if self.check(&Token::Colon) || self.check(&Token::RightBracket) {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(self.parse_expression()?)))
+    }
+
+    // call arguments up to the closing ')'; keyword arguments come last
+    fn parse_args(&mut self) -> Result<Vec<Arg>, RutenError> {
+        let mut args = Vec::new();
+        while !self.check(&Token::RightParen) {
+            let is_keyword = matches!(self.peek(), Token::Identifier(_))
+                && matches!(self.tokens.get(self.current + 1).map(|t| &t.token), Some(Token::Equal));
+            if is_keyword {
+                let name = match self.advance().clone() {
+                    Token::Identifier(name) => name,
+                    _ => unreachable!(),
+                };
+                self.advance();
+                args.push(Arg::Keyword(name, self.parse_expression()?));
+            } else {
+                if args.iter().any(|a| matches!(a, Arg::Keyword(..))) {
+                    return Err(self.error("positional argument follows keyword argument"));
+                }
+                args.push(Arg::Positional(self.parse_expression()?));
+            }
+            if !self.match_token(&[Token::Comma]) {
+                break;
+            }
+        }
+        Ok(args)
+    }
+
     fn parse_primary(&mut self) -> Result<Expr, RutenError> {
+        let start = self.peek_span();
+
         if let Token::Number(n) = self.peek() {
             let num = *n;
             self.advance();
-            return Ok(Expr::Number(num));
+            return Ok(Expr::new(ExprKind::Number(num), start));
+        }
+
+        if let Token::Int(n) = self.peek() {
+            let int = n.clone();
+            self.advance();
+            return Ok(Expr::new(ExprKind::Int(int), start));
         }
 
         if let Token::String(s) = self.peek() {
             let string = s.clone();
             self.advance();
-            return Ok(Expr::String(string));
+            return Ok(Expr::new(ExprKind::String(string), start));
+        }
+
+        if let Token::FString(chunks) = self.peek() {
+            let chunks = chunks.clone();
+            self.advance();
+            let mut parts = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                parts.push(match chunk {
+                    FStringChunk::Literal(text) => FStringPart::Literal(text),
+                    FStringChunk::Field(tokens, spec) => {
+                        let mut field = Parser::new(tokens);
+                        let expr = field.parse_expression()?;
+                        if !matches!(field.peek(), Token::Newline | Token::Eof) {
+                            return Err(field.error("f-string: expecting '}'"));
+                        }
+                        FStringPart::Field(expr, spec)
+                    }
+                });
+            }
+            return Ok(Expr::new(ExprKind::FString(parts), start));
         }
 
         if self.match_token(&[Token::True]) {
-            return Ok(Expr::Bool(true));
+            return Ok(Expr::new(ExprKind::Bool(true), start));
         }
 
         if self.match_token(&[Token::False]) {
-            return Ok(Expr::Bool(false));
+            return Ok(Expr::new(ExprKind::Bool(false), start));
         }
 
         if self.match_token(&[Token::None]) {
-            return Ok(Expr::None);
+            return Ok(Expr::new(ExprKind::None, start));
         }
 
         if let Token::Identifier(name) = self.peek() {
             let ident = name.clone();
             self.advance();
-            return Ok(Expr::Identifier(ident));
+            return Ok(Expr::new(ExprKind::Identifier(ident), start));
+        }
+
+        if self.match_token(&[Token::Lambda]) {
+            let params = self.parse_params(&Token::Colon)?;
+            if !self.match_token(&[Token::Colon]) {
+                return Err(self.error("expected ':' after lambda parameters"));
+            }
+            let body = self.parse_expression()?;
+            let body_span = body.span;
+            let body = Stmt::new(StmtKind::Return(Some(body)), body_span);
+            let span = start.to(body_span);
+            return Ok(Expr::new(
+                ExprKind::Lambda {
+                    params: params.into(),
+                    body: Rc::new([body]),
+                },
+                span,
+            ));
         }
 
         if self.match_token(&[Token::LeftParen]) {
-            let expr = self.parse_expression()?;
+            if self.match_token(&[Token::RightParen]) {
+                return Ok(Expr::new(ExprKind::Tuple(Vec::new()), self.span_from(start)));
+            }
+            let expr = self.parse_expression_list()?;
             if !self.match_token(&[Token::RightParen]) {
-                return Err(RutenError::SyntaxError("expected ')' after expression".to_string()));
+                return Err(self.error("expected ')' after expression"));
             }
-            return Ok(expr);
+            // only a tuple's span takes in its parentheses
+            return Ok(match expr.kind {
+                ExprKind::Tuple(_) => Expr::new(expr.kind, self.span_from(start)),
+                _ => expr,
+            });
         }
 
         if self.match_token(&[Token::LeftBracket]) {
@@ -575,15 +1324,19 @@ impl Parser {
             if !self.check(&Token::RightBracket) {
                 loop {
                     elements.push(self.parse_expression()?);
+                    if elements.len() == 1 && self.check(&Token::For) {
+                        let kind = ComprehensionKind::List(Box::new(elements.remove(0)));
+                        return self.parse_comprehension(kind, Token::RightBracket, start);
+                    }
                     if !self.match_token(&[Token::Comma]) {
                         break;
                     }
                 }
             }
             if !self.match_token(&[Token::RightBracket]) {
-                return Err(RutenError::SyntaxError("expected ']' after list elements".to_string()));
+                return Err(self.error("expected ']' after list elements"));
             }
-            return Ok(Expr::List(elements));
+            return Ok(Expr::new(ExprKind::List(elements), self.span_from(start)));
         }
 
         if self.match_token(&[Token::LeftBrace]) {
@@ -591,10 +1344,18 @@ impl Parser {
             if !self.check(&Token::RightBrace) {
                 loop {
                     let key = self.parse_expression()?;
+                    // a first item without a value makes it a set
+                    if pairs.is_empty() && !self.check(&Token::Colon) {
+                        return self.parse_set(key, start);
+                    }
                     if !self.match_token(&[Token::Colon]) {
-                        return Err(RutenError::SyntaxError("expected ':' in dictionary".to_string()));
+                        return Err(self.error("expected ':' in dictionary"));
                     }
                     let value = self.parse_expression()?;
+                    if pairs.is_empty() && self.check(&Token::For) {
+                        let kind = ComprehensionKind::Dict(Box::new(key), Box::new(value));
+                        return self.parse_comprehension(kind, Token::RightBrace, start);
+                    }
                     pairs.push((key, value));
                     if !self.match_token(&[Token::Comma]) {
                         break;
@@ -602,16 +1363,89 @@ impl Parser {
                 }
             }
             if !self.match_token(&[Token::RightBrace]) {
-                return Err(RutenError::SyntaxError("expected '}' after dictionary".to_string()));
+                return Err(self.error("expected '}' after dictionary"));
+            }
+            return Ok(Expr::new(ExprKind::Dict(pairs), self.span_from(start)));
+        }
+
+        Err(self.error(&format!("unexpected token: {:?}", self.peek())))
+    }
+
+    // the rest of a set literal or comprehension, after its first item
+    fn parse_set(&mut self, first: Expr, start: Span) -> Result<Expr, RutenError> {
+        if self.check(&Token::For) {
+            return self.parse_comprehension(ComprehensionKind::Set(Box::new(first)), Token::RightBrace, start);
+        }
+        let mut elements = vec![first];
+        while self.match_token(&[Token::Comma]) {
+            elements.push(self.parse_expression()?);
+        }
+        if !self.match_token(&[Token::RightBrace]) {
+            return Err(self.error("expected '}' after set elements"));
+        }
+        Ok(Expr::new(ExprKind::Set(elements), self.span_from(start)))
+    }
+
+    // the clauses after a comprehension's element, up to the closing bracket
+    fn parse_comprehension(&mut self, kind: ComprehensionKind, close: Token, start: Span) -> Result<Expr, RutenError> {
+        let mut clauses = Vec::new();
+        loop {
+            if self.match_token(&[Token::For]) {
+                let target = match self.parse_target_list() {
+                    Some(target) => target,
+                    None => return Err(self.error("expected variable name in comprehension")),
+                };
+                check_target(&target)?;
+                if !self.match_token(&[Token::In]) {
+                    return Err(self.error("expected 'in' in comprehension"));
+                }
+                let iterable = self.parse_expression()?;
+                clauses.push(ComprehensionClause::For { target, iterable });
+            } else if self.match_token(&[Token::If]) {
+                clauses.push(ComprehensionClause::If(self.parse_expression()?));
+            } else {
+                break;
             }
-            return Ok(Expr::Dict(pairs));
         }
+        if !self.match_token(std::slice::from_ref(&close)) {
+            let bracket = if close == Token::RightBracket { ']' } else { '}' };
+            return Err(self.error(&format!("expected '{}' after comprehension", bracket)));
+        }
+        Ok(Expr::new(ExprKind::Comprehension { kind, clauses }, self.span_from(start)))
+    }
+}
 
-        Err(RutenError::SyntaxError(format!("unexpected token: {:?}", self.peek())))
+// rejects starred targets anywhere but once inside a tuple or list of targets
+fn check_target(target: &Expr) -> Result<(), RutenError> {
+    match &target.kind {
+        ExprKind::Starred(_) => Err(RutenError::syntax_error(
+            "starred assignment target must be in a list or tuple",
+        )
+        .with_span(target.span)),
+        ExprKind::Tuple(items) | ExprKind::List(items) => {
+            let starred = items.iter().filter(|item| matches!(item.kind, ExprKind::Starred(_)));
+            if starred.count() > 1 {
+                return Err(RutenError::syntax_error("multiple starred expressions in assignment")
+                    .with_span(target.span));
+            }
+            for item in items {
+                match &item.kind {
+                    ExprKind::Starred(inner) => check_target(inner)?,
+                    _ => check_target(item)?,
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Program, RutenError> {
+// the error for a `nonlocal` name no enclosing function binds
+fn no_binding(name: &str, span: Span) -> RutenError {
+    RutenError::syntax_error(format!("no binding for nonlocal '{}' found", name)).with_span(span)
+}
+
+pub fn parse(tokens: Vec<SpannedToken>) -> Result<Program, RutenError> {
     let mut parser = Parser::new(tokens);
     parser.parse_program()
 }
\ No newline at end of file
@@ -0,0 +1,298 @@
+// renders a syntax tree back as source, for `ruten --dump-ast`; every binary
+// operation is parenthesized so the tree's shape is visible
+
+use crate::parser::{
+    Arg, BinaryOp, ComprehensionClause, ComprehensionKind, Expr, ExprKind, FStringPart, Param, ParamKind, Program,
+    Stmt, StmtKind, UnaryOp,
+};
+
+pub fn print_program(program: &Program) -> String {
+    let mut out = String::new();
+    block(&mut out, program, 0);
+    out
+}
+
+fn block(out: &mut String, stmts: &[Stmt], depth: usize) {
+    if stmts.is_empty() {
+        line(out, depth, "pass");
+    }
+    for stmt in stmts {
+        self::stmt(out, stmt, depth);
+    }
+}
+
+fn line(out: &mut String, depth: usize, text: &str) {
+    out.push_str(&"    ".repeat(depth));
+    out.push_str(text);
+    out.push('\n');
+}
+
+fn stmt(out: &mut String, stmt: &Stmt, depth: usize) {
+    match &stmt.kind {
+        StmtKind::Import { module, alias } => match alias {
+            Some(alias) => line(out, depth, &format!("import {} as {}", module, alias)),
+            None => line(out, depth, &format!("import {}", module)),
+        },
+        StmtKind::FromImport { module, names } => {
+            let names: Vec<String> = names
+                .iter()
+                .map(|(name, alias)| match alias {
+                    Some(alias) => format!("{} as {}", name, alias),
+                    None => name.clone(),
+                })
+                .collect();
+            line(out, depth, &format!("from {} import {}", module, names.join(", ")));
+        }
+        StmtKind::Assign { target, value } => {
+            line(out, depth, &format!("{} = {}", expr(target), expr(value)))
+        }
+        StmtKind::FunctionDef { name, params, body } => {
+            line(out, depth, &format!("def {}({}):", name, self::params(params)));
+            block(out, body, depth + 1);
+        }
+        StmtKind::Return(value) => match value {
+            Some(value) => line(out, depth, &format!("return {}", expr(value))),
+            None => line(out, depth, "return"),
+        },
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            line(out, depth, &format!("if {}:", expr(condition)));
+            block(out, then_branch, depth + 1);
+            if let Some(else_branch) = else_branch {
+                line(out, depth, "else:");
+                block(out, else_branch, depth + 1);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            line(out, depth, &format!("while {}:", expr(condition)));
+            block(out, body, depth + 1);
+        }
+        StmtKind::For { target, iterable, body } => {
+            line(out, depth, &format!("for {} in {}:", expr(target), expr(iterable)));
+            block(out, body, depth + 1);
+        }
+        StmtKind::Try {
+            body,
+            handlers,
+            finally_body,
+        } => {
+            line(out, depth, "try:");
+            block(out, body, depth + 1);
+            for handler in handlers {
+                let header = match (&handler.kind, &handler.name) {
+                    (Some(kind), Some(name)) => format!("except {} as {}:", expr(kind), name),
+                    (Some(kind), None) => format!("except {}:", expr(kind)),
+                    _ => "except:".to_string(),
+                };
+                line(out, depth, &header);
+                block(out, &handler.body, depth + 1);
+            }
+            if let Some(finally_body) = finally_body {
+                line(out, depth, "finally:");
+                block(out, finally_body, depth + 1);
+            }
+        }
+        StmtKind::Raise(value) => match value {
+            Some(value) => line(out, depth, &format!("raise {}", expr(value))),
+            None => line(out, depth, "raise"),
+        },
+        StmtKind::Global(names) => line(out, depth, &format!("global {}", names.join(", "))),
+        StmtKind::Nonlocal(names) => line(out, depth, &format!("nonlocal {}", names.join(", "))),
+        StmtKind::Break => line(out, depth, "break"),
+        StmtKind::Continue => line(out, depth, "continue"),
+        StmtKind::Expression(value) => line(out, depth, &expr(value)),
+    }
+}
+
+fn params(params: &[Param]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| {
+            let name = match param.kind {
+                ParamKind::Regular => param.name.clone(),
+                ParamKind::VarArgs => format!("*{}", param.name),
+                ParamKind::KwArgs => format!("**{}", param.name),
+            };
+            match &param.default {
+                Some(default) => format!("{}={}", name, expr(default)),
+                None => name,
+            }
+        })
+        .collect();
+    params.join(", ")
+}
+
+// `text` as written between `quote`s, with braces doubled inside f-strings
+fn escape(text: &str, quote: char, braces: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '{' | '}' if braces => {
+                out.push(ch);
+                out.push(ch);
+            }
+            _ if ch == quote => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+// a float as source that reads back as a float: always with a point or an exponent,
+// and infinity as a literal too large to be anything else
+fn float_literal(n: f64) -> String {
+    match n {
+        f64::INFINITY => "1e400".to_string(),
+        f64::NEG_INFINITY => "-1e400".to_string(),
+        _ => format!("{:?}", n),
+    }
+}
+
+fn expr(e: &Expr) -> String {
+    match &e.kind {
+        ExprKind::Number(n) => float_literal(*n),
+        ExprKind::Int(n) => n.to_string(),
+        ExprKind::String(s) => format!("\"{}\"", escape(s, '"', false)),
+        ExprKind::FString(parts) => {
+            // fields are source code, so they go in as they are; the quotes around them
+            // are whichever kind the fields don't use
+            let uses_double = parts.iter().any(|part| match part {
+                FStringPart::Field(value, _) => expr(value).contains('"'),
+                FStringPart::Literal(_) => false,
+            });
+            let quote = if uses_double { '\'' } else { '"' };
+            let mut text = String::new();
+            for part in parts {
+                match part {
+                    FStringPart::Literal(literal) => text.push_str(&escape(literal, quote, true)),
+                    FStringPart::Field(value, Some(spec)) => text.push_str(&format!("{{{}:{}}}", expr(value), spec)),
+                    FStringPart::Field(value, None) => text.push_str(&format!("{{{}}}", expr(value))),
+                }
+            }
+            format!("f{}{}{}", quote, text, quote)
+        }
+        ExprKind::Bool(true) => "True".to_string(),
+        ExprKind::Bool(false) => "False".to_string(),
+        ExprKind::None => "None".to_string(),
+        ExprKind::Identifier(name) => name.clone(),
+        ExprKind::Binary { left, op, right } => {
+            format!("({} {} {})", expr(left), binary_op(op), expr(right))
+        }
+        ExprKind::Unary { op, expr: operand } => match op {
+            UnaryOp::Neg => format!("-{}", expr(operand)),
+            UnaryOp::Not => format!("not {}", expr(operand)),
+        },
+        ExprKind::Call { callee, args } => {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| match arg {
+                    Arg::Positional(value) => expr(value),
+                    Arg::Keyword(name, value) => format!("{}={}", name, expr(value)),
+                })
+                .collect();
+            format!("{}({})", expr(callee), args.join(", "))
+        }
+        ExprKind::List(elements) => {
+            let elements: Vec<String> = elements.iter().map(expr).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        ExprKind::Set(elements) => {
+            let elements: Vec<String> = elements.iter().map(expr).collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+        ExprKind::Tuple(elements) => {
+            let elements: Vec<String> = elements.iter().map(expr).collect();
+            match elements.len() {
+                1 => format!("({},)", elements[0]),
+                _ => format!("({})", elements.join(", ")),
+            }
+        }
+        ExprKind::Starred(inner) => format!("*{}", expr(inner)),
+        ExprKind::Dict(pairs) => {
+            let pairs: Vec<String> = pairs
+                .iter()
+                .map(|(key, value)| format!("{}: {}", expr(key), expr(value)))
+                .collect();
+            format!("{{{}}}", pairs.join(", "))
+        }
+        ExprKind::Index { object, index } => format!("{}[{}]", expr(object), expr(index)),
+        ExprKind::Slice {
+            object,
+            start,
+            stop,
+            step,
+        } => {
+            let bound = |bound: &Option<Box<Expr>>| bound.as_deref().map(expr).unwrap_or_default();
+            match step {
+                Some(_) => format!("{}[{}:{}:{}]", expr(object), bound(start), bound(stop), bound(step)),
+                None => format!("{}[{}:{}]", expr(object), bound(start), bound(stop)),
+            }
+        }
+        ExprKind::Member { object, member } => format!("{}.{}", expr(object), member),
+        ExprKind::Comprehension { kind, clauses } => {
+            let clauses: Vec<String> = clauses
+                .iter()
+                .map(|clause| match clause {
+                    ComprehensionClause::For { target, iterable } => {
+                        format!(" for {} in {}", expr(target), expr(iterable))
+                    }
+                    ComprehensionClause::If(condition) => format!(" if {}", expr(condition)),
+                })
+                .collect();
+            match kind {
+                ComprehensionKind::List(element) => format!("[{}{}]", expr(element), clauses.concat()),
+                ComprehensionKind::Dict(key, value) => {
+                    format!("{{{}: {}{}}}", expr(key), expr(value), clauses.concat())
+                }
+                ComprehensionKind::Set(element) => format!("{{{}{}}}", expr(element), clauses.concat()),
+            }
+        }
+        ExprKind::Lambda { params, body } => {
+            // a lambda's body is the single return the parser wraps its expression in
+            let body = match body.first().map(|stmt| &stmt.kind) {
+                Some(StmtKind::Return(Some(value))) => expr(value),
+                _ => "None".to_string(),
+            };
+            if params.is_empty() {
+                format!("(lambda: {})", body)
+            } else {
+                format!("(lambda {}: {})", self::params(params), body)
+            }
+        }
+    }
+}
+
+fn binary_op(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::FloorDiv => "//",
+        BinaryOp::Pow => "**",
+        BinaryOp::Mod => "%",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::In => "in",
+        BinaryOp::NotIn => "not in",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitXor => "^",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+    }
+}
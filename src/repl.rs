@@ -1,4 +1,4 @@
-use crate::{error::RutenError, interpreter::Interpreter, lexer, parser};
+use crate::{error::RutenError, interpreter::Interpreter, lexer, optimizer, parser};
 use colored::Colorize;
 use std::io::{self, Write};
 
@@ -44,14 +44,20 @@ pub fn start() {
                     continue;
                 }
 
+                // a block header keeps reading indented lines until a blank one
+                let mut source = input.to_string();
+                if source.ends_with(':') {
+                    read_block(&mut source);
+                }
+
                 // evaluate input
-                match eval_line(&mut interpreter, input) {
+                match eval_line(&mut interpreter, &source) {
                     Ok(Some(value)) => {
-                        println!("{}", format!("=> {}", value.to_string()).yellow());
+                        println!("{}", format!("=> {}", value).yellow());
                     }
                     Ok(None) => {}
                     Err(err) => {
-                        eprintln!("{}", format!("{}", err).red());
+                        eprintln!("{}", err.render("<repl>", &source).red());
                     }
                 }
 
@@ -65,16 +71,36 @@ pub fn start() {
     }
 }
 
+fn read_block(source: &mut String) {
+    loop {
+        print!("{} ", "...".green().bold());
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = line.trim_end();
+                if line.trim().is_empty() {
+                    break;
+                }
+                source.push('\n');
+                source.push_str(line);
+            }
+        }
+    }
+}
+
 fn eval_line(interpreter: &mut Interpreter, input: &str) -> Result<Option<crate::interpreter::Value>, RutenError> {
     // tokenize
     let tokens = lexer::tokenize(input)?;
 
     // parse
-    let ast = parser::parse(tokens)?;
+    let ast = optimizer::optimize(parser::parse(tokens)?);
 
     // check if it's a single expression statement
     if ast.len() == 1 {
-        if let crate::parser::Stmt::Expression(expr) = &ast[0] {
+        if let crate::parser::StmtKind::Expression(expr) = &ast[0].kind {
             let value = interpreter.eval_expr(expr)?;
             return Ok(Some(value));
         }
@@ -95,7 +121,7 @@ fn print_help() {
     println!("  - functions: {}", "def add(a, b): return a + b".yellow());
     println!("  - control flow: {}, {}, {}", "if".yellow(), "while".yellow(), "for".yellow());
     println!("  - data structures: {}, {}", "[1, 2, 3]".yellow(), "{\"key\": \"value\"}".yellow());
-    println!("  - modules: {}", "import math".yellow());
+    println!("  - modules: {} or {}", "import math".yellow(), "from json import parse".yellow());
     println!("\n{}", "available modules:".bold().cyan());
     println!("  {}", "core:".bold());
     println!("    math, strings, json, crypto, http, webhook");
@@ -113,7 +139,7 @@ fn print_help() {
 fn clear_screen() {
     if cfg!(target_os = "windows") {
         std::process::Command::new("cmd")
-            .args(&["/C", "cls"])
+            .args(["/C", "cls"])
             .status()
             .ok();
     } else {
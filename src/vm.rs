@@ -0,0 +1,687 @@
+// a stack machine running code from the compiler; selected with `ruten --vm`,
+// while the tree-walking interpreter stays the reference implementation
+
+use crate::builtins;
+use crate::compiler::{self, Code, Op, COMPREHENSION};
+use crate::error::{RutenError, Span, TraceFrame};
+use crate::format_spec;
+use crate::dict::{key_set, DictMap, Key};
+use crate::interpreter::{Caller, Module, Value};
+use crate::loader::{self, ModuleLoader};
+use crate::optimizer;
+use crate::ops;
+use crate::parser::{self, Program};
+use crate::lexer;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// a variable shared between a function and the functions nested in it; empty until assigned
+pub type Cell = Rc<RefCell<Option<Value>>>;
+type Globals = Rc<RefCell<ModuleGlobals>>;
+
+/// a module's top-level variables, each in the slot the compiler gave its name
+#[derive(Default)]
+pub struct ModuleGlobals {
+    names: Vec<String>,
+    values: Vec<Option<Value>>,
+    // the builtin each slot falls back to until the module assigns it
+    builtins: Vec<Option<Value>>,
+}
+
+impl ModuleGlobals {
+    fn new(names: &[String], builtins: &HashMap<String, Value>) -> Self {
+        ModuleGlobals {
+            names: names.to_vec(),
+            values: vec![None; names.len()],
+            builtins: names.iter().map(|name| builtins.get(name).cloned()).collect(),
+        }
+    }
+
+    fn get(&self, slot: usize) -> Option<Value> {
+        self.values[slot].as_ref().or(self.builtins[slot].as_ref()).cloned()
+    }
+
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// `module.name`, which unlike the module's own code doesn't fall back to builtins
+    pub fn member(&self, name: &str) -> Option<Value> {
+        self.values[self.slot(name)?].clone()
+    }
+
+    /// `module.name = value`; a name the module's code never uses gets a slot of its own
+    pub fn set_member(&mut self, name: &str, value: Value) {
+        match self.slot(name) {
+            Some(slot) => self.values[slot] = Some(value),
+            None => {
+                self.names.push(name.to_string());
+                self.values.push(Some(value));
+                self.builtins.push(None);
+            }
+        }
+    }
+}
+
+// functions in the slots can reach these globals
+impl fmt::Debug for ModuleGlobals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module>")
+    }
+}
+
+/// a compiled function together with what it captured when it was defined
+pub struct Closure {
+    code: Rc<Code>,
+    defaults: Vec<Option<Value>>,
+    cells: Vec<Cell>,
+    globals: Globals,
+}
+
+// cells can reach the closure holding them
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<function {}>", self.code.name)
+    }
+}
+
+// an active call; the module's own code runs in the bottom frame
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // stack height when the call started
+    base: usize,
+    locals: Vec<Option<Value>>,
+    cells: Vec<Cell>,
+    handlers: Vec<Handler>,
+    // errors being handled when the call started
+    handling: usize,
+    call_site: Span,
+}
+
+// an active `try`, where errors raised inside jump to
+struct Handler {
+    target: usize,
+    stack: usize,
+    handling: usize,
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    // active calls to script functions; comprehension frames don't count, since the
+    // tree-walker runs them inline
+    calls: usize,
+    // errors being handled by enclosing except blocks, for bare `raise`
+    handling: Vec<RutenError>,
+    builtins: Rc<HashMap<String, Value>>,
+    globals: Globals,
+    filename: String,
+    loader: Rc<RefCell<ModuleLoader>>,
+}
+
+impl Vm {
+    /// a vm running the script `filename`, which tracebacks refer to
+    pub fn with_filename(filename: &str) -> Self {
+        Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            calls: 0,
+            handling: Vec::new(),
+            builtins: Rc::new(builtins::create_builtins()),
+            globals: Rc::new(RefCell::new(ModuleGlobals::default())),
+            filename: filename.to_string(),
+            loader: Rc::new(RefCell::new(ModuleLoader::for_script(filename))),
+        }
+    }
+
+    /// the script's top-level bindings, which is what importing it exposes
+    pub fn exports(&self) -> Module {
+        Module::Globals(Rc::clone(&self.globals))
+    }
+
+    pub fn run_program(&mut self, program: &Program) -> Result<(), RutenError> {
+        let code = compiler::compile(program, &self.filename)?;
+        self.globals = Rc::new(RefCell::new(ModuleGlobals::new(&code.globals, &self.builtins)));
+        let closure = Rc::new(Closure {
+            code,
+            defaults: Vec::new(),
+            cells: Vec::new(),
+            globals: Rc::clone(&self.globals),
+        });
+        self.push_frame(closure, Vec::new(), Span::default());
+        self.run(0).map(|_| ())
+    }
+
+    fn push_frame(&mut self, closure: Rc<Closure>, args: Vec<Value>, call_site: Span) {
+        let code = &closure.code;
+        if self.is_call(code) {
+            self.calls += 1;
+        }
+        let mut locals: Vec<Option<Value>> = Vec::with_capacity(code.local_names.len());
+        locals.extend(args.into_iter().map(Some));
+        locals.resize(code.local_names.len(), None);
+
+        let mut cells = Vec::with_capacity(code.own_cells);
+        cells.resize_with(code.own_cells, || Rc::new(RefCell::new(None)));
+        for &(slot, cell) in &code.cell_params {
+            *cells[cell].borrow_mut() = locals[slot].clone();
+        }
+
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base: self.stack.len(),
+            locals,
+            cells,
+            handlers: Vec::new(),
+            handling: self.handling.len(),
+            call_site,
+        });
+    }
+
+    // leaves the innermost call
+    fn pop_frame(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        if self.is_call(&frame.closure.code) {
+            self.calls -= 1;
+        }
+        self.stack.truncate(frame.base);
+        self.handling.truncate(frame.handling);
+        release_cells(frame.cells);
+    }
+
+    // whether a frame running `code` counts towards the call depth limit
+    fn is_call(&self, code: &Code) -> bool {
+        !self.frames.is_empty() && code.name != COMPREHENSION
+    }
+
+    // runs until the frame above `depth` returns
+    fn run(&mut self, depth: usize) -> Result<Value, RutenError> {
+        loop {
+            let outcome = match self.step() {
+                Ok(None) => continue,
+                Ok(Some(value)) => {
+                    if self.frames.len() == depth {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            };
+            if let Err(err) = outcome {
+                self.unwind(err, depth)?;
+            }
+        }
+    }
+
+    // finds the handler for `err`, leaving frames until one has it; fails once the
+    // frame above `depth` is left
+    fn unwind(&mut self, mut err: RutenError, depth: usize) -> Result<(), RutenError> {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            err = err.with_span(frame.closure.code.spans[frame.ip - 1]);
+            if let Some(handler) = frame.handlers.pop() {
+                frame.ip = handler.target;
+                self.stack.truncate(handler.stack);
+                self.handling.truncate(handler.handling);
+                self.stack.push(Value::Error(Box::new(err)));
+                return Ok(());
+            }
+
+            let file = frame.closure.code.file.clone();
+            if self.frames.len() > 1 {
+                err = self.with_traceback(err.with_file(&file));
+            }
+            self.pop_frame();
+            if self.frames.len() == depth {
+                return Err(err);
+            }
+        }
+    }
+
+    // records the call stack on an error leaving the innermost function
+    fn with_traceback(&self, mut err: RutenError) -> RutenError {
+        let in_function = self.frames[1..].iter().any(|frame| frame.closure.code.name != COMPREHENSION);
+        if !err.traceback.is_empty() || !in_function {
+            return err;
+        }
+
+        let mut location = err.span;
+        for frame in self.frames[1..].iter().rev() {
+            // the tree-walker runs comprehensions inline, so neither backend shows them
+            if frame.closure.code.name == COMPREHENSION {
+                continue;
+            }
+            err.traceback.push(TraceFrame {
+                function: frame.closure.code.name.clone(),
+                file: frame.closure.code.file.clone(),
+                span: location,
+            });
+            location = Some(frame.call_site);
+        }
+        err.traceback.push(TraceFrame {
+            function: "<module>".to_string(),
+            file: self.filename.clone(),
+            span: location,
+        });
+        err
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("vm stack underflow")
+    }
+
+    // the value of a name that isn't bound locally, or not yet
+    fn global(&self, globals: &Globals, name: &str) -> Result<Value, RutenError> {
+        let globals = globals.borrow();
+        globals
+            .slot(name)
+            .and_then(|slot| globals.get(slot))
+            .or_else(|| self.builtins.get(name).cloned())
+            .ok_or_else(|| undefined(name))
+    }
+
+    // executes one op; returns the value of a call that just returned
+    fn step(&mut self) -> Result<Option<Value>, RutenError> {
+        let frame = self.frames.last_mut().unwrap();
+        let op = frame.closure.code.ops[frame.ip];
+        frame.ip += 1;
+
+        match op {
+            Op::Constant(index) => {
+                let value = frame.closure.code.constants[index].clone();
+                self.stack.push(value);
+            }
+            Op::LoadLocal(slot) => {
+                let value = match &frame.locals[slot] {
+                    Some(value) => value.clone(),
+                    None => {
+                        let closure = Rc::clone(&frame.closure);
+                        self.global(&closure.globals, &closure.code.local_names[slot])?
+                    }
+                };
+                self.stack.push(value);
+            }
+            Op::StoreLocal(slot) => {
+                let value = self.stack.pop();
+                self.frames.last_mut().unwrap().locals[slot] = value;
+            }
+            Op::LoadCell(index) => {
+                let value = frame_cell(frame, index).borrow().clone();
+                let value = match value {
+                    Some(value) => value,
+                    None => {
+                        let closure = Rc::clone(&frame.closure);
+                        self.global(&closure.globals, &closure.code.cell_names[index])?
+                    }
+                };
+                self.stack.push(value);
+            }
+            Op::StoreCell(index) => {
+                let cell = Rc::clone(frame_cell(frame, index));
+                *cell.borrow_mut() = Some(self.pop());
+            }
+            Op::LoadGlobal(slot) => {
+                let globals = frame.closure.globals.borrow();
+                let value = globals.get(slot).ok_or_else(|| undefined(&globals.names[slot]))?;
+                drop(globals);
+                self.stack.push(value);
+            }
+            Op::StoreGlobal(slot) => {
+                frame.closure.globals.borrow_mut().values[slot] = self.stack.pop();
+            }
+            Op::Pop => {
+                self.pop();
+            }
+            Op::Dup => {
+                let value = self.stack.last().cloned().expect("vm stack underflow");
+                self.stack.push(value);
+            }
+            Op::Binary(op) => {
+                let right = self.pop();
+                let left = self.pop();
+                self.stack.push(ops::binary_op(&left, &op, &right)?);
+            }
+            Op::Unary(op) => {
+                let value = self.pop();
+                self.stack.push(ops::unary_op(&op, &value)?);
+            }
+            Op::Jump(target) => frame.ip = target,
+            Op::JumpIfFalse(target) => {
+                if !self.pop().is_truthy() {
+                    self.frames.last_mut().unwrap().ip = target;
+                }
+            }
+            Op::BuildList(count) => {
+                let items = self.stack.split_off(self.stack.len() - count);
+                self.stack.push(Value::list(items));
+            }
+            Op::ListAppend(depth) => {
+                let value = self.pop();
+                match &self.stack[self.stack.len() - 1 - depth] {
+                    Value::List(items) => items.borrow_mut().push(value),
+                    _ => unreachable!(),
+                }
+            }
+            Op::DictInsert(depth) => {
+                let value = self.pop();
+                let key = self.pop();
+                let dict = self.stack[self.stack.len() - 1 - depth].clone();
+                ops::set_index(dict, key, value)?;
+            }
+            Op::SetAdd(depth) => {
+                let value = self.pop();
+                match &self.stack[self.stack.len() - 1 - depth] {
+                    Value::Set(items) => items.borrow_mut().insert(Key::new(value)?),
+                    _ => unreachable!(),
+                };
+            }
+            Op::BuildTuple(count) => {
+                let items = self.stack.split_off(self.stack.len() - count);
+                self.stack.push(Value::tuple(items));
+            }
+            Op::Unpack(count, star) => {
+                let value = self.pop();
+                let items = ops::unpack(value, count, star)?;
+                self.stack.extend(items.into_iter().rev());
+            }
+            Op::FormatValue(spec) => {
+                let closure = Rc::clone(&frame.closure);
+                let spec = spec.map(|index| match &closure.code.constants[index] {
+                    Value::String(spec) => spec.as_str(),
+                    _ => unreachable!(),
+                });
+                let value = self.pop();
+                self.stack.push(Value::String(format_spec::format_value(&value, spec)?));
+            }
+            Op::BuildString(count) => {
+                let parts = self.stack.split_off(self.stack.len() - count);
+                let text: String = parts.iter().map(|part| part.to_string()).collect();
+                self.stack.push(Value::String(text));
+            }
+            Op::BuildDict(count) => {
+                let items = self.stack.split_off(self.stack.len() - count * 2);
+                let mut map = DictMap::new();
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    map.insert(Key::new(key)?, value);
+                }
+                self.stack.push(Value::dict(map));
+            }
+            Op::BuildSet(count) => {
+                let items = self.stack.split_off(self.stack.len() - count);
+                self.stack.push(Value::set(key_set(items)?));
+            }
+            Op::Index => {
+                let index = self.pop();
+                let object = self.pop();
+                self.stack.push(ops::get_index(object, index)?);
+            }
+            Op::Slice => {
+                let step = self.pop();
+                let stop = self.pop();
+                let start = self.pop();
+                let object = self.pop();
+                self.stack.push(ops::get_slice(object, start, stop, step)?);
+            }
+            Op::StoreIndex => {
+                let index = self.pop();
+                let object = self.pop();
+                let value = self.pop();
+                ops::set_index(object, index, value)?;
+            }
+            Op::GetMember(index) => {
+                let closure = Rc::clone(&frame.closure);
+                let object = self.pop();
+                self.stack.push(ops::get_member(object, &closure.code.names[index])?);
+            }
+            Op::StoreMember(index) => {
+                let closure = Rc::clone(&frame.closure);
+                let object = self.pop();
+                let value = self.pop();
+                ops::set_member(object, &closure.code.names[index], value)?;
+            }
+            Op::Call(count) => {
+                let call_site = frame.closure.code.spans[frame.ip - 1];
+                let args = self.stack.split_off(self.stack.len() - count);
+                let func = self.pop();
+                return self.call(func, args, Vec::new(), call_site);
+            }
+            Op::CallKw(count, keywords) => {
+                let closure = Rc::clone(&frame.closure);
+                let call_site = closure.code.spans[frame.ip - 1];
+                let names = &closure.code.keywords[keywords];
+                let values = self.stack.split_off(self.stack.len() - names.len());
+                let args = self.stack.split_off(self.stack.len() - count);
+                let func = self.pop();
+                let kwargs = names.iter().cloned().zip(values).collect();
+                return self.call(func, args, kwargs, call_site);
+            }
+            Op::MakeFunction(index) => {
+                let code = Rc::clone(&frame.closure.code.functions[index]);
+                let cells = code
+                    .captures
+                    .iter()
+                    .map(|&cell| Rc::clone(frame_cell(frame, cell)))
+                    .collect();
+                let globals = Rc::clone(&frame.closure.globals);
+
+                let count = code.params.iter().filter(|p| p.default.is_some()).count();
+                let mut given = self.stack.split_off(self.stack.len() - count).into_iter();
+                let defaults = code
+                    .params
+                    .iter()
+                    .map(|p| p.default.as_ref().and_then(|_| given.next()))
+                    .collect();
+
+                self.stack.push(Value::Closure(Rc::new(Closure {
+                    code,
+                    defaults,
+                    cells,
+                    globals,
+                })));
+            }
+            Op::Return => {
+                let value = self.pop();
+                self.pop_frame();
+                return Ok(Some(value));
+            }
+            Op::GetIter => {
+                let iterable = self.pop();
+                let items = ops::iter_items(&iterable)?;
+                self.stack.push(Value::list(items));
+                self.stack.push(Value::Number(0.0));
+            }
+            Op::ForIter(exit) => {
+                let len = self.stack.len();
+                let position = match &self.stack[len - 1] {
+                    Value::Number(n) => *n as usize,
+                    _ => unreachable!(),
+                };
+                let item = match &self.stack[len - 2] {
+                    Value::List(items) => items.borrow().get(position).cloned(),
+                    _ => unreachable!(),
+                };
+                match item {
+                    Some(item) => {
+                        self.stack[len - 1] = Value::Number((position + 1) as f64);
+                        self.stack.push(item);
+                    }
+                    None => {
+                        self.stack.truncate(len - 2);
+                        self.frames.last_mut().unwrap().ip = exit;
+                    }
+                }
+            }
+            Op::SetupExcept(target) => {
+                let handler = Handler {
+                    target,
+                    stack: self.stack.len(),
+                    handling: self.handling.len(),
+                };
+                frame.handlers.push(handler);
+            }
+            Op::PopExcept => {
+                frame.handlers.pop();
+            }
+            Op::MatchError(target) => {
+                let kind = self.pop();
+                let matches = match self.stack.last() {
+                    Some(Value::Error(err)) => ops::error_matches(&kind, err)?,
+                    _ => unreachable!(),
+                };
+                if !matches {
+                    self.frames.last_mut().unwrap().ip = target;
+                }
+            }
+            Op::EnterHandler => match self.pop() {
+                Value::Error(err) => self.handling.push(*err),
+                _ => unreachable!(),
+            },
+            Op::ExitHandler => {
+                self.handling.pop();
+            }
+            Op::Raise => {
+                let value = self.pop();
+                return Err(ops::raised_error(value)?);
+            }
+            Op::RaiseCurrent => {
+                return Err(self.handling.last().cloned().ok_or_else(|| {
+                    RutenError::runtime_error("no active error to re-raise".to_string())
+                })?);
+            }
+            Op::ReRaise => match self.pop() {
+                Value::Error(err) => return Err(*err),
+                _ => unreachable!(),
+            },
+            Op::Import(index) => {
+                let closure = Rc::clone(&frame.closure);
+                let span = closure.code.spans[frame.ip - 1];
+                let module = self.import(&closure.code.names[index], span)?;
+                self.stack.push(module);
+            }
+            Op::ImportName(index, module_index) => {
+                let closure = Rc::clone(&frame.closure);
+                let name = &closure.code.names[index];
+                let member = match self.stack.last() {
+                    Some(Value::Module(members)) => members.get(name),
+                    _ => unreachable!(),
+                };
+                let member = member.ok_or_else(|| {
+                    RutenError::import_error(format!(
+                        "cannot import name '{}' from '{}'",
+                        name, closure.code.names[module_index]
+                    ))
+                })?;
+                self.stack.push(member);
+            }
+        }
+        Ok(None)
+    }
+
+    // script functions get a new frame, which the run loop carries on with;
+    // anything else is called right away
+    fn call(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+        call_site: Span,
+    ) -> Result<Option<Value>, RutenError> {
+        match func {
+            Value::Closure(closure) => {
+                if closure.code.name != COMPREHENSION {
+                    ops::check_call_depth(self.calls)?;
+                }
+                let args = ops::bind_args(
+                    &closure.code.name,
+                    &closure.code.params,
+                    &closure.defaults,
+                    args,
+                    kwargs,
+                )?;
+                self.push_frame(closure, args, call_site);
+                Ok(None)
+            }
+            Value::Function(_) => Err(RutenError::type_error(
+                "tree-walker functions cannot be called by the vm".to_string(),
+            )),
+            func => {
+                let value = ops::call_builtin(self, func, args, kwargs, call_site)?;
+                self.stack.push(value);
+                Ok(None)
+            }
+        }
+    }
+
+    fn import(&mut self, name: &str, span: Span) -> Result<Value, RutenError> {
+        let loader = Rc::clone(&self.loader);
+        loader::import(&self.loader, name, &self.filename, span, |file, source| {
+            let mut vm = Vm::with_filename(file);
+            vm.loader = loader;
+            lexer::tokenize(source)
+                .and_then(parser::parse)
+                .map(optimizer::optimize)
+                .and_then(|program| vm.run_program(&program))?;
+            Ok(vm.exports())
+        })
+    }
+}
+
+impl Caller for Vm {
+    fn call_value(
+        &mut self,
+        func: Value,
+        args: Vec<Value>,
+        kwargs: Vec<(String, Value)>,
+        call_site: Span,
+    ) -> Result<Value, RutenError> {
+        let depth = self.frames.len();
+        self.call(func, args, kwargs, call_site)?;
+        if self.frames.len() > depth {
+            // a script function: run it to completion before handing its value back
+            self.run(depth)
+        } else {
+            Ok(self.pop())
+        }
+    }
+}
+
+fn undefined(name: &str) -> RutenError {
+    RutenError::name_error(format!("undefined variable: {}", name))
+}
+
+// a finished call's cells are emptied when the only things still holding them are closures
+// stored in those same cells, so a nested function that refers to itself is freed
+fn release_cells(cells: Vec<Cell>) {
+    let unreachable = cells.iter().all(|cell| {
+        let from_own_closures: usize = cells
+            .iter()
+            .map(|holder| match &*holder.borrow() {
+                Some(Value::Closure(closure)) if Rc::strong_count(closure) == 1 => {
+                    closure.cells.iter().filter(|captured| Rc::ptr_eq(captured, cell)).count()
+                }
+                _ => 0,
+            })
+            .sum();
+        Rc::strong_count(cell) == 1 + from_own_closures
+    });
+    if unreachable {
+        for cell in &cells {
+            // dropped outside the borrow, since the closure may hold this cell
+            let value = cell.borrow_mut().take();
+            drop(value);
+        }
+    }
+}
+
+fn frame_cell(frame: &Frame, index: usize) -> &Cell {
+    match frame.cells.get(index) {
+        Some(cell) => cell,
+        None => &frame.closure.cells[index - frame.cells.len()],
+    }
+}
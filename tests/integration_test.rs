@@ -1,6 +1,24 @@
 // -- IGNORE ---
 // integration tests for ruten language
-use std::process::Command;
+#![allow(clippy::needless_borrows_for_generic_args)]
+use std::fs;
+use std::process::{Command, Output};
+
+// writes `source` to a temp script and runs it with the built binary
+fn run_script(name: &str, source: &str) -> Output {
+    let path = std::env::temp_dir().join(format!("ruten_test_{}.rtn", name));
+    fs::write(&path, source).expect("failed to write script");
+    let output = Command::new(env!("CARGO_BIN_EXE_ruten"))
+        .arg(&path)
+        .output()
+        .expect("failed to execute");
+    fs::remove_file(&path).ok();
+    output
+}
+
+fn stdout_of(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
 
 #[test]
 fn test_math_operations() {
@@ -25,4 +43,874 @@ fn test_repl_help() {
         .expect("failed to build");
     
     assert!(output.status.success());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_nested_blocks_follow_indentation() {
+    let output = run_script(
+        "nested_blocks",
+        r#"
+def classify(n):
+    if n > 0:
+        if n > 10:
+            return "big"
+        return "small"
+    return "negative"
+
+for i in [20, 5, -1]:
+    print(classify(i))
+print("done")
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "big\nsmall\nnegative\ndone\n");
+}
+
+#[test]
+fn test_inconsistent_indentation_is_rejected() {
+    let output = run_script("bad_indent", "if True:\n\tx = 1\n        y = 2\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("inconsistent use of tabs and spaces"));
+}
+
+#[test]
+fn test_runtime_error_points_at_source() {
+    let output = run_script("error_snippet", "xs = [1, 2, 3]\nprint(xs[10])\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("runtime error: list index out of range"));
+    assert!(stderr.contains("ruten_test_error_snippet.rtn:2:7"));
+    assert!(stderr.contains("2 | print(xs[10])\n  |       ^^^^^^"));
+}
+
+#[test]
+fn test_runtime_error_reports_traceback() {
+    let output = run_script(
+        "traceback",
+        r#"
+def fetch_prices(xs):
+    return xs[10]
+
+def main():
+    return fetch_prices([1, 2])
+
+main()
+"#,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("in fetch_prices (") && stderr.contains("ruten_test_traceback.rtn:3)"));
+    assert!(stderr.contains("called from main (") && stderr.contains("ruten_test_traceback.rtn:6)"));
+    assert!(stderr.contains("called from <module> ("));
+}
+
+#[test]
+fn test_elif_chain_evaluates_lazily() {
+    let output = run_script(
+        "elif_chain",
+        r#"
+def check(label, result):
+    print("checked " + label)
+    return result
+
+if check("a", False):
+    print("first")
+elif check("b", True):
+    print("second")
+else if check("c", True):
+    print("third")
+else:
+    print("fallback")
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "checked a\nchecked b\nsecond\n");
+}
+
+#[test]
+fn test_try_except_finally_and_raise() {
+    let output = run_script(
+        "try_except",
+        r#"
+import json
+
+def parse_or_default(text):
+    try:
+        return json.parse(text)
+    except RuntimeError as e:
+        print(e.kind + ": caught")
+        return None
+    finally:
+        print("finally")
+
+print(parse_or_default("{bad"))
+
+try:
+    raise TypeError("custom")
+except NameError:
+    print("wrong handler")
+except TypeError as e:
+    print(e.message)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "RuntimeError: caught\nfinally\nNone\ncustom\n"
+    );
+}
+
+#[test]
+fn test_uncaught_raise_reports_kind() {
+    let output = run_script("uncaught_raise", "raise NameError(\"missing\")\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("name error: missing"));
+}
+
+#[test]
+fn test_containers_are_shared_and_index_assignable() {
+    let output = run_script(
+        "shared_containers",
+        r#"
+def mark(cells, i):
+    cells[i] = "x"
+
+board = ["-", "-", "-"]
+alias = board
+mark(board, 0)
+alias[-1] = "o"
+print(board)
+
+counts = {"a": 1}
+counts["b"] = 2
+counts["a"] = counts["a"] + 1
+print(counts["a"], counts["b"])
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "['x', '-', 'o']\n2 2\n");
+}
+
+#[test]
+fn test_containers_holding_themselves() {
+    let output = run_script(
+        "self_containing",
+        r#"
+xs = [1, 2]
+xs.append(xs)
+d = {"a": 1}
+d["self"] = d
+print(xs, d)
+
+ys = [1, 2]
+ys.append(ys)
+print(xs == ys, xs == [1, 3, xs])
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "[1, 2, [...]] {'a': 1, 'self': {...}}\ntrue false\n");
+}
+
+#[test]
+fn test_builtin_methods_on_values() {
+    let output = run_script(
+        "builtin_methods",
+        r#"
+print("  Hello World ".strip().lower().split())
+print("-".join(["a", "b", "c"]), "banana".find("na"))
+
+xs = [3, 1, 2]
+xs.append(0)
+xs.sort()
+last = xs.pop()
+print(xs, last)
+
+d = {"a": 1}
+d.update({"b": 2})
+print(d.get("b"), d.get("c", "none"))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "['hello', 'world']\na-b-c 2\n[0, 1, 2] 3\n2 none\n"
+    );
+}
+
+#[test]
+fn test_higher_order_builtins_call_script_functions() {
+    let output = run_script(
+        "higher_order",
+        r#"
+import multiproc
+
+def square(x):
+    return x * x
+
+def is_odd(x):
+    return x % 2 == 1
+
+def add(a, b):
+    return a + b
+
+def negate(x):
+    return -x
+
+print(map(square, [1, 2, 3]), filter(is_odd, range(6)))
+print(reduce(add, [1, 2, 3]), reduce(add, [], 10))
+print(sorted([3, 1, 2]), sorted([3, 1, 2], negate))
+print(multiproc.parallel(square, [4, 5]))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "[1, 4, 9] [1, 3, 5]\n6 10\n[1, 2, 3] [3, 2, 1]\n[16, 25]\n"
+    );
+}
+
+#[test]
+fn test_lambdas_capture_their_environment() {
+    let output = run_script(
+        "lambdas",
+        r#"
+def adder(n):
+    return lambda x: x + n
+
+add_five = adder(5)
+print(add_five(1), map(lambda x: x * 2, [1, 2, 3]))
+print(sorted(["ccc", "a", "bb"], lambda s: len(s)))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "6 [2, 4, 6]\n['a', 'bb', 'ccc']\n");
+}
+
+#[test]
+fn test_default_keyword_and_variadic_parameters() {
+    let output = run_script(
+        "parameters",
+        r#"
+def describe(name, greeting="hello", *rest, sep=" ", **extra):
+    return [greeting + sep + name, rest, extra]
+
+print(describe("ann"))
+print(describe("bob", "hi", 1, 2, sep="-"))
+print(describe(greeting="yo", name="cy", mood="ok"))
+print(sorted([1, 3, 2], key=lambda x: -x))
+
+try:
+    describe("dee", greeting="a", name="b")
+except TypeError as e:
+    print(e.message)
+
+try:
+    print("never shown", colour="red")
+except TypeError as e:
+    print(e.message)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "['hello ann', (), {}]\n['hi-bob', (1, 2), {}]\n['yo cy', (), {'mood': 'ok'}]\n[3, 2, 1]\n\
+         describe() got multiple values for argument 'name'\nunexpected keyword argument 'colour'\n"
+    );
+}
+
+#[test]
+fn test_http_rejects_out_of_range_timeouts() {
+    let output = run_script(
+        "http_timeout",
+        r#"
+import http
+
+for timeout in [1e400, 2 ** 2000, -1]:
+    try:
+        http.get("http://127.0.0.1:1/", timeout=timeout)
+    except RuntimeError as e:
+        print(e.message)
+    except TypeError as e:
+        print(e.message)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "timeout is too large\ntimeout is too large\n\
+         timeout must be a positive number of seconds, got -1\n"
+    );
+}
+
+#[test]
+fn test_import_script_module_once() {
+    // lives next to the script, which is where imports look first
+    let module = std::env::temp_dir().join("ruten_test_helpers.rtn");
+    fs::write(
+        &module,
+        r#"
+print("loading helpers")
+SCALE = 3
+
+def scaled(x):
+    return x * SCALE
+"#,
+    )
+    .expect("failed to write module");
+
+    let output = run_script(
+        "import_module",
+        r#"
+import ruten_test_helpers
+import ruten_test_helpers
+print(ruten_test_helpers.scaled(2), ruten_test_helpers.SCALE)
+"#,
+    );
+    fs::remove_file(&module).ok();
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "loading helpers\n6 3\n");
+}
+
+#[test]
+fn test_module_attributes_stay_live() {
+    let module = std::env::temp_dir().join("ruten_test_counter.rtn");
+    fs::write(
+        &module,
+        r#"
+count = 0
+value = 1
+
+def bump():
+    global count
+    count = count + 1
+
+def get():
+    return value
+"#,
+    )
+    .expect("failed to write module");
+    let script = std::env::temp_dir().join("ruten_test_live_module.rtn");
+    fs::write(
+        &script,
+        r#"
+import ruten_test_counter as counter
+counter.bump()
+counter.bump()
+counter.bump()
+counter.value = 10
+print(counter.count, counter.get())
+"#,
+    )
+    .expect("failed to write script");
+
+    // both backends, since each keeps a script's globals its own way
+    for extra in [&[][..], &["--vm"][..]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ruten"))
+            .args(extra)
+            .arg(&script)
+            .output()
+            .expect("failed to execute");
+        assert!(output.status.success());
+        assert_eq!(stdout_of(&output), "3 10\n");
+    }
+    fs::remove_file(&module).ok();
+    fs::remove_file(&script).ok();
+}
+
+#[test]
+fn test_import_aliases_and_from_import() {
+    let output = run_script(
+        "from_import",
+        r#"
+import math as m
+from math import sqrt, pow as power
+print(m.sqrt(9), sqrt(16), power(2, 3))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "3 4 8\n");
+}
+
+#[test]
+fn test_closures_share_their_scopes() {
+    let output = run_script(
+        "closures",
+        r#"
+def make_counter():
+    count = 0
+    def increment():
+        nonlocal count
+        count = count + 1
+        return count
+    return increment
+
+counter = make_counter()
+counter()
+print(counter())
+
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+print(fib(10))
+
+total = 0
+def add(n):
+    global total
+    total = total + n
+
+def read_later():
+    return later
+
+later = "defined afterwards"
+add(4)
+print(total, read_later())
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "2\n55\n4 defined afterwards\n");
+}
+
+#[test]
+fn test_break_and_continue_outside_loop_are_rejected() {
+    let output = run_script("top_level_break", "print(1)\nbreak\nprint(2)\n");
+    assert!(!output.status.success());
+    assert_eq!(stdout_of(&output), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("syntax error: 'break' outside loop"));
+
+    let output = run_script(
+        "continue_in_function",
+        "while True:\n    def skip():\n        continue\n    break\n",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("syntax error: 'continue' outside loop"));
+}
+
+#[test]
+fn test_nonlocal_without_binding_is_rejected() {
+    // checked when the script is parsed, even if the function never runs
+    let output = run_script("unbound_nonlocal", "print(1)\ndef f():\n    nonlocal z\n");
+    assert!(!output.status.success());
+    assert_eq!(stdout_of(&output), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("syntax error: no binding for nonlocal 'z' found"));
+
+    // bound further down the enclosing function is fine
+    let output = run_script(
+        "late_nonlocal",
+        "def outer():\n    def inner():\n        nonlocal n\n        n = 2\n    n = 1\n    inner()\n    return n\nprint(outer())\n",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "2\n");
+}
+
+#[test]
+fn test_runaway_recursion_raises() {
+    let output = run_script(
+        "recursion",
+        r#"
+def depth(n):
+    if n == 0:
+        return 0
+    return 1 + depth(n - 1)
+
+print(depth(900))
+try:
+    depth(100000)
+except RuntimeError as e:
+    print(e.message)
+
+def forever(n):
+    return forever(n + 1)
+forever(0)
+"#,
+    );
+    assert!(!output.status.success());
+    assert_eq!(stdout_of(&output), "900\nmaximum recursion depth exceeded\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("runtime error: maximum recursion depth exceeded"));
+    assert!(stderr.contains("[previous line repeated 998 more times]"));
+}
+
+#[test]
+fn test_integers_are_exact() {
+    let output = run_script(
+        "integers",
+        r#"
+import math
+print(2 ** 64, 9223372036854775807 + 1, math.fibonacci(90))
+print(-7 // 2, -7 % 3, 7 / 2, 1 + 0.5)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "18446744073709551616 9223372036854775808 2880067194370816120\n-4 2 3.5 1.5\n"
+    );
+}
+
+#[test]
+fn test_ints_and_floats_compare_and_divide_exactly() {
+    let output = run_script(
+        "exact_mixed",
+        r#"
+big = 2 ** 53 + 1
+print(big != 2.0 ** 53, big > 2.0 ** 53, big <= 2.0 ** 53)
+print(sorted([big, 2.0 ** 53])[1] == big)
+print(2 ** 10000 / 2 ** 9999)
+for compute in [lambda: 0 ** -1, lambda: 2 ** 2000 / 3]:
+    try:
+        compute()
+    except RuntimeError as e:
+        print(e.message)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "true true false\ntrue\n2\ndivision by zero\nint division result too large for a float\n"
+    );
+}
+
+#[test]
+fn test_and_or_accept_number_operands() {
+    let output = run_script(
+        "number_logic",
+        r#"
+count = 3
+total = 0
+print(1 or 2, 1.5 and 2.5, 0 and 1, 0.0 or 2 ** 70)
+if count and total:
+    print("both")
+elif count or total:
+    print("either")
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "true true false true\neither\n");
+}
+
+#[test]
+fn test_numeric_literal_formats() {
+    let output = run_script("literals", "print(0xff, 0o755, 0b1010, 1_000_000, 1e3, 2.5e-1)\n");
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "255 493 10 1000000 1000 0.25\n");
+
+    let output = run_script("bad_literal", "x = 1\ny = 0o78\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid number '0o78': '8' is not a valid octal digit"));
+    assert!(stderr.contains("2 | y = 0o78\n  |     ^^^^"));
+}
+
+#[test]
+fn test_fstrings_raw_and_triple_quoted_strings() {
+    let output = run_script(
+        "fstrings",
+        r#"
+price = 4
+print(f"total: {price * 2.5:.2f} ({price:03}) {{x}}")
+print(r"\d+", """two
+lines""")
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "total: 10.00 (004) {x}\n\\d+ two\nlines\n");
+}
+
+#[test]
+fn test_slicing_lists_and_strings() {
+    let output = run_script(
+        "slices",
+        r#"
+xs = [0, 1, 2, 3, 4, 5]
+print(xs[1:4], xs[::-2], xs[-2:], xs[-100:2], xs[4:100])
+print("ruten"[1:3], "ruten"[::-1])
+word = "héllo"
+print(len(word), word[len(word) - 1], word[1:3])
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "[1, 2, 3] [5, 3, 1] [4, 5] [0, 1] [4, 5]\nut netur\n5 o él\n"
+    );
+}
+
+#[test]
+fn test_tuples_and_destructuring() {
+    let output = run_script(
+        "tuples",
+        r#"
+a, b = 1, 2
+a, b = b, a
+first, *rest, (x, y) = [0, 1, 2, (3, 4)]
+print(a, b, first, rest, x, y, (5,))
+for key, value in {"k": "v"}.items():
+    print(key, value)
+p, q = [1, 2, 3]
+"#,
+    );
+    assert!(!output.status.success());
+    assert_eq!(stdout_of(&output), "2 1 0 [1, 2] 3 4 (5,)\nk v\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("too many values to unpack (expected 2)"));
+}
+
+#[test]
+fn test_comparison_operators_agree_with_sorted() {
+    let output = run_script(
+        "tuple_order",
+        r#"
+pairs = [(1, 3), (1, 2), (0, 9)]
+print((1, 2) < (1, 3), (1,) < (1, 0), sorted(pairs) == [(0, 9), (1, 2), (1, 3)])
+print("apple" < "banana", float("nan") < 1, (1, 2) + (3,))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "true true true\ntrue false (1, 2, 3)\n");
+}
+
+#[test]
+fn test_list_dict_and_set_comprehensions() {
+    let output = run_script(
+        "comprehensions",
+        r#"
+x = "kept"
+evens = [x * 10 for x in range(6) if x % 2 == 0]
+lengths = {word: len(word) for word in ["ab", "abc"]}
+print(evens, x, lengths["abc"], [(a, b) for a in [1, 2] for b in [a]])
+print({x % 2 for x in range(5)}, x)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "[0, 20, 40] kept 3 [(1, 1), (2, 2)]\n{0, 1} kept\n");
+}
+
+#[test]
+fn test_dicts_are_ordered_with_hashable_keys() {
+    let output = run_script(
+        "dicts",
+        r#"
+import json
+d = {"z": 1, 1: "int", "1": "string", (0, 0): "origin"}
+d["a"] = 2
+print(d, d[1.0])
+print(json.stringify({"b": 1, "a": [None]}))
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "{'z': 1, 1: 'int', '1': 'string', (0, 0): 'origin', 'a': 2} int\n{\"b\":1,\"a\":[null]}\n"
+    );
+}
+
+#[test]
+fn test_json_keeps_keys_apart_and_ints_exact() {
+    let output = run_script(
+        "json_exact",
+        r#"
+import json
+try:
+    json.stringify({1: 2, "1": 3})
+except RuntimeError as e:
+    print(e.message)
+print(json.stringify({"big": 2 ** 70}))
+print(json.parse("12345678901234567890123") + 1)
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "dict keys collide as the json key \"1\"\n{\"big\":1180591620717411303424}\n12345678901234567890124\n"
+    );
+}
+
+#[test]
+fn test_sets_and_set_algebra() {
+    let output = run_script(
+        "sets",
+        r#"
+a = {1, 2, 3, 3}
+b = set([3, 4])
+print(a | b, a & b, a - b, a ^ b, 2 in a, 4 not in a)
+print(a.issuperset({1, 2}), {n % 2 for n in range(5)}, set())
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "{1, 2, 3, 4} {3} {1, 2} {1, 2, 4} true true\ntrue {0, 1} set()\n"
+    );
+}
+
+#[test]
+fn test_membership_and_structural_equality() {
+    let output = run_script(
+        "membership",
+        r#"
+import testing
+print("ell" in "hello", 2.0 in [1, 2], "a" in {"a": 1}, 3 not in (1, 2))
+print([1, {"a": [2]}] == [1, {"a": [2]}], {"a": 1, "b": 2} == {"b": 2, "a": 1}, 1 == None, (1,) != [1])
+testing.assert_equal({"xs": [1, 2]}, {"xs": [1.0, 2]})
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "true true true true\ntrue true false true\n");
+}
+
+#[test]
+fn test_natives_and_methods_equal_themselves() {
+    let output = run_script(
+        "native_equality",
+        r#"
+xs = [1]
+print(len == len, [len] == [len], len in [len], len == print)
+print(xs.append == xs.append, xs.append == [1].append)
+d = {float("nan"): "found"}
+print(d[d.keys()[0]])
+"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "true true true false\ntrue false\nfound\n");
+}
+
+#[test]
+fn test_dump_ast_shows_optimized_program() {
+    let path = std::env::temp_dir().join("ruten_test_dump_ast.rtn");
+    fs::write(
+        &path,
+        r#"
+x = 2 * 3 + 1
+if False:
+    print("never")
+else:
+    print("hello, " + "world", x / 0)
+
+def f():
+    return 1
+    print("unreachable")
+
+def huge():
+    return 7 ** 3000000 + 2 ** 64
+"#,
+    )
+    .expect("failed to write script");
+    let output = Command::new(env!("CARGO_BIN_EXE_ruten"))
+        .arg("--dump-ast")
+        .arg(&path)
+        .output()
+        .expect("failed to execute");
+    fs::remove_file(&path).ok();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "x = 7\nprint(\"hello, world\", (x / 0))\ndef f():\n    return 1\n\
+         def huge():\n    return ((7 ** 3000000) + 18446744073709551616)\n"
+    );
+}
+
+#[test]
+fn test_dump_ast_output_parses_back() {
+    let path = std::env::temp_dir().join("ruten_test_dump_strings.rtn");
+    fs::write(
+        &path,
+        r#"
+d = {"k": "v"}
+print(f"{d["k"]:>3} {{x}} it's\n", "tab\t\"q\"")
+"#,
+    )
+    .expect("failed to write script");
+    let dump = |path: &std::path::Path| {
+        Command::new(env!("CARGO_BIN_EXE_ruten"))
+            .arg("--dump-ast")
+            .arg(path)
+            .output()
+            .expect("failed to execute")
+    };
+    let output = dump(&path);
+    assert!(output.status.success());
+    let printed = stdout_of(&output);
+    assert_eq!(
+        printed,
+        r#"d = {"k": "v"}
+print(f'{d["k"]:>3} {{x}} it\'s\n', "tab\t\"q\"")
+"#
+    );
+
+    // the printed program reads back as the same program
+    fs::write(&path, &printed).expect("failed to write script");
+    let output = dump(&path);
+    fs::remove_file(&path).ok();
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), printed);
+}
+
+#[test]
+fn test_dump_ast_keeps_floats_and_dead_bindings() {
+    let path = std::env::temp_dir().join("ruten_test_dump_floats.rtn");
+    fs::write(
+        &path,
+        r#"
+y = 1.0
+z = 1e400
+
+def f():
+    def g():
+        nonlocal v
+        v = 3
+    g()
+    if False:
+        v = 0
+    return v
+"#,
+    )
+    .expect("failed to write script");
+    let output = Command::new(env!("CARGO_BIN_EXE_ruten"))
+        .arg("--dump-ast")
+        .arg(&path)
+        .output()
+        .expect("failed to execute");
+    fs::remove_file(&path).ok();
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "y = 1.0\nz = 1e400\ndef f():\n    def g():\n        nonlocal v\n        v = 3\n    g()\n    \
+         if False:\n        v = 0\n    return v\n"
+    );
+}
+
+#[test]
+fn test_vm_matches_tree_walker() {
+    // every fixture must print the same output and errors under both backends
+    let mut scripts: Vec<_> = ["tests/scripts", "benches/scripts"]
+        .iter()
+        .flat_map(|dir| fs::read_dir(dir).expect("missing scripts directory"))
+        .map(|entry| entry.expect("unreadable entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rtn"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty());
+
+    for script in scripts {
+        let run = |extra: &[&str]| {
+            Command::new(env!("CARGO_BIN_EXE_ruten"))
+                .args(extra)
+                .arg(&script)
+                .output()
+                .expect("failed to execute")
+        };
+        let tree = run(&[]);
+        let vm = run(&["--vm"]);
+        assert_eq!(stdout_of(&tree), stdout_of(&vm), "stdout differs for {}", script.display());
+        assert_eq!(
+            String::from_utf8_lossy(&tree.stderr),
+            String::from_utf8_lossy(&vm.stderr),
+            "stderr differs for {}",
+            script.display()
+        );
+        assert_eq!(tree.status.code(), vm.status.code(), "status differs for {}", script.display());
+    }
+}